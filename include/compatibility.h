@@ -36,14 +36,14 @@
  * Introduces ReadOnlyTree boolean
  */
 #if PG_VERSION_NUM >= 140000
-#define _PU_HOOK \
-	static void PU_hook(PlannedStmt *pstmt, const char *queryString, bool ReadOnlyTree, \
+#define _PROCESS_UTILITY_HOOK(hookname) \
+	static void hookname(PlannedStmt *pstmt, const char *queryString, bool ReadOnlyTree, \
 						ProcessUtilityContext context, ParamListInfo params, \
 						QueryEnvironment *queryEnv, \
 						DestReceiver *dest, QueryCompletion *qc)
 
-#define _prev_hook \
-	prev_hook(pstmt, queryString, ReadOnlyTree, context, params, queryEnv, dest, qc)
+#define _prev_process_utility_hook(prevhook) \
+	prevhook(pstmt, queryString, ReadOnlyTree, context, params, queryEnv, dest, qc)
 
 #define _standard_ProcessUtility \
 	standard_ProcessUtility(pstmt, queryString, ReadOnlyTree, context, params, queryEnv, dest, qc)
@@ -59,15 +59,15 @@
  * Introduces QueryCompletion struct
  */
 #if PG_VERSION_NUM >= 130000
-#ifndef _PU_HOOK
-#define _PU_HOOK \
-	static void PU_hook(PlannedStmt *pstmt, const char *queryString, \
+#ifndef _PROCESS_UTILITY_HOOK
+#define _PROCESS_UTILITY_HOOK(hookname) \
+	static void hookname(PlannedStmt *pstmt, const char *queryString, \
 						ProcessUtilityContext context, ParamListInfo params, \
 						QueryEnvironment *queryEnv, \
 						DestReceiver *dest, QueryCompletion *qc)
 
-#define _prev_hook \
-	prev_hook(pstmt, queryString, context, params, queryEnv, dest, qc)
+#define _prev_process_utility_hook(prevhook) \
+	prevhook(pstmt, queryString, context, params, queryEnv, dest, qc)
 
 #define _standard_ProcessUtility \
 	standard_ProcessUtility(pstmt, queryString,	context, params, queryEnv, dest, qc)
@@ -82,21 +82,24 @@
  * - Introduces varlena.h
  */
 #if PG_VERSION_NUM >= 100000
-#ifndef _PU_HOOK
-#define _PU_HOOK \
-	static void PU_hook(PlannedStmt *pstmt, const char *queryString, \
+#ifndef _PROCESS_UTILITY_HOOK
+#define _PROCESS_UTILITY_HOOK(hookname) \
+	static void hookname(PlannedStmt *pstmt, const char *queryString, \
 						ProcessUtilityContext context, ParamListInfo params, \
 						QueryEnvironment *queryEnv, \
 						DestReceiver *dest, char *completionTag)
 
-#define _prev_hook \
-	prev_hook(pstmt, queryString, context, params, queryEnv, dest, completionTag)
+#define _prev_process_utility_hook(prevhook) \
+	prevhook(pstmt, queryString, context, params, queryEnv, dest, completionTag)
 
 #define _standard_ProcessUtility \
 	standard_ProcessUtility(pstmt, queryString, context, params, queryEnv, dest, completionTag)
 
 #endif
 
+#define _PU_HOOK _PROCESS_UTILITY_HOOK(PU_hook)
+#define _prev_hook _prev_process_utility_hook(prev_hook)
+
 #define pu_parsetree ((Node *) pstmt->utilityStmt)
 
 #endif							/* 10+ */
@@ -217,15 +220,37 @@
 #define SPI_NUMVALS(tuptable)	(tuptable->numvals)
 #endif
 
+/*
+ * PostgreSQL 13 changed command tags from strings to the CommandTag enum.
+ */
+#if PG_VERSION_NUM < 130000
+#define GET_COMMAND_TAG_NAME(parsetree)	CreateCommandTag(parsetree)
+#else
+#define GET_COMMAND_TAG_NAME(parsetree)	GetCommandTagName(CreateCommandTag(parsetree))
+#endif
+
 /* prior to pg12 some additional missing macros */
 #if PG_VERSION_NUM < 120000
 #define table_open(r,l)		heap_open(r,l)
 #define table_close(r,l)	heap_close(r,l)
+#define TableScanDesc		HeapScanDesc
+#define table_beginscan_catalog(r,n,k)	heap_beginscan_catalog(r,n,k)
+#define table_endscan(s)	heap_endscan(s)
 #ifndef Anum_pg_extension_oid
 #define Anum_pg_extension_oid	ObjectIdAttributeNumber
 #endif
 #endif
 
+/*
+ * prior to pg12 pglz_decompress did not take check_complete and always checked
+ * that the whole input was consumed
+ */
+#if PG_VERSION_NUM < 120000
+#include "common/pg_lzcompress.h"
+#define pglz_decompress(source, slen, dest, rawsize, check_complete) \
+	pglz_decompress(source, slen, dest, rawsize)
+#endif
+
 /*
  * a8671545 introduced a syntax change for ereport et al. that was backpatched
  * to PostgreSQL 12, but not all. This overwrites those macros for compatibility
@@ -556,7 +581,9 @@ CastCreate(Oid sourcetypeid, Oid targettypeid, Oid funcid, char castcontext,
 #define PG_EXTENSION_OWNERCHECK(ExtensionOid, UserId) pg_extension_ownercheck(ExtensionOid, UserId)
 #define PG_NAMESPACE_ACLCHECK(NamespaceOid, UserId, Operation) pg_namespace_aclcheck(NamespaceOid, UserId, Operation)
 #define PG_PROC_OWNERCHECK(ProcOid, UserId) pg_proc_ownercheck(ProcOid, UserId)
+#define PG_PROC_ACLCHECK(ProcOid, UserId, Operation) pg_proc_aclcheck(ProcOid, UserId, Operation)
 #define PG_TYPE_OWNERCHECK(TypeOid, UserId) pg_type_ownercheck(TypeOid, UserId)
+#define PG_OPER_OWNERCHECK(OperOid, UserId) pg_oper_ownercheck(OperOid, UserId)
 #define STRING_TO_QUALIFIED_NAME_LIST(string) stringToQualifiedNameList(string)
 #define CHECK_CAN_SET_ROLE(member, role) check_is_member_of_role(member, role)
 #else
@@ -564,11 +591,56 @@ CastCreate(Oid sourcetypeid, Oid targettypeid, Oid funcid, char castcontext,
 #define PG_EXTENSION_OWNERCHECK(ExtensionOid, UserId) object_ownercheck(ExtensionRelationId, ExtensionOid, UserId)
 #define PG_NAMESPACE_ACLCHECK(NamespaceOid, UserId, Operation) object_aclcheck(NamespaceRelationId, NamespaceOid, UserId, Operation)
 #define PG_PROC_OWNERCHECK(ProcOid, UserId) object_ownercheck(ProcedureRelationId, ProcOid, UserId)
+#define PG_PROC_ACLCHECK(ProcOid, UserId, Operation) object_aclcheck(ProcedureRelationId, ProcOid, UserId, Operation)
 #define PG_TYPE_OWNERCHECK(TypeOid, UserId) object_ownercheck(TypeRelationId, TypeOid, UserId)
+#define PG_OPER_OWNERCHECK(OperOid, UserId) object_ownercheck(OperatorRelationId, OperOid, UserId)
 #define STRING_TO_QUALIFIED_NAME_LIST(string) stringToQualifiedNameList(string, NULL)
 #define CHECK_CAN_SET_ROLE(member, role) check_can_set_role(member, role)
 #endif
 
+/*
+ * PostgreSQL version 15+
+ *
+ * DefineRange takes a ParseState so that errors can report a position
+ */
+#if PG_VERSION_NUM >= 150000
+#define DEFINE_RANGE(pstate, stmt) DefineRange(pstate, stmt)
+#else
+#define DEFINE_RANGE(pstate, stmt) DefineRange(stmt)
+#endif
+
+/*
+ * PostgreSQL version 12+
+ *
+ * DefineAggregate takes a replace flag to support CREATE OR REPLACE AGGREGATE
+ */
+#if PG_VERSION_NUM >= 120000
+#define DEFINE_AGGREGATE(pstate, name, args, oldstyle, parameters) \
+	DefineAggregate(pstate, name, args, oldstyle, parameters, false)
+#else
+#define DEFINE_AGGREGATE(pstate, name, args, oldstyle, parameters) \
+	DefineAggregate(pstate, name, args, oldstyle, parameters)
+#endif
+
+/*
+ * PostgreSQL version 14+
+ *
+ * post_parse_analyze_hook takes a JumbleState
+ */
+#if PG_VERSION_NUM >= 140000
+#define _POST_PARSE_ANALYZE_HOOK(hookname) \
+	static void hookname(ParseState *pstate, Query *query, JumbleState *jstate)
+
+#define _prev_post_parse_analyze_hook \
+	prev_post_parse_analyze_hook(pstate, query, jstate)
+#else
+#define _POST_PARSE_ANALYZE_HOOK(hookname) \
+	static void hookname(ParseState *pstate, Query *query)
+
+#define _prev_post_parse_analyze_hook \
+	prev_post_parse_analyze_hook(pstate, query)
+#endif
+
 /*
  * PostgreSQL version 17+
  *
@@ -32,9 +32,19 @@
 
 #define TLE_BASE_TYPE_IN         "pg_tle_base_type_in"
 #define TLE_BASE_TYPE_OUT        "pg_tle_base_type_out"
+#define TLE_BASE_TYPE_RECV       "pg_tle_base_type_recv"
+#define TLE_BASE_TYPE_SEND       "pg_tle_base_type_send"
+#define TLE_BASE_TYPE_TYPMOD_IN  "pg_tle_base_type_typmod_in"
+#define TLE_BASE_TYPE_TYPMOD_OUT "pg_tle_base_type_typmod_out"
+#define TLE_BASE_TYPE_ANALYZE    "pg_tle_base_type_analyze"
 #define TLE_OPERATOR_FUNC        "pg_tle_operator_func"
 #define TLE_INPUT_FUNC_STR       "input"
 #define TLE_OUTPUT_FUNC_STR      "output"
+#define TLE_RECEIVE_FUNC_STR     "receive"
+#define TLE_SEND_FUNC_STR        "send"
+#define TLE_TYPMOD_IN_FUNC_STR   "typmod_in"
+#define TLE_TYPMOD_OUT_FUNC_STR  "typmod_out"
+#define TLE_ANALYZE_FUNC_STR     "analyze"
 
 /*
  * TLE_BASE_TYPE_SIZE_LIMIT is the maximum allowed size of pg_tle type.
@@ -48,6 +58,15 @@
  */
 #define TLE_REQUIRES_LIMIT        1024
 
+/*
+ * Scripts of at least this many bytes are stored compressed, when that makes
+ * them smaller. Smaller scripts are stored as plain text so that they stay
+ * readable in the catalog.
+ */
+#define TLE_SCRIPT_COMPRESS_MIN_SIZE 2048
+#define TLE_COMPRESSED_SCRIPT_PREFIX "-- pg_tle compressed script of "
+#define TLE_COMPRESSED_SCRIPT_SUFFIX " bytes\n"
+
 /* general PostgreSQL names */
 #define PG_CTLG_SCHEMA            "pg_catalog"
 
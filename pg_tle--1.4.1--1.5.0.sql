@@ -0,0 +1,1404 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+-- complain if script is sourced in psql, rather than via CREATE EXTENSION
+\echo Use "CREATE EXTENSION pg_tle" to load this file. \quit
+
+CREATE FUNCTION pgtle.create_enum_type
+(
+  typenamespace regnamespace,
+  typename name,
+  labels text[]
+)
+RETURNS void
+SET search_path TO 'pgtle'
+STRICT
+AS 'MODULE_PATHNAME', 'pg_tle_create_enum_type'
+LANGUAGE C;
+
+CREATE FUNCTION pgtle.create_enum_type_if_not_exists
+(
+  typenamespace regnamespace,
+  typename name,
+  labels text[]
+)
+RETURNS boolean
+SET search_path TO 'pgtle'
+STRICT
+AS 'MODULE_PATHNAME', 'pg_tle_create_enum_type_if_not_exists'
+LANGUAGE C;
+
+CREATE FUNCTION pgtle.add_enum_label
+(
+  typenamespace regnamespace,
+  typename name,
+  label text,
+  before_label text default NULL,
+  after_label text default NULL
+)
+RETURNS void
+SET search_path TO 'pgtle'
+AS 'MODULE_PATHNAME', 'pg_tle_add_enum_label'
+LANGUAGE C;
+
+CREATE FUNCTION pgtle.add_enum_label_if_not_exists
+(
+  typenamespace regnamespace,
+  typename name,
+  label text,
+  before_label text default NULL,
+  after_label text default NULL
+)
+RETURNS boolean
+SET search_path TO 'pgtle'
+AS 'MODULE_PATHNAME', 'pg_tle_add_enum_label_if_not_exists'
+LANGUAGE C;
+
+REVOKE EXECUTE ON FUNCTION pgtle.create_enum_type
+(
+  typenamespace regnamespace,
+  typename name,
+  labels text[]
+) FROM PUBLIC;
+
+REVOKE EXECUTE ON FUNCTION pgtle.create_enum_type_if_not_exists
+(
+  typenamespace regnamespace,
+  typename name,
+  labels text[]
+) FROM PUBLIC;
+
+REVOKE EXECUTE ON FUNCTION pgtle.add_enum_label
+(
+  typenamespace regnamespace,
+  typename name,
+  label text,
+  before_label text,
+  after_label text
+) FROM PUBLIC;
+
+REVOKE EXECUTE ON FUNCTION pgtle.add_enum_label_if_not_exists
+(
+  typenamespace regnamespace,
+  typename name,
+  label text,
+  before_label text,
+  after_label text
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.create_enum_type
+(
+  typenamespace regnamespace,
+  typename name,
+  labels text[]
+) TO pgtle_admin;
+
+GRANT EXECUTE ON FUNCTION pgtle.create_enum_type_if_not_exists
+(
+  typenamespace regnamespace,
+  typename name,
+  labels text[]
+) TO pgtle_admin;
+
+GRANT EXECUTE ON FUNCTION pgtle.add_enum_label
+(
+  typenamespace regnamespace,
+  typename name,
+  label text,
+  before_label text,
+  after_label text
+) TO pgtle_admin;
+
+GRANT EXECUTE ON FUNCTION pgtle.add_enum_label_if_not_exists
+(
+  typenamespace regnamespace,
+  typename name,
+  label text,
+  before_label text,
+  after_label text
+) TO pgtle_admin;
+
+CREATE FUNCTION pgtle.create_range_type
+(
+  typenamespace regnamespace,
+  typename name,
+  subtype regtype,
+  subtype_diff regprocedure default NULL,
+  subtype_opclass text default NULL
+)
+RETURNS void
+SET search_path TO 'pgtle'
+AS 'MODULE_PATHNAME', 'pg_tle_create_range_type'
+LANGUAGE C;
+
+CREATE FUNCTION pgtle.create_range_type_if_not_exists
+(
+  typenamespace regnamespace,
+  typename name,
+  subtype regtype,
+  subtype_diff regprocedure default NULL,
+  subtype_opclass text default NULL
+)
+RETURNS boolean
+SET search_path TO 'pgtle'
+AS 'MODULE_PATHNAME', 'pg_tle_create_range_type_if_not_exists'
+LANGUAGE C;
+
+REVOKE EXECUTE ON FUNCTION pgtle.create_range_type
+(
+  typenamespace regnamespace,
+  typename name,
+  subtype regtype,
+  subtype_diff regprocedure,
+  subtype_opclass text
+) FROM PUBLIC;
+
+REVOKE EXECUTE ON FUNCTION pgtle.create_range_type_if_not_exists
+(
+  typenamespace regnamespace,
+  typename name,
+  subtype regtype,
+  subtype_diff regprocedure,
+  subtype_opclass text
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.create_range_type
+(
+  typenamespace regnamespace,
+  typename name,
+  subtype regtype,
+  subtype_diff regprocedure,
+  subtype_opclass text
+) TO pgtle_admin;
+
+GRANT EXECUTE ON FUNCTION pgtle.create_range_type_if_not_exists
+(
+  typenamespace regnamespace,
+  typename name,
+  subtype regtype,
+  subtype_diff regprocedure,
+  subtype_opclass text
+) TO pgtle_admin;
+
+CREATE FUNCTION pgtle.create_operator
+(
+  opnamespace regnamespace,
+  opname text,
+  leftarg regtype,
+  rightarg regtype,
+  opfunc regprocedure,
+  commutator text default NULL,
+  negator text default NULL,
+  restrict_func regproc default NULL,
+  join_func regproc default NULL,
+  hashes boolean default false,
+  merges boolean default false
+)
+RETURNS void
+SET search_path TO 'pgtle'
+AS 'MODULE_PATHNAME', 'pg_tle_create_operator'
+LANGUAGE C;
+
+CREATE FUNCTION pgtle.create_operator_class
+(
+  opcnamespace regnamespace,
+  opcname name,
+  typename regtype,
+  method text,
+  operators regoperator[],
+  functions regprocedure[],
+  is_default boolean default true
+)
+RETURNS void
+SET search_path TO 'pgtle'
+STRICT
+AS 'MODULE_PATHNAME', 'pg_tle_create_operator_class'
+LANGUAGE C;
+
+REVOKE EXECUTE ON FUNCTION pgtle.create_operator
+(
+  opnamespace regnamespace,
+  opname text,
+  leftarg regtype,
+  rightarg regtype,
+  opfunc regprocedure,
+  commutator text,
+  negator text,
+  restrict_func regproc,
+  join_func regproc,
+  hashes boolean,
+  merges boolean
+) FROM PUBLIC;
+
+REVOKE EXECUTE ON FUNCTION pgtle.create_operator_class
+(
+  opcnamespace regnamespace,
+  opcname name,
+  typename regtype,
+  method text,
+  operators regoperator[],
+  functions regprocedure[],
+  is_default boolean
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.create_operator
+(
+  opnamespace regnamespace,
+  opname text,
+  leftarg regtype,
+  rightarg regtype,
+  opfunc regprocedure,
+  commutator text,
+  negator text,
+  restrict_func regproc,
+  join_func regproc,
+  hashes boolean,
+  merges boolean
+) TO pgtle_admin;
+
+GRANT EXECUTE ON FUNCTION pgtle.create_operator_class
+(
+  opcnamespace regnamespace,
+  opcname name,
+  typename regtype,
+  method text,
+  operators regoperator[],
+  functions regprocedure[],
+  is_default boolean
+) TO pgtle_admin;
+
+CREATE FUNCTION pgtle.create_cast
+(
+  source regtype,
+  target regtype,
+  castfunc regprocedure,
+  context text default 'explicit'
+)
+RETURNS void
+SET search_path TO 'pgtle'
+STRICT
+AS 'MODULE_PATHNAME', 'pg_tle_create_cast'
+LANGUAGE C;
+
+CREATE FUNCTION pgtle.create_cast_if_not_exists
+(
+  source regtype,
+  target regtype,
+  castfunc regprocedure,
+  context text default 'explicit'
+)
+RETURNS boolean
+SET search_path TO 'pgtle'
+STRICT
+AS 'MODULE_PATHNAME', 'pg_tle_create_cast_if_not_exists'
+LANGUAGE C;
+
+REVOKE EXECUTE ON FUNCTION pgtle.create_cast
+(
+  source regtype,
+  target regtype,
+  castfunc regprocedure,
+  context text
+) FROM PUBLIC;
+
+REVOKE EXECUTE ON FUNCTION pgtle.create_cast_if_not_exists
+(
+  source regtype,
+  target regtype,
+  castfunc regprocedure,
+  context text
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.create_cast
+(
+  source regtype,
+  target regtype,
+  castfunc regprocedure,
+  context text
+) TO pgtle_admin;
+
+GRANT EXECUTE ON FUNCTION pgtle.create_cast_if_not_exists
+(
+  source regtype,
+  target regtype,
+  castfunc regprocedure,
+  context text
+) TO pgtle_admin;
+
+CREATE FUNCTION pgtle.set_base_type_binary_io
+(
+  typenamespace regnamespace,
+  typename name,
+  recvfunc regprocedure,
+  sendfunc regprocedure
+)
+RETURNS void
+SET search_path TO 'pgtle'
+STRICT
+AS 'MODULE_PATHNAME', 'pg_tle_set_base_type_binary_io'
+LANGUAGE C;
+
+REVOKE EXECUTE ON FUNCTION pgtle.set_base_type_binary_io
+(
+  typenamespace regnamespace,
+  typename name,
+  recvfunc regprocedure,
+  sendfunc regprocedure
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.set_base_type_binary_io
+(
+  typenamespace regnamespace,
+  typename name,
+  recvfunc regprocedure,
+  sendfunc regprocedure
+) TO pgtle_admin;
+
+CREATE FUNCTION pgtle.set_base_type_typmod_io
+(
+  typenamespace regnamespace,
+  typename name,
+  typmodinfunc regprocedure,
+  typmodoutfunc regprocedure
+)
+RETURNS void
+SET search_path TO 'pgtle'
+STRICT
+AS 'MODULE_PATHNAME', 'pg_tle_set_base_type_typmod_io'
+LANGUAGE C;
+
+REVOKE EXECUTE ON FUNCTION pgtle.set_base_type_typmod_io
+(
+  typenamespace regnamespace,
+  typename name,
+  typmodinfunc regprocedure,
+  typmodoutfunc regprocedure
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.set_base_type_typmod_io
+(
+  typenamespace regnamespace,
+  typename name,
+  typmodinfunc regprocedure,
+  typmodoutfunc regprocedure
+) TO pgtle_admin;
+
+CREATE FUNCTION pgtle.create_aggregate
+(
+  aggnamespace regnamespace,
+  aggname name,
+  argtypes regtype[],
+  sfunc regprocedure,
+  stype regtype,
+  finalfunc regprocedure default NULL,
+  combinefunc regprocedure default NULL,
+  initcond text default NULL,
+  parallel text default 'unsafe'
+)
+RETURNS void
+SET search_path TO 'pgtle'
+AS 'MODULE_PATHNAME', 'pg_tle_create_aggregate'
+LANGUAGE C;
+
+REVOKE EXECUTE ON FUNCTION pgtle.create_aggregate
+(
+  aggnamespace regnamespace,
+  aggname name,
+  argtypes regtype[],
+  sfunc regprocedure,
+  stype regtype,
+  finalfunc regprocedure,
+  combinefunc regprocedure,
+  initcond text,
+  parallel text
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.create_aggregate
+(
+  aggnamespace regnamespace,
+  aggname name,
+  argtypes regtype[],
+  sfunc regprocedure,
+  stype regtype,
+  finalfunc regprocedure,
+  combinefunc regprocedure,
+  initcond text,
+  parallel text
+) TO pgtle_admin;
+
+CREATE TABLE pgtle.custom_guc
+(
+  name text PRIMARY KEY,
+  type text NOT NULL,
+  default_value text NOT NULL,
+  description text NOT NULL,
+  context text NOT NULL
+);
+
+SELECT pg_catalog.pg_extension_config_dump('pgtle.custom_guc', '');
+
+GRANT SELECT ON pgtle.custom_guc TO PUBLIC;
+
+CREATE FUNCTION pgtle.define_custom_guc
+(
+  name text,
+  type text,
+  default_value text,
+  description text,
+  context text default 'user'
+)
+RETURNS void
+SET search_path TO 'pgtle'
+STRICT
+AS 'MODULE_PATHNAME', 'pg_tle_define_custom_guc'
+LANGUAGE C;
+
+REVOKE EXECUTE ON FUNCTION pgtle.define_custom_guc
+(
+  name text,
+  type text,
+  default_value text,
+  description text,
+  context text
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.define_custom_guc
+(
+  name text,
+  type text,
+  default_value text,
+  description text,
+  context text
+) TO pgtle_admin;
+
+ALTER TYPE pgtle.pg_tle_features ADD VALUE 'login';
+ALTER TYPE pgtle.pg_tle_features ADD VALUE 'ddl_command_start';
+ALTER TYPE pgtle.pg_tle_features ADD VALUE 'ddl_command_end';
+
+CREATE TABLE pgtle.scheduled_job
+(
+  name text PRIMARY KEY,
+  schedule interval NOT NULL,
+  schema_name text NOT NULL,
+  proname text NOT NULL,
+  run_as regrole NOT NULL,
+  next_run timestamptz NOT NULL,
+  last_run timestamptz,
+  last_status text,
+  last_error text
+);
+
+SELECT pg_catalog.pg_extension_config_dump('pgtle.scheduled_job', '');
+
+GRANT SELECT ON pgtle.scheduled_job TO PUBLIC;
+
+CREATE FUNCTION pgtle.schedule_job
+(
+  name text,
+  schedule interval,
+  function regproc
+)
+RETURNS void
+SET search_path TO 'pgtle'
+STRICT
+AS 'MODULE_PATHNAME', 'pg_tle_schedule_job'
+LANGUAGE C;
+
+REVOKE EXECUTE ON FUNCTION pgtle.schedule_job
+(
+  name text,
+  schedule interval,
+  function regproc
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.schedule_job
+(
+  name text,
+  schedule interval,
+  function regproc
+) TO pgtle_admin;
+
+CREATE FUNCTION pgtle.unschedule_job
+(
+  name text
+)
+RETURNS void
+SET search_path TO 'pgtle'
+STRICT
+AS 'MODULE_PATHNAME', 'pg_tle_unschedule_job'
+LANGUAGE C;
+
+REVOKE EXECUTE ON FUNCTION pgtle.unschedule_job
+(
+  name text
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.unschedule_job
+(
+  name text
+) TO pgtle_admin;
+
+-- Password policy helpers that passcheck functions can call, or that can be
+-- registered directly in the case of pgtle.passcheck_policy. The checks can
+-- only be applied to plaintext passwords; passwords that were hashed by the
+-- client are accepted unless pgtle.passcheck_require_plaintext is called.
+CREATE FUNCTION pgtle.passcheck_require_plaintext
+(
+  password_type pgtle.password_types
+)
+RETURNS void
+SET search_path TO 'pgtle'
+LANGUAGE plpgsql
+AS $_pgtleie_$
+BEGIN
+  IF password_type OPERATOR(pg_catalog.<>) 'PASSWORD_TYPE_PLAINTEXT' THEN
+    RAISE EXCEPTION 'password must not be hashed by the client'
+      USING HINT = 'Set the password with a plaintext PASSWORD clause so that it can be checked.';
+  END IF;
+END;
+$_pgtleie_$;
+
+CREATE FUNCTION pgtle.passcheck_min_length
+(
+  password text,
+  password_type pgtle.password_types,
+  min_length integer
+)
+RETURNS void
+SET search_path TO 'pgtle'
+LANGUAGE plpgsql
+AS $_pgtleie_$
+BEGIN
+  IF password_type OPERATOR(pg_catalog.=) 'PASSWORD_TYPE_PLAINTEXT' AND
+     pg_catalog.length(password) OPERATOR(pg_catalog.<) min_length THEN
+    RAISE EXCEPTION 'password must be at least % characters long', min_length;
+  END IF;
+END;
+$_pgtleie_$;
+
+CREATE FUNCTION pgtle.passcheck_character_classes
+(
+  password text,
+  password_type pgtle.password_types,
+  min_classes integer
+)
+RETURNS void
+SET search_path TO 'pgtle'
+LANGUAGE plpgsql
+AS $_pgtleie_$
+DECLARE
+  num_classes integer := 0;
+BEGIN
+  IF password_type OPERATOR(pg_catalog.<>) 'PASSWORD_TYPE_PLAINTEXT' THEN
+    RETURN;
+  END IF;
+
+  IF password OPERATOR(pg_catalog.~) '[a-z]' THEN
+    num_classes := num_classes OPERATOR(pg_catalog.+) 1;
+  END IF;
+  IF password OPERATOR(pg_catalog.~) '[A-Z]' THEN
+    num_classes := num_classes OPERATOR(pg_catalog.+) 1;
+  END IF;
+  IF password OPERATOR(pg_catalog.~) '[0-9]' THEN
+    num_classes := num_classes OPERATOR(pg_catalog.+) 1;
+  END IF;
+  IF password OPERATOR(pg_catalog.~) '[^a-zA-Z0-9]' THEN
+    num_classes := num_classes OPERATOR(pg_catalog.+) 1;
+  END IF;
+
+  IF num_classes OPERATOR(pg_catalog.<) min_classes THEN
+    RAISE EXCEPTION 'password must contain characters from at least % of the following classes: lowercase letters, uppercase letters, digits, other characters', min_classes;
+  END IF;
+END;
+$_pgtleie_$;
+
+-- Estimates the entropy of a password in bits, as its length times the
+-- logarithm of the number of characters in the classes it uses.
+CREATE FUNCTION pgtle.password_entropy
+(
+  password text
+)
+RETURNS double precision
+SET search_path TO 'pgtle'
+LANGUAGE plpgsql
+IMMUTABLE STRICT
+AS $_pgtleie_$
+DECLARE
+  pool_size integer := 0;
+BEGIN
+  IF password OPERATOR(pg_catalog.~) '[a-z]' THEN
+    pool_size := pool_size OPERATOR(pg_catalog.+) 26;
+  END IF;
+  IF password OPERATOR(pg_catalog.~) '[A-Z]' THEN
+    pool_size := pool_size OPERATOR(pg_catalog.+) 26;
+  END IF;
+  IF password OPERATOR(pg_catalog.~) '[0-9]' THEN
+    pool_size := pool_size OPERATOR(pg_catalog.+) 10;
+  END IF;
+  IF password OPERATOR(pg_catalog.~) '[^a-zA-Z0-9]' THEN
+    pool_size := pool_size OPERATOR(pg_catalog.+) 33;
+  END IF;
+
+  IF pool_size OPERATOR(pg_catalog.=) 0 THEN
+    RETURN 0;
+  END IF;
+
+  RETURN pg_catalog.length(password) OPERATOR(pg_catalog.*) pg_catalog.log(2.0, pool_size::pg_catalog.numeric)::double precision;
+END;
+$_pgtleie_$;
+
+CREATE FUNCTION pgtle.passcheck_min_entropy
+(
+  password text,
+  password_type pgtle.password_types,
+  min_bits double precision
+)
+RETURNS void
+SET search_path TO 'pgtle'
+LANGUAGE plpgsql
+AS $_pgtleie_$
+BEGIN
+  IF password_type OPERATOR(pg_catalog.=) 'PASSWORD_TYPE_PLAINTEXT' AND
+     pgtle.password_entropy(password) OPERATOR(pg_catalog.<) min_bits THEN
+    RAISE EXCEPTION 'password is too easy to guess'
+      USING HINT = 'Use a longer password with a mix of lowercase letters, uppercase letters, digits and other characters.';
+  END IF;
+END;
+$_pgtleie_$;
+
+CREATE FUNCTION pgtle.passcheck_not_similar_to_username
+(
+  username text,
+  password text,
+  password_type pgtle.password_types
+)
+RETURNS void
+SET search_path TO 'pgtle'
+LANGUAGE plpgsql
+AS $_pgtleie_$
+DECLARE
+  lower_username text := pg_catalog.lower(username);
+  lower_password text := pg_catalog.lower(password);
+BEGIN
+  IF password_type OPERATOR(pg_catalog.<>) 'PASSWORD_TYPE_PLAINTEXT' OR
+     lower_username OPERATOR(pg_catalog.=) '' THEN
+    RETURN;
+  END IF;
+
+  IF pg_catalog.strpos(lower_password, lower_username) OPERATOR(pg_catalog.>) 0 OR
+     pg_catalog.strpos(lower_password, pg_catalog.reverse(lower_username)) OPERATOR(pg_catalog.>) 0 THEN
+    RAISE EXCEPTION 'password must not contain the role name';
+  END IF;
+END;
+$_pgtleie_$;
+
+-- A passcheck function that can be registered as is
+CREATE FUNCTION pgtle.passcheck_policy
+(
+  username text,
+  password text,
+  password_type pgtle.password_types,
+  valid_until timestamptz,
+  valid_null boolean
+)
+RETURNS void
+SET search_path TO 'pgtle'
+LANGUAGE plpgsql
+AS $_pgtleie_$
+BEGIN
+  PERFORM pgtle.passcheck_min_length(password, password_type, 12);
+  PERFORM pgtle.passcheck_character_classes(password, password_type, 3);
+  PERFORM pgtle.passcheck_not_similar_to_username(username, password, password_type);
+END;
+$_pgtleie_$;
+
+CREATE TABLE pgtle.extension_install_time
+(
+  name text NOT NULL,
+  version text NOT NULL,
+  installed_at timestamptz NOT NULL,
+  PRIMARY KEY (name, version)
+);
+
+SELECT pg_catalog.pg_extension_config_dump('pgtle.extension_install_time', '');
+
+GRANT SELECT ON pgtle.extension_install_time TO PUBLIC;
+
+DROP FUNCTION pgtle.available_extensions();
+
+CREATE FUNCTION pgtle.available_extensions
+(
+  OUT name name,
+  OUT default_version text,
+  OUT comment text,
+  OUT superuser boolean,
+  OUT trusted boolean,
+  OUT relocatable boolean,
+  OUT schema name,
+  OUT requires name[],
+  OUT installed_at timestamptz
+)
+RETURNS SETOF RECORD
+AS 'MODULE_PATHNAME', 'pg_tle_available_extensions'
+LANGUAGE C STABLE STRICT;
+
+DROP FUNCTION pgtle.available_extension_versions();
+
+CREATE FUNCTION pgtle.available_extension_versions
+(
+  OUT name name,
+  OUT version text,
+  OUT superuser boolean,
+  OUT trusted boolean,
+  OUT relocatable boolean,
+  OUT schema name,
+  OUT requires name[],
+  OUT comment text,
+  OUT installed_at timestamptz
+)
+RETURNS SETOF RECORD
+AS 'MODULE_PATHNAME', 'pg_tle_available_extension_versions'
+LANGUAGE C STABLE STRICT;
+
+CREATE FUNCTION pgtle.cluster_extensions
+(
+  OUT database name,
+  OUT name name,
+  OUT version text,
+  OUT refreshed_at timestamptz
+)
+RETURNS SETOF RECORD
+SET search_path TO 'pgtle'
+AS 'MODULE_PATHNAME', 'pg_tle_cluster_extensions'
+LANGUAGE C STRICT;
+
+REVOKE EXECUTE ON FUNCTION pgtle.cluster_extensions() FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.cluster_extensions() TO pgtle_admin;
+
+CREATE TABLE pgtle.extension_history
+(
+  id bigserial PRIMARY KEY,
+  action text NOT NULL,
+  name text NOT NULL,
+  version text,
+  feature text,
+  performed_by name NOT NULL DEFAULT CURRENT_USER,
+  performed_at timestamptz NOT NULL DEFAULT pg_catalog.now(),
+  checksum text,
+  script text
+);
+
+SELECT pg_catalog.pg_extension_config_dump('pgtle.extension_history', '');
+SELECT pg_catalog.pg_extension_config_dump('pgtle.extension_history_id_seq', '');
+
+-- only pg_tle writes to the history, so pgtle_admin can read it but cannot
+-- add, change or remove what is recorded
+GRANT SELECT ON pgtle.extension_history TO pgtle_admin;
+
+CREATE FUNCTION pgtle.feature_info_history()
+RETURNS trigger
+SET search_path TO 'pgtle'
+AS 'MODULE_PATHNAME', 'pg_tle_feature_info_history'
+LANGUAGE C;
+
+REVOKE EXECUTE ON FUNCTION pgtle.feature_info_history() FROM PUBLIC;
+
+-- uninstalls are recorded when the control and script functions are dropped,
+-- and features when they are added to or removed from pgtle.feature_info
+CREATE TRIGGER feature_info_history
+  AFTER INSERT OR DELETE ON pgtle.feature_info
+  FOR EACH ROW
+  EXECUTE FUNCTION pgtle.feature_info_history();
+
+DROP FUNCTION pgtle.uninstall_extension(extname text);
+
+CREATE FUNCTION pgtle.uninstall_extension(extname text, cascade boolean DEFAULT false)
+RETURNS boolean
+SET search_path TO 'pgtle'
+AS $_pgtleie_$
+  DECLARE
+    dropsql    text;
+    pgtlensp    text := 'pgtle';
+    func       text;
+    existsvar  record;
+    dependents text[];
+    ext        text;
+  BEGIN
+
+    SELECT 1 INTO existsvar
+      FROM pg_catalog.pg_proc p JOIN pg_catalog.pg_namespace n ON n.oid = p.pronamespace
+      WHERE n.nspname = pgtlensp AND p.proname = extname || '.control';
+    IF existsvar IS NULL THEN
+      RAISE EXCEPTION 'Extension % does not exist', extname USING ERRCODE = 'no_data_found';
+    END IF;
+
+    -- find the extensions whose primary or auxiliary control functions
+    -- require this extension, directly or indirectly, in the order they have
+    -- to be uninstalled in
+    dependents := ARRAY(
+      WITH RECURSIVE tle_requires(name, required) AS (
+        SELECT pg_catalog.split_part(pg_catalog.left(p.proname::pg_catalog.text, -8), '--', 1),
+          pg_catalog.unnest(pg_catalog.string_to_array(pg_catalog.replace(
+            pg_catalog.substring(p.prosrc, $re$\nrequires = E?'((?:[^']|'')*)'$re$),
+            $re$''$re$, $re$'$re$), ','))
+        FROM pg_catalog.pg_proc p JOIN pg_catalog.pg_namespace n ON n.oid = p.pronamespace
+        WHERE n.nspname = pgtlensp AND p.proname LIKE '%.control'
+      ), tle_dependents(name, depth, path) AS (
+        SELECT r.name, 1, ARRAY[extname, r.name]
+        FROM tle_requires r
+        WHERE r.required = extname AND r.name <> extname
+        UNION ALL
+        SELECT r.name, d.depth + 1, d.path || r.name
+        FROM tle_dependents d JOIN tle_requires r ON r.required = d.name
+        WHERE r.name <> ALL (d.path)
+      )
+      SELECT d.name FROM tle_dependents d GROUP BY d.name ORDER BY pg_catalog.max(d.depth) DESC, d.name
+    );
+
+    IF pg_catalog.cardinality(dependents) > 0 AND NOT cascade THEN
+      RAISE EXCEPTION 'Extension % is required by other extensions', extname
+        USING ERRCODE = 'dependent_objects_still_exist',
+              DETAIL = format('Extensions that require %s: %s', extname, pg_catalog.array_to_string(dependents, ', ')),
+              HINT = 'Use cascade => true to uninstall them as well.';
+    END IF;
+
+    FOREACH ext IN ARRAY dependents || extname LOOP
+      IF ext <> extname THEN
+        RAISE NOTICE 'uninstall cascades to extension %', ext;
+      END IF;
+
+      -- match the functions of this extension exactly, so that e.g. the
+      -- functions of foobar are not dropped with foo; extension names
+      -- cannot contain "--"
+      FOR func IN
+        SELECT p.proname FROM pg_catalog.pg_proc p JOIN pg_catalog.pg_namespace n ON n.oid = p.pronamespace
+        WHERE n.nspname = pgtlensp AND (p.proname = ext || '.control' OR
+          (p.proname LIKE '%.control' AND pg_catalog.split_part(p.proname, '--', 1) = ext))
+        ORDER BY p.proname = ext || '.control' DESC
+      LOOP
+        dropsql := format('DROP FUNCTION %I()', func);
+        EXECUTE dropsql;
+      END LOOP;
+
+      SELECT 1 INTO existsvar
+        FROM pg_catalog.pg_proc p JOIN pg_catalog.pg_namespace n ON n.oid = p.pronamespace
+        WHERE n.nspname = pgtlensp AND p.proname LIKE '%.sql'
+          AND pg_catalog.split_part(p.proname, '--', 1) = ext;
+      IF existsvar IS NULL THEN
+        RAISE WARNING 'Extension % has an anomaly; control function exists, but no sql commands function exists', ext;
+      ELSE
+        FOR func IN
+          SELECT p.proname FROM pg_catalog.pg_proc p JOIN pg_catalog.pg_namespace n ON n.oid = p.pronamespace
+          WHERE n.nspname = pgtlensp AND p.proname LIKE '%.sql'
+            AND pg_catalog.split_part(p.proname, '--', 1) = ext
+        LOOP
+          dropsql := format('DROP FUNCTION %I()', func);
+          EXECUTE dropsql;
+        END LOOP;
+      END IF;
+
+    END LOOP;
+
+    RETURN true;
+  END;
+$_pgtleie_$
+LANGUAGE plpgsql STRICT;
+
+REVOKE EXECUTE ON FUNCTION pgtle.uninstall_extension
+(
+  extname text,
+  cascade boolean
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.uninstall_extension
+(
+  extname text,
+  cascade boolean
+) TO pgtle_admin;
+
+CREATE OR REPLACE FUNCTION pgtle.uninstall_extension(extname text, version text)
+RETURNS boolean
+SET search_path TO 'pgtle'
+AS $_pgtleie_$
+  DECLARE
+    ctrpattern         text;
+    sqlpattern         text;
+    countverssql       text;
+    vers_count         bigint;
+    defaultversql      text;
+    defaultver         text;
+    searchsql          text;
+    dropsql            text;
+    pgtlensp           text := 'pgtle';
+    func_available_vers text := 'available_extension_versions()';
+    func_available_ext text := 'available_extensions()';
+    func               text;
+  BEGIN
+    ctrpattern := format('%s%%.control', extname);
+    sqlpattern := format('%s--%%%s%%.sql', extname, version);
+    countverssql := format('SELECT COUNT(*) FROM %s.%s WHERE name = $1', pgtlensp, func_available_vers);
+    defaultversql := format('SELECT default_version FROM %s.%s WHERE name = $1', pgtlensp, func_available_ext);
+    searchsql := 'SELECT proname FROM pg_catalog.pg_proc p JOIN pg_catalog.pg_namespace n ON n.oid = p.pronamespace WHERE proname LIKE $1 AND n.nspname = $2';
+
+    EXECUTE countverssql USING extname INTO vers_count;
+    EXECUTE defaultversql USING extname INTO defaultver;
+
+    IF vers_count > 1 THEN
+      -- if multiple versions exist and this is the default version, don't uninstall
+      IF version = defaultver THEN
+        RAISE EXCEPTION 'Can not uninstall default version of extension %, use set_default_version to update the default to another available version and retry', extname;
+      ELSE
+        -- remove the specified version sql file function only, don't remove control file function
+        FOR func IN EXECUTE searchsql USING sqlpattern, pgtlensp LOOP
+          dropsql := format('DROP FUNCTION %I()', func);
+          EXECUTE dropsql;
+        END LOOP;
+      END IF;
+    ELSE
+      -- check that the specified version matches the only version that exists
+      -- if it does then uninstall the extension completely
+      -- if it doesn't then don't uninstall anything to avoid accidental uninstall
+      IF version = defaultver THEN
+        -- go through uninstall_extension so that extensions requiring this
+        -- one are not left behind
+        PERFORM pgtle.uninstall_extension(extname);
+      ELSE
+        RAISE EXCEPTION 'Version % of extension % is not installed and therefore can not be uninstalled', extname, version;
+      END IF;
+    END IF;
+
+    RETURN TRUE;
+  END;
+$_pgtleie_$
+LANGUAGE plpgsql STRICT;
+
+DROP FUNCTION pgtle.install_extension
+(
+  name text,
+  version text,
+  description text,
+  ext text,
+  requires text[]
+);
+
+CREATE FUNCTION pgtle.install_extension
+(
+  name text,
+  version text,
+  description text,
+  ext text,
+  requires text[] DEFAULT NULL,
+  trusted boolean DEFAULT false
+)
+RETURNS boolean
+SET search_path TO 'pgtle'
+AS 'MODULE_PATHNAME', 'pg_tle_install_extension'
+LANGUAGE C;
+
+REVOKE EXECUTE ON FUNCTION pgtle.install_extension
+(
+  name text,
+  version text,
+  description text,
+  ext text,
+  requires text[],
+  trusted boolean
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.install_extension
+(
+  name text,
+  version text,
+  description text,
+  ext text,
+  requires text[],
+  trusted boolean
+) TO pgtle_admin;
+
+CREATE FUNCTION pgtle.set_requires
+(
+  name text,
+  requires text[]
+)
+RETURNS boolean
+SET search_path TO 'pgtle'
+AS 'MODULE_PATHNAME', 'pg_tle_set_requires'
+LANGUAGE C;
+
+REVOKE EXECUTE ON FUNCTION pgtle.set_requires
+(
+  name text,
+  requires text[]
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.set_requires
+(
+  name text,
+  requires text[]
+) TO pgtle_admin;
+
+CREATE FUNCTION pgtle.set_comment
+(
+  name text,
+  comment text
+)
+RETURNS boolean
+SET search_path TO 'pgtle'
+AS 'MODULE_PATHNAME', 'pg_tle_set_comment'
+LANGUAGE C;
+
+REVOKE EXECUTE ON FUNCTION pgtle.set_comment
+(
+  name text,
+  comment text
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.set_comment
+(
+  name text,
+  comment text
+) TO pgtle_admin;
+
+CREATE FUNCTION pgtle.extension_storage_stats
+(
+  OUT name text,
+  OUT scripts bigint,
+  OUT compressed_scripts bigint,
+  OUT script_bytes bigint,
+  OUT stored_bytes bigint
+)
+RETURNS SETOF RECORD
+SET search_path TO 'pgtle'
+AS $_pgtleie_$
+  DECLARE
+    sqlfunc record;
+    script_size bigint;
+  BEGIN
+    -- compressed scripts start with a header giving their size
+    FOR sqlfunc IN
+      SELECT pg_catalog.split_part(p.proname, '--', 1) AS extname,
+             p.proname,
+             pg_catalog.substring(p.prosrc,
+               '^SELECT \$_pgtle_i_\$-- pg_tle compressed script of ([0-9]+) bytes')::bigint AS compressed_size,
+             pg_catalog.pg_column_size(p.prosrc) AS stored_size
+      FROM pg_catalog.pg_proc p
+      JOIN pg_catalog.pg_namespace n ON n.oid = p.pronamespace
+      WHERE n.nspname = 'pgtle'
+        AND p.proname LIKE '%--%.sql'
+      ORDER BY 1, 2
+    LOOP
+      IF name IS DISTINCT FROM sqlfunc.extname THEN
+        IF name IS NOT NULL THEN
+          RETURN NEXT;
+        END IF;
+        name := sqlfunc.extname;
+        scripts := 0;
+        compressed_scripts := 0;
+        script_bytes := 0;
+        stored_bytes := 0;
+      END IF;
+
+      IF sqlfunc.compressed_size IS NOT NULL THEN
+        compressed_scripts := compressed_scripts + 1;
+        script_size := sqlfunc.compressed_size;
+      ELSE
+        EXECUTE pg_catalog.format('SELECT pg_catalog.octet_length(pgtle.%I())', sqlfunc.proname)
+          INTO script_size;
+      END IF;
+
+      scripts := scripts + 1;
+      script_bytes := script_bytes + script_size;
+      stored_bytes := stored_bytes + sqlfunc.stored_size;
+    END LOOP;
+
+    IF name IS NOT NULL THEN
+      RETURN NEXT;
+    END IF;
+  END;
+$_pgtleie_$
+LANGUAGE plpgsql STABLE;
+
+CREATE OR REPLACE FUNCTION pgtle.uninstall_update_path(extname text, fromvers text, tovers text)
+RETURNS boolean
+SET search_path TO 'pgtle'
+AS $_pgtleie_$
+  DECLARE
+    sqlpattern text;
+    searchsql  text;
+    dropsql    text;
+    pgtlensp   text := 'pgtle';
+    func       text;
+    existsvar  record;
+  BEGIN
+    sqlpattern := format('%s--%s--%s.sql', extname, fromvers, tovers);
+    searchsql := 'SELECT proname FROM pg_catalog.pg_proc p JOIN pg_catalog.pg_namespace n ON n.oid = p.pronamespace WHERE proname = $1 AND n.nspname = $2';
+
+    EXECUTE searchsql USING sqlpattern, pgtlensp INTO existsvar;
+
+    IF existsvar IS NULL THEN
+      RAISE EXCEPTION 'Extension % does not exist', extname USING ERRCODE = 'no_data_found';
+    ELSE
+      FOR func IN EXECUTE searchsql USING sqlpattern, pgtlensp LOOP
+        dropsql := format('DROP FUNCTION %I()', func);
+        EXECUTE dropsql;
+      END LOOP;
+    END IF;
+
+    RETURN TRUE;
+  END;
+$_pgtleie_$
+LANGUAGE plpgsql STRICT;
+
+CREATE OR REPLACE FUNCTION pgtle.register_feature(proc regproc, feature pgtle.pg_tle_features)
+RETURNS VOID
+LANGUAGE plpgsql
+AS $$
+DECLARE
+pg_proc_relid oid;
+proc_oid oid;
+schema_name text;
+nspoid oid;
+proname text;
+proc_schema_name text;
+ident text;
+passcheck_enabled text;
+clientauth_enabled text;
+current_db text;
+passcheck_db text;
+clientauth_db text;
+
+BEGIN
+    SELECT setting FROM pg_catalog.pg_settings WHERE name = 'pgtle.enable_password_check' INTO passcheck_enabled;
+    SELECT setting FROM pg_catalog.pg_settings WHERE name = 'pgtle.enable_clientauth' INTO clientauth_enabled;
+    SELECT pg_catalog.CURRENT_DATABASE() INTO current_db;
+    SELECT setting FROM pg_catalog.pg_settings WHERE name = 'pgtle.passcheck_db_name' INTO passcheck_db;
+    SELECT setting FROM pg_catalog.pg_settings WHERE name = 'pgtle.clientauth_db_name' INTO clientauth_db;
+
+    IF feature = 'passcheck' THEN
+        IF passcheck_enabled = 'off' THEN
+           RAISE NOTICE 'pgtle.enable_password_check is set to off. To enable passcheck, set pgtle.enable_password_check = on';
+        ELSE
+        -- passcheck_db_name is an optional param, we only emit a warning if it's non-empty and is not the current database
+            IF passcheck_db != '' AND current_db != passcheck_db THEN
+                RAISE NOTICE '%', pg_catalog.FORMAT('pgtle.passcheck_db_name is currently %I. To trigger this passcheck function, register the function in that database.', passcheck_db)
+                USING HINT = pg_catalog.FORMAT('Alternatively, to use the current database for passcheck, set pgtle.passcheck_db_name = %I and reload the PostgreSQL configuration.', current_db);
+            END IF;
+        END IF;
+    END IF;
+
+    IF feature = 'clientauth' THEN
+        IF clientauth_enabled = 'off' THEN
+            RAISE NOTICE 'pgtle.enable_clientauth is set to off. To enable clientauth, set pgtle.enable_clientauth = on';
+        ELSE
+            IF current_db != clientauth_db THEN
+                RAISE NOTICE '%', pg_catalog.FORMAT('pgtle.clientauth_db_name is currently %I. To trigger this clientauth function, register the function in that database.', clientauth_db)
+                USING HINT = pg_catalog.FORMAT('Alternatively, to use the current database for clientauth, set pgtle.clientauth_db_name = %I and reload the PostgreSQL configuration.', current_db);
+            END IF;
+        END IF;
+    END IF;
+
+    SELECT oid into nspoid FROM pg_catalog.pg_namespace
+    where nspname = 'pg_catalog';
+
+    SELECT oid into pg_proc_relid from pg_catalog.pg_class
+    where relname = 'pg_proc' and relnamespace = nspoid;
+
+    SELECT pg_namespace.nspname, pg_proc.oid, pg_proc.proname into proc_schema_name, proc_oid, proname FROM
+                                                                                                           pg_catalog.pg_namespace, pg_catalog.pg_proc
+    where pg_proc.oid = proc AND pg_proc.pronamespace = pg_namespace.oid;
+
+    SELECT identity into ident FROM pg_catalog.pg_identify_object(pg_proc_relid, proc_oid, 0);
+
+    INSERT INTO pgtle.feature_info VALUES (feature, proc_schema_name, proname, ident);
+END;
+$$;
+
+CREATE OR REPLACE FUNCTION pgtle.unregister_feature(proc regproc, feature pgtle.pg_tle_features)
+RETURNS void
+LANGUAGE plpgsql
+AS $$
+DECLARE
+	pg_proc_relid oid;
+	proc_oid oid;
+	schema_name text;
+	nspoid oid;
+	proc_name text;
+	proc_schema_name text;
+	ident text;
+	row_count bigint;
+BEGIN
+	SELECT oid into nspoid
+  FROM pg_catalog.pg_namespace
+	WHERE nspname = 'pg_catalog';
+
+	SELECT oid into pg_proc_relid
+  FROM pg_catalog.pg_class
+	WHERE
+		relname = 'pg_proc' AND
+		relnamespace = nspoid;
+
+	SELECT
+		pg_namespace.nspname,
+		pg_proc.oid,
+		pg_proc.proname
+  INTO
+		proc_schema_name,
+		proc_oid,
+		proc_name
+	FROM pg_catalog.pg_namespace, pg_catalog.pg_proc
+	WHERE
+		pg_proc.oid = proc AND
+		pg_proc.pronamespace = pg_namespace.oid;
+
+	DELETE FROM pgtle.feature_info
+	WHERE
+		feature_info.feature = $2 AND
+		feature_info.schema_name = proc_schema_name AND
+		feature_info.proname = proc_name;
+
+	GET DIAGNOSTICS row_count = ROW_COUNT;
+
+	IF ROW_COUNT = 0 THEN
+    RAISE EXCEPTION 'Could not unregister "%": does not exist.', $1 USING ERRCODE = 'no_data_found';
+  END IF;
+
+	SELECT identity INTO ident FROM pg_catalog.pg_identify_object(pg_proc_relid, proc_oid, 0);
+END;
+$$;
+
+CREATE FUNCTION pgtle.extension_history_script
+(
+  name text,
+  version text,
+  at timestamptz DEFAULT pg_catalog.now()
+)
+RETURNS text
+SET search_path TO 'pgtle'
+AS 'MODULE_PATHNAME', 'pg_tle_extension_history_script'
+LANGUAGE C STABLE STRICT;
+
+REVOKE EXECUTE ON FUNCTION pgtle.extension_history_script
+(
+  name text,
+  version text,
+  at timestamptz
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.extension_history_script
+(
+  name text,
+  version text,
+  at timestamptz
+) TO pgtle_admin;
+
+DROP FUNCTION pgtle.create_base_type CASCADE;
+DROP FUNCTION pgtle.create_base_type_if_not_exists CASCADE;
+
+CREATE FUNCTION pgtle.create_base_type
+(
+  typenamespace regnamespace,
+  typename name,
+  infunc regprocedure,
+  outfunc regprocedure,
+  internallength int4,
+  alignment text default 'int4',
+  storage text default 'plain',
+  default_value text default NULL,
+  analyzefunc regprocedure default NULL
+)
+RETURNS void
+SET search_path TO 'pgtle'
+AS 'MODULE_PATHNAME', 'pg_tle_create_base_type_with_options'
+LANGUAGE C;
+
+CREATE FUNCTION pgtle.create_base_type_if_not_exists
+(
+  typenamespace regnamespace,
+  typename name,
+  infunc regprocedure,
+  outfunc regprocedure,
+  internallength int4,
+  alignment text default 'int4',
+  storage text default 'plain',
+  default_value text default NULL,
+  analyzefunc regprocedure default NULL
+)
+RETURNS boolean
+SET search_path TO 'pgtle'
+AS $_pgtleie_$
+BEGIN
+  PERFORM pgtle.create_base_type(typenamespace, typename, infunc, outfunc, internallength, alignment, storage, default_value, analyzefunc);
+  RETURN TRUE;
+EXCEPTION
+  -- only catch the duplicate_object exception, let all other exceptions pass through.
+  WHEN duplicate_object THEN
+    RETURN FALSE;
+END;
+$_pgtleie_$
+LANGUAGE plpgsql;
+
+REVOKE EXECUTE ON FUNCTION pgtle.create_base_type
+(
+  typenamespace regnamespace,
+  typename name,
+  infunc regprocedure,
+  outfunc regprocedure,
+  internallength int4,
+  alignment text,
+  storage text,
+  default_value text,
+  analyzefunc regprocedure
+) FROM PUBLIC;
+
+REVOKE EXECUTE ON FUNCTION pgtle.create_base_type_if_not_exists
+(
+  typenamespace regnamespace,
+  typename name,
+  infunc regprocedure,
+  outfunc regprocedure,
+  internallength int4,
+  alignment text,
+  storage text,
+  default_value text,
+  analyzefunc regprocedure
+) FROM PUBLIC;
+
+GRANT EXECUTE ON FUNCTION pgtle.create_base_type
+(
+  typenamespace regnamespace,
+  typename name,
+  infunc regprocedure,
+  outfunc regprocedure,
+  internallength int4,
+  alignment text,
+  storage text,
+  default_value text,
+  analyzefunc regprocedure
+) TO pgtle_admin;
+
+GRANT EXECUTE ON FUNCTION pgtle.create_base_type_if_not_exists
+(
+  typenamespace regnamespace,
+  typename name,
+  infunc regprocedure,
+  outfunc regprocedure,
+  internallength int4,
+  alignment text,
+  storage text,
+  default_value text,
+  analyzefunc regprocedure
+) TO pgtle_admin;
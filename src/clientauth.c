@@ -40,7 +40,13 @@
  *
  * Note that if the connecting user or database is found in
  * pgtle.clientauth_users_to_skip or pgtle.clientauth_databases_to_skip, then
- * the connection is accepted before doing anything.
+ * the connection is accepted before doing anything. Likewise if
+ * pgtle.clientauth_users_to_check or pgtle.clientauth_databases_to_check is
+ * set and the connecting user or database is not found in it, if the
+ * connection's application_name is found in
+ * pgtle.clientauth_application_names_to_skip, or if the connection is a
+ * replication or superuser connection and the corresponding
+ * pgtle.clientauth_skip_* parameter is on.
  */
 
 #include "postgres.h"
@@ -53,6 +59,8 @@
 #include "libpq/auth.h"
 #include "nodes/pg_list.h"
 #include "postmaster/bgworker_internals.h"
+#include "replication/walsender.h"
+#include "utils/acl.h"
 #include "utils/builtins.h"
 #include "utils/elog.h"
 #include "utils/errcodes.h"
@@ -138,6 +146,21 @@ static char *clientauth_users_to_skip = "";
 /* GUC that determines databases that clientauth feature skips */
 static char *clientauth_databases_to_skip = "";
 
+/* GUC that determines users that clientauth feature checks, if set */
+static char *clientauth_users_to_check = "";
+
+/* GUC that determines databases that clientauth feature checks, if set */
+static char *clientauth_databases_to_check = "";
+
+/* GUC that determines application_names that clientauth feature skips */
+static char *clientauth_application_names_to_skip = "";
+
+/* GUC that determines whether clientauth feature skips replication connections */
+static bool clientauth_skip_replication_connections = false;
+
+/* GUC that determines whether clientauth feature skips superuser connections */
+static bool clientauth_skip_superusers = false;
+
 /* Global flags */
 static bool clientauth_reload_config = false;
 
@@ -197,9 +220,12 @@ typedef struct ClientAuthStatusEntry
 	/* PID of backend process that is currently using this entry */
 	int			pid;
 
-	/* Error message to be emitted back to client */
+	/* Error to be emitted back to client */
 	bool		error;
+	int			error_code;
 	char		error_msg[CLIENT_AUTH_USER_ERROR_MAX_STRLEN];
+	char		error_detail[CLIENT_AUTH_USER_ERROR_MAX_STRLEN];
+	char		error_hint[CLIENT_AUTH_USER_ERROR_MAX_STRLEN];
 }			ClientAuthStatusEntry;
 
 /*
@@ -298,6 +324,56 @@ clientauth_init(void)
 							   GUC_LIST_INPUT,
 							   NULL, NULL, NULL);
 
+	DefineCustomStringVariable(
+							   "pgtle.clientauth_users_to_check",
+							   gettext_noop("Comma-delimited list of users that pg_tle clientauth hook checks. If empty, all users are checked."),
+							   NULL,
+							   &clientauth_users_to_check,
+							   "",
+							   PGC_SIGHUP,
+							   GUC_LIST_INPUT,
+							   NULL, NULL, NULL);
+
+	DefineCustomStringVariable(
+							   "pgtle.clientauth_databases_to_check",
+							   gettext_noop("Comma-delimited list of databases that pg_tle clientauth hook checks. If empty, all databases are checked."),
+							   NULL,
+							   &clientauth_databases_to_check,
+							   "",
+							   PGC_SIGHUP,
+							   GUC_LIST_INPUT,
+							   NULL, NULL, NULL);
+
+	DefineCustomStringVariable(
+							   "pgtle.clientauth_application_names_to_skip",
+							   gettext_noop("Comma-delimited list of application_names that pg_tle clientauth hook skips."),
+							   NULL,
+							   &clientauth_application_names_to_skip,
+							   "",
+							   PGC_SIGHUP,
+							   GUC_LIST_INPUT,
+							   NULL, NULL, NULL);
+
+	DefineCustomBoolVariable(
+							 "pgtle.clientauth_skip_replication_connections",
+							 gettext_noop("Whether pg_tle clientauth hook skips replication connections."),
+							 NULL,
+							 &clientauth_skip_replication_connections,
+							 false,
+							 PGC_SIGHUP,
+							 GUC_SUPERUSER_ONLY,
+							 NULL, NULL, NULL);
+
+	DefineCustomBoolVariable(
+							 "pgtle.clientauth_skip_superusers",
+							 gettext_noop("Whether pg_tle clientauth hook skips connections of superusers."),
+							 NULL,
+							 &clientauth_skip_superusers,
+							 false,
+							 PGC_SIGHUP,
+							 GUC_SUPERUSER_ONLY,
+							 NULL, NULL, NULL);
+
 	/* Do not register hooks or background workers if we are in pg_upgrade */
 	if (IsBinaryUpgrade)
 		return;
@@ -394,6 +470,14 @@ clientauth_launcher_main(Datum arg)
 		char		error_msg[CLIENT_AUTH_USER_ERROR_MAX_STRLEN];
 		bool		error;
 
+		/*
+		 * SQLSTATE, detail and hint of an error raised by the user function,
+		 * to be copied into shared memory
+		 */
+		int			error_code = ERRCODE_CONNECTION_EXCEPTION;
+		char		error_detail[CLIENT_AUTH_USER_ERROR_MAX_STRLEN] = "";
+		char		error_hint[CLIENT_AUTH_USER_ERROR_MAX_STRLEN] = "";
+
 		/* Used for error handling */
 		MemoryContext old_context;
 		ResourceOwner old_owner;
@@ -490,9 +574,15 @@ clientauth_launcher_main(Datum arg)
 			CurrentResourceOwner = old_owner;
 
 			/*
-			 * Return the error from SPI to the user and reject the connection
+			 * Return the error from SPI to the user and reject the connection.
+			 * Errors raised without an explicit SQLSTATE, e.g. with a plain
+			 * RAISE EXCEPTION, are reported as connection exceptions.
 			 */
 			snprintf(error_msg, CLIENT_AUTH_USER_ERROR_MAX_STRLEN, "%s", edata->message);
+			snprintf(error_detail, CLIENT_AUTH_USER_ERROR_MAX_STRLEN, "%s", edata->detail ? edata->detail : "");
+			snprintf(error_hint, CLIENT_AUTH_USER_ERROR_MAX_STRLEN, "%s", edata->hint ? edata->hint : "");
+			if (edata->sqlerrcode != ERRCODE_RAISE_EXCEPTION)
+				error_code = edata->sqlerrcode;
 			error = true;
 			FreeErrorData(edata);
 		}
@@ -508,7 +598,10 @@ clientauth_launcher_main(Datum arg)
 		 */
 		LWLockAcquire(clientauth_ss->lock, LW_EXCLUSIVE);
 		clientauth_ss->requests[idx].error = error;
+		clientauth_ss->requests[idx].error_code = error_code;
 		snprintf(clientauth_ss->requests[idx].error_msg, CLIENT_AUTH_USER_ERROR_MAX_STRLEN, "%s", error_msg);
+		snprintf(clientauth_ss->requests[idx].error_detail, CLIENT_AUTH_USER_ERROR_MAX_STRLEN, "%s", error_detail);
+		snprintf(clientauth_ss->requests[idx].error_hint, CLIENT_AUTH_USER_ERROR_MAX_STRLEN, "%s", error_hint);
 		clientauth_ss->requests[idx].done_processing = true;
 		LWLockRelease(clientauth_ss->lock);
 		ConditionVariableSignal(&clientauth_ss->requests[idx].client_cv);
@@ -648,6 +741,9 @@ clientauth_hook(Port *port, int status)
 	 */
 	int			idx = MyProc->pid % CLIENT_AUTH_MAX_PENDING_ENTRIES;
 	char		error_msg[CLIENT_AUTH_USER_ERROR_MAX_STRLEN];
+	char		error_detail[CLIENT_AUTH_USER_ERROR_MAX_STRLEN];
+	char		error_hint[CLIENT_AUTH_USER_ERROR_MAX_STRLEN];
+	int			error_code;
 	bool		error;
 
 	if (prev_clientauth_hook)
@@ -662,6 +758,33 @@ clientauth_hook(Port *port, int status)
 	/* Skip if this database is on the skip list */
 	if (check_string_in_guc_list(port->database_name, clientauth_databases_to_skip, "pgtle.clientauth_databases_to_skip"))
 		return;
+	/* Skip if there is a list of users to check and this user is not on it */
+	if (strcmp(clientauth_users_to_check, "") != 0 &&
+		!check_string_in_guc_list(port->user_name, clientauth_users_to_check, "pgtle.clientauth_users_to_check"))
+		return;
+	/* Skip if there is a list of databases to check and this database is not on it */
+	if (strcmp(clientauth_databases_to_check, "") != 0 &&
+		!check_string_in_guc_list(port->database_name, clientauth_databases_to_check, "pgtle.clientauth_databases_to_check"))
+		return;
+	/* Skip if this application_name is on the skip list */
+	if (port->application_name != NULL &&
+		check_string_in_guc_list(port->application_name, clientauth_application_names_to_skip, "pgtle.clientauth_application_names_to_skip"))
+		return;
+	/* Skip replication connections if configured to */
+	if (clientauth_skip_replication_connections && am_walsender)
+		return;
+
+	/*
+	 * Skip superusers if configured to. Authentication runs in a transaction
+	 * in which the shared catalogs can be read, so the role can be looked up.
+	 */
+	if (clientauth_skip_superusers && port->user_name != NULL)
+	{
+		Oid			roleid = get_role_oid(port->user_name, true);
+
+		if (OidIsValid(roleid) && superuser_arg(roleid))
+			return;
+	}
 
 	/*
 	 * If the queue entry is not available, wait until another client using it
@@ -740,12 +863,18 @@ clientauth_hook(Port *port, int status)
 
 	/* Copy results of BGW processing from shared memory */
 	snprintf(error_msg, CLIENT_AUTH_USER_ERROR_MAX_STRLEN, "%s", clientauth_ss->requests[idx].error_msg);
+	snprintf(error_detail, CLIENT_AUTH_USER_ERROR_MAX_STRLEN, "%s", clientauth_ss->requests[idx].error_detail);
+	snprintf(error_hint, CLIENT_AUTH_USER_ERROR_MAX_STRLEN, "%s", clientauth_ss->requests[idx].error_hint);
+	error_code = clientauth_ss->requests[idx].error_code;
 	error = clientauth_ss->requests[idx].error;
 
 	/* Erase data about this request from shared memory now that we're done */
 	memset(&clientauth_ss->requests[idx].port_info, 0, sizeof(PortSubset));
 	clientauth_ss->requests[idx].status = 0;
 	memset(clientauth_ss->requests[idx].error_msg, 0, sizeof(char) * CLIENT_AUTH_USER_ERROR_MAX_STRLEN);
+	memset(clientauth_ss->requests[idx].error_detail, 0, sizeof(char) * CLIENT_AUTH_USER_ERROR_MAX_STRLEN);
+	memset(clientauth_ss->requests[idx].error_hint, 0, sizeof(char) * CLIENT_AUTH_USER_ERROR_MAX_STRLEN);
+	clientauth_ss->requests[idx].error_code = ERRCODE_CONNECTION_EXCEPTION;
 	clientauth_ss->requests[idx].error = false;
 
 	clientauth_ss->requests[idx].available_entry = true;
@@ -753,7 +882,11 @@ clientauth_hook(Port *port, int status)
 	ConditionVariableSignal(clientauth_ss->requests[idx].available_entry_cv_ptr);
 
 	if (error)
-		ereport(ERROR, errcode(ERRCODE_CONNECTION_EXCEPTION), errmsg("%s", error_msg));
+		ereport(ERROR,
+				errcode(error_code),
+				errmsg("%s", error_msg),
+				error_detail[0] != '\0' ? errdetail("%s", error_detail) : 0,
+				error_hint[0] != '\0' ? errhint("%s", error_hint) : 0);
 }
 
 static void
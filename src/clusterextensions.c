@@ -0,0 +1,601 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * Cluster-wide view of trusted language extensions.
+ *
+ * A backend can only read the catalogs of the database it is connected to,
+ * so a launcher background worker periodically starts a worker in each
+ * database in turn. The worker looks up the TLEs installed in its database
+ * and the version they are created at, if any, and stores them in shared
+ * memory. pgtle.cluster_extensions() reports the contents of shared memory
+ * from any database.
+ */
+#include "postgres.h"
+#include "access/heapam.h"
+#include "access/htup_details.h"
+#include "access/xact.h"
+#include "catalog/namespace.h"
+#include "catalog/pg_database.h"
+#include "catalog/pg_type.h"
+#include "commands/dbcommands.h"
+#include "commands/extension.h"
+#include "executor/spi.h"
+#include "fmgr.h"
+#include "funcapi.h"
+#include "miscadmin.h"
+#include "pgstat.h"
+#include "postmaster/bgworker.h"
+#include "storage/ipc.h"
+#include "storage/latch.h"
+#include "storage/lwlock.h"
+#include "storage/shmem.h"
+#include "utils/builtins.h"
+#include "utils/guc.h"
+#include "utils/memutils.h"
+#include "utils/snapmgr.h"
+#include "utils/timestamp.h"
+
+#if PG_VERSION_NUM >= 120000
+#include "access/tableam.h"
+#endif
+
+#include "compatibility.h"
+#include "constants.h"
+#include "clusterextensions.h"
+#include "tleextension.h"
+
+/* Maximum length of versions (including \0) stored in shared memory */
+#define CLUSTER_EXTENSIONS_VERSION_MAX_STRLEN 256
+
+static const char *cluster_extensions_shmem_name = "pgtle_cluster_extensions";
+static const char *cluster_extensions_launcher_name = "pg_tle_cluster_extensions launcher";
+static const char *cluster_extensions_worker_name = "pg_tle_cluster_extensions worker";
+
+void		clusterextensions_init(void);
+
+PGDLLEXPORT void clusterextensions_launcher_main(Datum arg);
+PGDLLEXPORT void clusterextensions_worker_main(Datum arg);
+
+static shmem_startup_hook_type prev_shmem_startup_hook = NULL;
+static void clusterextensions_shmem_startup(void);
+
+#if (PG_VERSION_NUM >= 150000)
+static shmem_request_hook_type prev_shmem_request_hook = NULL;
+static void clusterextensions_shmem_request(void);
+#endif
+
+static Size clusterextensions_shared_memsize(void);
+
+/* GUC that determines whether the TLEs in all databases are tracked */
+static bool enable_cluster_extensions = false;
+
+/* GUC that determines how often the TLEs in all databases are looked up */
+static int	cluster_extensions_refresh_interval = 60;
+
+/* GUC that determines how many TLEs can be tracked across all databases */
+static int	cluster_extensions_max_entries = 1024;
+
+static volatile sig_atomic_t cluster_extensions_reload_config = false;
+
+/* A TLE installed in a database */
+typedef struct ClusterExtensionEntry
+{
+	Oid			dboid;
+	NameData	name;
+	/* Version the extension is created at, empty if it is not created */
+	char		version[CLUSTER_EXTENSIONS_VERSION_MAX_STRLEN];
+	TimestampTz refreshed_at;
+}			ClusterExtensionEntry;
+
+typedef struct ClusterExtensionsSharedState
+{
+	LWLock	   *lock;
+	int			num_entries;
+	ClusterExtensionEntry entries[FLEXIBLE_ARRAY_MEMBER];
+}			ClusterExtensionsSharedState;
+
+static ClusterExtensionsSharedState * clusterextensions_ss = NULL;
+
+static void clusterextensions_sighup(SIGNAL_ARGS);
+static List *get_database_list(void);
+static void refresh_database(Oid dboid);
+static void remove_database_entries(Oid dboid);
+static void remove_dropped_database_entries(List *dboids);
+
+void
+clusterextensions_init(void)
+{
+	BackgroundWorker worker;
+
+	DefineCustomBoolVariable(
+							 "pgtle.enable_cluster_extensions",
+							 gettext_noop("Starts the pg_tle background worker that tracks the TLEs in all databases."),
+							 NULL,
+							 &enable_cluster_extensions,
+							 false,
+							 PGC_POSTMASTER,
+							 GUC_SUPERUSER_ONLY,
+							 NULL, NULL, NULL);
+
+	DefineCustomIntVariable(
+							"pgtle.cluster_extensions_refresh_interval",
+							gettext_noop("Time between lookups of the TLEs in all databases."),
+							NULL,
+							&cluster_extensions_refresh_interval,
+							60,
+							1,
+							INT_MAX / 1000,
+							PGC_SIGHUP,
+							GUC_UNIT_S | GUC_SUPERUSER_ONLY,
+							NULL, NULL, NULL);
+
+	DefineCustomIntVariable(
+							"pgtle.cluster_extensions_max_entries",
+							gettext_noop("Maximum number of TLEs tracked across all databases."),
+							NULL,
+							&cluster_extensions_max_entries,
+							1024,
+							1,
+							INT_MAX / sizeof(ClusterExtensionEntry),
+							PGC_POSTMASTER,
+							GUC_SUPERUSER_ONLY,
+							NULL, NULL, NULL);
+
+	/* Do not register hooks or background workers if we are in pg_upgrade */
+	if (IsBinaryUpgrade)
+		return;
+	if (!enable_cluster_extensions)
+		return;
+
+#if (PG_VERSION_NUM < 150000)
+	RequestNamedLWLockTranche(cluster_extensions_shmem_name, 1);
+	RequestAddinShmemSpace(clusterextensions_shared_memsize());
+#endif
+
+	/* PG15 requires shared memory space to be requested in shmem_request_hook */
+#if (PG_VERSION_NUM >= 150000)
+	prev_shmem_request_hook = shmem_request_hook;
+	shmem_request_hook = clusterextensions_shmem_request;
+#endif
+
+	/* Install our shmem hooks */
+	prev_shmem_startup_hook = shmem_startup_hook;
+	shmem_startup_hook = clusterextensions_shmem_startup;
+
+	memset(&worker, 0, sizeof(worker));
+	worker.bgw_flags = BGWORKER_SHMEM_ACCESS | BGWORKER_BACKEND_DATABASE_CONNECTION;
+	worker.bgw_start_time = BgWorkerStart_RecoveryFinished;
+	worker.bgw_restart_time = 10;
+	worker.bgw_notify_pid = 0;
+	sprintf(worker.bgw_library_name, PG_TLE_EXTNAME);
+	sprintf(worker.bgw_function_name, "clusterextensions_launcher_main");
+	snprintf(worker.bgw_type, BGW_MAXLEN, "%s", cluster_extensions_launcher_name);
+	snprintf(worker.bgw_name, BGW_MAXLEN, "%s", cluster_extensions_launcher_name);
+	RegisterBackgroundWorker(&worker);
+}
+
+static void
+clusterextensions_sighup(SIGNAL_ARGS)
+{
+	int			save_errno = errno;
+
+	cluster_extensions_reload_config = true;
+	SetLatch(MyLatch);
+
+	errno = save_errno;
+}
+
+/*
+ * Starts a worker in each database in turn, then sleeps for
+ * pgtle.cluster_extensions_refresh_interval.
+ */
+void
+clusterextensions_launcher_main(Datum arg)
+{
+	/* Establish signal handlers before unblocking signals */
+	pqsignal(SIGHUP, clusterextensions_sighup);
+	pqsignal(SIGTERM, die);
+	BackgroundWorkerUnblockSignals();
+
+	/* Only the shared catalogs are needed to list the databases */
+	BackgroundWorkerInitializeConnection(NULL, NULL, 0);
+
+	while (true)
+	{
+		List	   *dboids;
+		ListCell   *lc;
+		int			rc;
+
+		CHECK_FOR_INTERRUPTS();
+
+		if (cluster_extensions_reload_config)
+		{
+			cluster_extensions_reload_config = false;
+			ProcessConfigFile(PGC_SIGHUP);
+		}
+
+		dboids = get_database_list();
+
+		foreach(lc, dboids)
+		{
+			CHECK_FOR_INTERRUPTS();
+			refresh_database(lfirst_oid(lc));
+		}
+
+		remove_dropped_database_entries(dboids);
+		list_free(dboids);
+
+		rc = WaitLatch(MyLatch,
+					   WL_LATCH_SET | WL_TIMEOUT | WL_POSTMASTER_DEATH,
+					   cluster_extensions_refresh_interval * 1000L,
+					   PG_WAIT_EXTENSION);
+		ResetLatch(MyLatch);
+
+		if (rc & WL_POSTMASTER_DEATH)
+			proc_exit(1);
+	}
+}
+
+/*
+ * Returns the OIDs of the databases that accept connections.
+ */
+static List *
+get_database_list(void)
+{
+	List	   *dboids = NIL;
+	Relation	rel;
+	TableScanDesc scan;
+	HeapTuple	tup;
+	MemoryContext resultcxt = CurrentMemoryContext;
+
+	StartTransactionCommand();
+	(void) GetTransactionSnapshot();
+
+	rel = table_open(DatabaseRelationId, AccessShareLock);
+	scan = table_beginscan_catalog(rel, 0, NULL);
+
+	while (HeapTupleIsValid(tup = heap_getnext(scan, ForwardScanDirection)))
+	{
+		Form_pg_database pgdatabase = (Form_pg_database) GETSTRUCT(tup);
+		MemoryContext oldcxt;
+
+		if (!pgdatabase->datallowconn)
+			continue;
+
+		oldcxt = MemoryContextSwitchTo(resultcxt);
+#if PG_VERSION_NUM >= 120000
+		dboids = lappend_oid(dboids, pgdatabase->oid);
+#else
+		dboids = lappend_oid(dboids, HeapTupleGetOid(tup));
+#endif
+		MemoryContextSwitchTo(oldcxt);
+	}
+
+	table_endscan(scan);
+	table_close(rel, AccessShareLock);
+
+	CommitTransactionCommand();
+
+	return dboids;
+}
+
+/*
+ * Starts a worker in the database and waits for it to store the TLEs of the
+ * database in shared memory.
+ */
+static void
+refresh_database(Oid dboid)
+{
+	BackgroundWorker worker;
+	BackgroundWorkerHandle *worker_handle;
+
+	memset(&worker, 0, sizeof(worker));
+	worker.bgw_flags = BGWORKER_SHMEM_ACCESS | BGWORKER_BACKEND_DATABASE_CONNECTION;
+	worker.bgw_start_time = BgWorkerStart_RecoveryFinished;
+	worker.bgw_restart_time = BGW_NEVER_RESTART;
+	worker.bgw_notify_pid = MyProcPid;
+	worker.bgw_main_arg = ObjectIdGetDatum(dboid);
+	sprintf(worker.bgw_library_name, PG_TLE_EXTNAME);
+	sprintf(worker.bgw_function_name, "clusterextensions_worker_main");
+	snprintf(worker.bgw_type, BGW_MAXLEN, "%s", cluster_extensions_worker_name);
+	snprintf(worker.bgw_name, BGW_MAXLEN, "%s", cluster_extensions_worker_name);
+
+	if (!RegisterDynamicBackgroundWorker(&worker, &worker_handle))
+	{
+		ereport(LOG,
+				errmsg("%s cluster extensions feature failed to spawn background worker", PG_TLE_EXTNAME),
+				errhint("Consider increasing max_worker_processes or reducing other background workers."));
+		return;
+	}
+
+	(void) WaitForBackgroundWorkerShutdown(worker_handle);
+	pfree(worker_handle);
+}
+
+/*
+ * Looks up the TLEs installed in the database given by arg and replaces the
+ * entries of the database in shared memory.
+ */
+void
+clusterextensions_worker_main(Datum arg)
+{
+	Oid			dboid = DatumGetObjectId(arg);
+	Oid			pgtlensp;
+	char	   *query;
+	Oid			argtypes[SPI_NARGS_1] = {OIDOID};
+	Datum		args[SPI_NARGS_1];
+	TimestampTz now;
+	bool		full = false;
+	uint64		i;
+
+	pqsignal(SIGTERM, die);
+	BackgroundWorkerUnblockSignals();
+
+	BackgroundWorkerInitializeConnectionByOid(dboid, InvalidOid, 0);
+
+	SetCurrentStatementStartTimestamp();
+	StartTransactionCommand();
+	PushActiveSnapshot(GetTransactionSnapshot());
+
+	if (get_extension_oid(PG_TLE_EXTNAME, true) == InvalidOid)
+	{
+		LWLockAcquire(clusterextensions_ss->lock, LW_EXCLUSIVE);
+		remove_database_entries(dboid);
+		LWLockRelease(clusterextensions_ss->lock);
+		PopActiveSnapshot();
+		CommitTransactionCommand();
+		return;
+	}
+
+	if (SPI_connect() != SPI_OK_CONNECT)
+		elog(ERROR, "SPI_connect failed");
+
+	/*
+	 * A TLE is installed if its control function exists, and is created if
+	 * there is an extension of the same name.
+	 */
+	pgtlensp = get_namespace_oid(PG_TLE_NSPNAME, false);
+	args[0] = ObjectIdGetDatum(pgtlensp);
+	query = psprintf("SELECT x.name, e.extversion FROM "
+					 "(SELECT pg_catalog.left(p.proname::pg_catalog.text, -8) AS name "
+					 "FROM pg_catalog.pg_proc p "
+					 "WHERE p.pronamespace OPERATOR(pg_catalog.=) $1 "
+					 "AND p.proname OPERATOR(pg_catalog.~~) '%%.control' "
+					 "AND p.proname OPERATOR(pg_catalog.!~~) '%%--%%') x "
+					 "LEFT JOIN pg_catalog.pg_extension e "
+					 "ON e.extname::pg_catalog.text OPERATOR(pg_catalog.=) x.name "
+					 "ORDER BY x.name");
+	pgstat_report_activity(STATE_RUNNING, query);
+
+	if (SPI_execute_with_args(query, 1, argtypes, args, NULL, true, 0) != SPI_OK_SELECT)
+		elog(ERROR, "failed to look up the installed TLEs");
+
+	now = GetCurrentTimestamp();
+
+	LWLockAcquire(clusterextensions_ss->lock, LW_EXCLUSIVE);
+	remove_database_entries(dboid);
+
+	for (i = 0; i < SPI_processed; i++)
+	{
+		HeapTuple	tuple = SPI_tuptable->vals[i];
+		TupleDesc	tupdesc = SPI_tuptable->tupdesc;
+		ClusterExtensionEntry *entry;
+		char	   *name;
+		char	   *version;
+
+		if (clusterextensions_ss->num_entries >= cluster_extensions_max_entries)
+		{
+			full = true;
+			break;
+		}
+
+		name = SPI_getvalue(tuple, tupdesc, 1);
+		version = SPI_getvalue(tuple, tupdesc, 2);
+
+		entry = &clusterextensions_ss->entries[clusterextensions_ss->num_entries++];
+		entry->dboid = dboid;
+		namestrcpy(&entry->name, name);
+		strlcpy(entry->version, version ? version : "", sizeof(entry->version));
+		entry->refreshed_at = now;
+	}
+
+	LWLockRelease(clusterextensions_ss->lock);
+
+	if (full)
+		ereport(WARNING,
+				errmsg("not all TLEs in database \"%s\" are tracked", get_database_name(dboid)),
+				errhint("Consider increasing pgtle.cluster_extensions_max_entries."));
+
+	SPI_finish();
+	PopActiveSnapshot();
+	CommitTransactionCommand();
+	pgstat_report_activity(STATE_IDLE, NULL);
+}
+
+/*
+ * Removes the entries of a database from shared memory. The caller must hold
+ * the lock in exclusive mode.
+ */
+static void
+remove_database_entries(Oid dboid)
+{
+	int			i;
+	int			num_kept = 0;
+
+	for (i = 0; i < clusterextensions_ss->num_entries; i++)
+	{
+		if (clusterextensions_ss->entries[i].dboid == dboid)
+			continue;
+		if (i != num_kept)
+			clusterextensions_ss->entries[num_kept] = clusterextensions_ss->entries[i];
+		num_kept++;
+	}
+	clusterextensions_ss->num_entries = num_kept;
+}
+
+/*
+ * Removes the entries of databases that are not in dboids, i.e. that have
+ * been dropped or no longer accept connections.
+ */
+static void
+remove_dropped_database_entries(List *dboids)
+{
+	int			i;
+	int			num_kept = 0;
+
+	LWLockAcquire(clusterextensions_ss->lock, LW_EXCLUSIVE);
+
+	for (i = 0; i < clusterextensions_ss->num_entries; i++)
+	{
+		if (!list_member_oid(dboids, clusterextensions_ss->entries[i].dboid))
+			continue;
+		if (i != num_kept)
+			clusterextensions_ss->entries[num_kept] = clusterextensions_ss->entries[i];
+		num_kept++;
+	}
+	clusterextensions_ss->num_entries = num_kept;
+
+	LWLockRelease(clusterextensions_ss->lock);
+}
+
+/*
+ * Reports the TLEs installed in each database, as of the latest lookup in
+ * that database.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_cluster_extensions);
+Datum
+pg_tle_cluster_extensions(PG_FUNCTION_ARGS)
+{
+	FuncCallContext *funcctx;
+	ClusterExtensionEntry *entries;
+
+	if (SRF_IS_FIRSTCALL())
+	{
+		MemoryContext oldcontext;
+		TupleDesc	tupdesc;
+		int			num_entries;
+
+		if (clusterextensions_ss == NULL)
+			ereport(ERROR,
+					(errcode(ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE),
+					 errmsg("pgtle.enable_cluster_extensions is set to off"),
+					 errhint("To track the TLEs in all databases, set pgtle.enable_cluster_extensions = on and restart the database.")));
+
+		funcctx = SRF_FIRSTCALL_INIT();
+		oldcontext = MemoryContextSwitchTo(funcctx->multi_call_memory_ctx);
+
+		if (get_call_result_type(fcinfo, NULL, &tupdesc) != TYPEFUNC_COMPOSITE)
+			elog(ERROR, "return type must be a row type");
+		funcctx->tuple_desc = BlessTupleDesc(tupdesc);
+
+		/* Copy the entries so that the lock is not held during catalog lookups */
+		LWLockAcquire(clusterextensions_ss->lock, LW_SHARED);
+		num_entries = clusterextensions_ss->num_entries;
+		entries = palloc(sizeof(ClusterExtensionEntry) * Max(num_entries, 1));
+		memcpy(entries, clusterextensions_ss->entries,
+			   sizeof(ClusterExtensionEntry) * num_entries);
+		LWLockRelease(clusterextensions_ss->lock);
+
+		funcctx->user_fctx = entries;
+		funcctx->max_calls = num_entries;
+
+		MemoryContextSwitchTo(oldcontext);
+	}
+
+	funcctx = SRF_PERCALL_SETUP();
+	entries = (ClusterExtensionEntry *) funcctx->user_fctx;
+
+	while (funcctx->call_cntr < funcctx->max_calls)
+	{
+		ClusterExtensionEntry *entry = &entries[funcctx->call_cntr++];
+		char	   *dbname;
+		Datum		values[4];
+		bool		nulls[4];
+		HeapTuple	tuple;
+
+		/* Skip databases dropped since the latest lookup */
+		dbname = get_database_name(entry->dboid);
+		if (dbname == NULL)
+			continue;
+
+		memset(values, 0, sizeof(values));
+		memset(nulls, 0, sizeof(nulls));
+
+		/* database */
+		values[0] = DirectFunctionCall1(namein, CStringGetDatum(dbname));
+		/* name */
+		values[1] = NameGetDatum(&entry->name);
+		/* version */
+		if (entry->version[0] == '\0')
+			nulls[2] = true;
+		else
+			values[2] = CStringGetTextDatum(entry->version);
+		/* refreshed_at */
+		values[3] = TimestampTzGetDatum(entry->refreshed_at);
+
+		tuple = heap_form_tuple(funcctx->tuple_desc, values, nulls);
+		SRF_RETURN_NEXT(funcctx, HeapTupleGetDatum(tuple));
+	}
+
+	SRF_RETURN_DONE(funcctx);
+}
+
+static void
+clusterextensions_shmem_startup(void)
+{
+	bool		found;
+
+	if (prev_shmem_startup_hook)
+		prev_shmem_startup_hook();
+
+	LWLockAcquire(AddinShmemInitLock, LW_EXCLUSIVE);
+	clusterextensions_ss = ShmemInitStruct(cluster_extensions_shmem_name,
+										   clusterextensions_shared_memsize(),
+										   &found);
+
+	if (!found)
+	{
+		clusterextensions_ss->lock = &(GetNamedLWLockTranche(cluster_extensions_shmem_name))->lock;
+		clusterextensions_ss->num_entries = 0;
+	}
+
+	LWLockRelease(AddinShmemInitLock);
+}
+
+#if (PG_VERSION_NUM >= 150000)
+static void
+clusterextensions_shmem_request(void)
+{
+	if (prev_shmem_request_hook)
+		prev_shmem_request_hook();
+
+	RequestNamedLWLockTranche(cluster_extensions_shmem_name, 1);
+	RequestAddinShmemSpace(clusterextensions_shared_memsize());
+}
+#endif
+
+static Size
+clusterextensions_shared_memsize(void)
+{
+	Size		size;
+
+	size = MAXALIGN(offsetof(ClusterExtensionsSharedState, entries));
+	size = add_size(size, mul_size(cluster_extensions_max_entries,
+								   sizeof(ClusterExtensionEntry)));
+
+	return size;
+}
@@ -0,0 +1,729 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * Custom configuration parameters for trusted language extensions.
+ *
+ * Extensions written in C define their configuration parameters in _PG_init,
+ * i.e. every time their shared library is loaded into a backend. A TLE has
+ * no shared library, so the parameters registered with
+ * pgtle.define_custom_guc are stored in the pgtle.custom_guc table instead,
+ * and pg_tle defines them in the backend the first time a statement is
+ * parsed in a database where pg_tle is installed. Until then, any value set
+ * for a parameter (e.g. with ALTER ROLE ... SET) is kept as a placeholder,
+ * and it is validated once the parameter is defined.
+ */
+#include "postgres.h"
+
+#include <float.h>
+#include <limits.h>
+
+#include "access/genam.h"
+#include "access/heapam.h"
+#include "access/htup_details.h"
+#include "access/xact.h"
+#include "catalog/namespace.h"
+#include "catalog/pg_authid.h"
+#include "catalog/pg_type.h"
+#include "executor/spi.h"
+#include "fmgr.h"
+#include "miscadmin.h"
+#include "parser/analyze.h"
+#include "utils/acl.h"
+#include "utils/builtins.h"
+#include "utils/guc.h"
+#include "utils/inval.h"
+#include "utils/lsyscache.h"
+#include "utils/memutils.h"
+#include "utils/rel.h"
+#include "utils/syscache.h"
+
+#include "compatibility.h"
+#include "constants.h"
+#include "customguc.h"
+#include "tleextension.h"
+
+#define CUSTOM_GUC_TABLE "custom_guc"
+
+/* Attribute numbers of the pgtle.custom_guc table */
+#define Anum_custom_guc_name			1
+#define Anum_custom_guc_type			2
+#define Anum_custom_guc_default_value	3
+#define Anum_custom_guc_description		4
+#define Anum_custom_guc_context			5
+
+typedef enum CustomGucType
+{
+	CUSTOM_GUC_BOOL,
+	CUSTOM_GUC_INT,
+	CUSTOM_GUC_REAL,
+	CUSTOM_GUC_STRING
+}			CustomGucType;
+
+void		customguc_init(void);
+
+static post_parse_analyze_hook_type prev_post_parse_analyze_hook = NULL;
+
+_POST_PARSE_ANALYZE_HOOK(customguc_post_parse_analyze);
+
+/* Whether the parameters in pgtle.custom_guc have been defined in this backend */
+static bool custom_gucs_loaded = false;
+static Oid	custom_guc_relid = InvalidOid;
+
+/* Whether pgtle.custom_guc was found missing the last time it was looked up */
+static bool custom_guc_table_missing = false;
+
+/*
+ * A parameter registered by the current transaction, which is defined in
+ * this backend when the transaction commits.
+ */
+typedef struct PendingCustomGuc
+{
+	char	   *name;
+	CustomGucType type;
+	char	   *defaultValue;
+	char	   *description;
+	GucContext	context;
+	SubTransactionId subid;
+}			PendingCustomGuc;
+
+/* Parameters registered by the current transaction, allocated in TopMemoryContext */
+static List *pending_custom_gucs = NIL;
+
+/* Names of the parameters defined in this backend, allocated in TopMemoryContext */
+static List *defined_custom_gucs = NIL;
+
+/*
+ * Prefixes that cannot be used by custom parameters: pg_tle's own, and those
+ * of the modules shipped with PostgreSQL that define parameters when they
+ * are loaded, which may happen after pg_tle has defined the custom ones.
+ */
+static const char *const reserved_custom_guc_prefixes[] = {
+	PG_TLE_NSPNAME,
+	"auth_delay",
+	"auto_explain",
+	"basic_archive",
+	"pg_prewarm",
+	"pg_stat_statements",
+	"pg_trgm",
+	"plperl",
+	"plpgsql",
+	"plpython",
+	"pltcl",
+	"postgres_fdw",
+	"sepgsql",
+	NULL
+};
+
+static void customguc_relcache_callback(Datum arg, Oid relid);
+static void customguc_relname_callback(Datum arg, int cacheid, uint32 hashvalue);
+static void customguc_xact_callback(XactEvent event, void *arg);
+static void customguc_subxact_callback(SubXactEvent event, SubTransactionId mySubid,
+									   SubTransactionId parentSubid, void *arg);
+static void free_pending_custom_guc(PendingCustomGuc * pending);
+static void load_custom_gucs(void);
+static void check_custom_guc_name(const char *name);
+static CustomGucType get_custom_guc_type(const char *typeStr);
+static GucContext get_custom_guc_context(const char *contextStr);
+static void check_custom_guc_value(const char *name, CustomGucType type, const char *value);
+static void define_custom_guc_variable(const char *name, CustomGucType type,
+									   const char *defaultValue, const char *description,
+									   GucContext context);
+
+void
+customguc_init(void)
+{
+	prev_post_parse_analyze_hook = post_parse_analyze_hook;
+	post_parse_analyze_hook = customguc_post_parse_analyze;
+
+	/*
+	 * pgtle.define_custom_guc sends a relcache invalidation for
+	 * pgtle.custom_guc, so other backends know to look for new parameters.
+	 */
+	CacheRegisterRelcacheCallback(customguc_relcache_callback, (Datum) 0);
+
+	/*
+	 * When pgtle.custom_guc does not exist, look for it again only once a
+	 * relation may have been created.
+	 */
+	CacheRegisterSyscacheCallback(RELNAMENSP, customguc_relname_callback, (Datum) 0);
+
+	/*
+	 * Parameters registered by pgtle.define_custom_guc are only defined in
+	 * this backend once the transaction commits.
+	 */
+	RegisterXactCallback(customguc_xact_callback, NULL);
+	RegisterSubXactCallback(customguc_subxact_callback, NULL);
+}
+
+/*
+ * customguc_post_parse_analyze
+ *
+ * Defines the parameters in pgtle.custom_guc before the statement is executed.
+ */
+_POST_PARSE_ANALYZE_HOOK(customguc_post_parse_analyze)
+{
+	if (prev_post_parse_analyze_hook)
+		_prev_post_parse_analyze_hook;
+
+	/*
+	 * Relcache lookups must be done in a transaction state, e.g. not while
+	 * parsing ROLLBACK of an aborted transaction.
+	 */
+	if (!custom_gucs_loaded && IsTransactionState())
+		load_custom_gucs();
+}
+
+static void
+customguc_relcache_callback(Datum arg, Oid relid)
+{
+	if (relid == InvalidOid || relid == custom_guc_relid)
+		custom_gucs_loaded = false;
+}
+
+static void
+customguc_relname_callback(Datum arg, int cacheid, uint32 hashvalue)
+{
+	if (custom_guc_table_missing)
+	{
+		custom_guc_table_missing = false;
+		custom_gucs_loaded = false;
+	}
+}
+
+/*
+ * customguc_xact_callback
+ *
+ * Defines the parameters registered by the transaction before it commits, so
+ * that a parameter that cannot be defined aborts the transaction. Parameters
+ * registered by an aborted transaction are forgotten, and so are those of a
+ * prepared transaction, which are loaded like the ones registered by other
+ * backends once it is committed.
+ */
+static void
+customguc_xact_callback(XactEvent event, void *arg)
+{
+	List	   *pending;
+	ListCell   *lc;
+
+	switch (event)
+	{
+		case XACT_EVENT_PRE_COMMIT:
+			pending = pending_custom_gucs;
+			pending_custom_gucs = NIL;
+			foreach(lc, pending)
+			{
+				PendingCustomGuc *p = (PendingCustomGuc *) lfirst(lc);
+
+				define_custom_guc_variable(p->name, p->type, p->defaultValue,
+										   p->description, p->context);
+				free_pending_custom_guc(p);
+			}
+			list_free(pending);
+			break;
+		case XACT_EVENT_PRE_PREPARE:
+		case XACT_EVENT_ABORT:
+		case XACT_EVENT_PARALLEL_ABORT:
+			foreach(lc, pending_custom_gucs)
+				free_pending_custom_guc((PendingCustomGuc *) lfirst(lc));
+			list_free(pending_custom_gucs);
+			pending_custom_gucs = NIL;
+			break;
+		default:
+			break;
+	}
+}
+
+/*
+ * customguc_subxact_callback
+ *
+ * Forgets the parameters registered by an aborted subtransaction, and hands
+ * the parameters registered by a committed subtransaction to its parent.
+ */
+static void
+customguc_subxact_callback(SubXactEvent event, SubTransactionId mySubid,
+						   SubTransactionId parentSubid, void *arg)
+{
+	List	   *kept = NIL;
+	ListCell   *lc;
+	MemoryContext oldcontext;
+
+	if (event != SUBXACT_EVENT_ABORT_SUB && event != SUBXACT_EVENT_COMMIT_SUB)
+		return;
+
+	oldcontext = MemoryContextSwitchTo(TopMemoryContext);
+	foreach(lc, pending_custom_gucs)
+	{
+		PendingCustomGuc *p = (PendingCustomGuc *) lfirst(lc);
+
+		if (p->subid != mySubid)
+			kept = lappend(kept, p);
+		else if (event == SUBXACT_EVENT_COMMIT_SUB)
+		{
+			p->subid = parentSubid;
+			kept = lappend(kept, p);
+		}
+		else
+			free_pending_custom_guc(p);
+	}
+	MemoryContextSwitchTo(oldcontext);
+
+	list_free(pending_custom_gucs);
+	pending_custom_gucs = kept;
+}
+
+static void
+free_pending_custom_guc(PendingCustomGuc * pending)
+{
+	pfree(pending->name);
+	pfree(pending->defaultValue);
+	pfree(pending->description);
+	pfree(pending);
+}
+
+/*
+ * load_custom_gucs
+ *
+ * Defines all the parameters in pgtle.custom_guc that are not yet defined in this backend.
+ * Does nothing if pg_tle is not installed in the current database.
+ */
+static void
+load_custom_gucs(void)
+{
+	Oid			namespaceOid;
+	Relation	rel;
+	TupleDesc	tupdesc;
+	SysScanDesc scan;
+	HeapTuple	tuple;
+
+	namespaceOid = get_namespace_oid(PG_TLE_NSPNAME, true);
+	if (OidIsValid(namespaceOid))
+		custom_guc_relid = get_relname_relid(CUSTOM_GUC_TABLE, namespaceOid);
+	else
+		custom_guc_relid = InvalidOid;
+
+	if (!OidIsValid(custom_guc_relid))
+	{
+		/* there is nothing to load until the table is created */
+		custom_guc_table_missing = true;
+		custom_gucs_loaded = true;
+		return;
+	}
+
+	/*
+	 * Scan the table directly instead of going through SPI, so we do not take
+	 * a transaction snapshot before the statement does (e.g. SET TRANSACTION
+	 * ISOLATION LEVEL must be called before any query).
+	 */
+	rel = table_open(custom_guc_relid, AccessShareLock);
+	tupdesc = RelationGetDescr(rel);
+	scan = systable_beginscan(rel, InvalidOid, false, NULL, 0, NULL);
+
+	while (HeapTupleIsValid(tuple = systable_getnext(scan)))
+	{
+		char	   *values[Anum_custom_guc_context];
+		int			i;
+
+		/*
+		 * Parameters registered by the current transaction are defined when
+		 * it commits.
+		 */
+		if (TransactionIdIsCurrentTransactionId(HeapTupleHeaderGetXmin(tuple->t_data)))
+			continue;
+
+		for (i = 0; i < Anum_custom_guc_context; i++)
+		{
+			bool		isnull;
+			Datum		datum = heap_getattr(tuple, i + 1, tupdesc, &isnull);
+
+			if (isnull)
+				elog(ERROR, "unexpected null value in \"%s.%s\"",
+					 PG_TLE_NSPNAME, CUSTOM_GUC_TABLE);
+			values[i] = TextDatumGetCString(datum);
+		}
+
+		define_custom_guc_variable(values[Anum_custom_guc_name - 1],
+								   get_custom_guc_type(values[Anum_custom_guc_type - 1]),
+								   values[Anum_custom_guc_default_value - 1],
+								   values[Anum_custom_guc_description - 1],
+								   get_custom_guc_context(values[Anum_custom_guc_context - 1]));
+	}
+
+	systable_endscan(scan);
+	table_close(rel, AccessShareLock);
+
+	custom_gucs_loaded = true;
+}
+
+/*
+ * check_custom_guc_name
+ *
+ * Custom parameter names must be two or more simple identifiers separated by dots, and
+ * must not use a reserved prefix.
+ */
+static void
+check_custom_guc_name(const char *name)
+{
+	const char *p;
+	bool		saw_sep = false;
+	bool		name_start = true;
+	bool		valid = true;
+	size_t		prefix_len;
+	int			i;
+
+	for (p = name; *p && valid; p++)
+	{
+		if (*p == GUC_QUALIFIER_SEPARATOR)
+		{
+			/* empty name component */
+			if (name_start)
+				valid = false;
+			saw_sep = true;
+			name_start = true;
+		}
+		else if (strchr("ABCDEFGHIJKLMNOPQRSTUVWXYZ"
+						"abcdefghijklmnopqrstuvwxyz_", *p) != NULL ||
+				 IS_HIGHBIT_SET(*p))
+			name_start = false;
+		else if (!name_start && strchr("0123456789$", *p) != NULL)
+			 /* okay as non-first character */ ;
+		else
+			valid = false;
+	}
+
+	if (!valid || name_start || !saw_sep)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_NAME),
+				 errmsg("invalid configuration parameter name \"%s\"", name),
+				 errdetail("Custom parameter names must be two or more simple identifiers separated by dots.")));
+
+	prefix_len = strchr(name, GUC_QUALIFIER_SEPARATOR) - name;
+	for (i = 0; reserved_custom_guc_prefixes[i] != NULL; i++)
+	{
+		const char *prefix = reserved_custom_guc_prefixes[i];
+
+		if (strlen(prefix) == prefix_len &&
+			pg_strncasecmp(name, prefix, prefix_len) == 0)
+			ereport(ERROR,
+					(errcode(ERRCODE_INVALID_NAME),
+					 errmsg("invalid configuration parameter name \"%s\"", name),
+					 errdetail("\"%s\" is a reserved prefix.", prefix)));
+	}
+}
+
+static CustomGucType
+get_custom_guc_type(const char *typeStr)
+{
+	if (strcmp(typeStr, "bool") == 0)
+		return CUSTOM_GUC_BOOL;
+	if (strcmp(typeStr, "integer") == 0)
+		return CUSTOM_GUC_INT;
+	if (strcmp(typeStr, "real") == 0)
+		return CUSTOM_GUC_REAL;
+	if (strcmp(typeStr, "string") == 0)
+		return CUSTOM_GUC_STRING;
+
+	ereport(ERROR,
+			(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
+			 errmsg("invalid configuration parameter type \"%s\"", typeStr),
+			 errhint("Valid types are \"bool\", \"integer\", \"real\" and \"string\".")));
+	return CUSTOM_GUC_STRING;	/* keep compiler quiet */
+}
+
+static GucContext
+get_custom_guc_context(const char *contextStr)
+{
+	if (strcmp(contextStr, "user") == 0)
+		return PGC_USERSET;
+	if (strcmp(contextStr, "superuser") == 0)
+		return PGC_SUSET;
+	if (strcmp(contextStr, "sighup") == 0)
+		return PGC_SIGHUP;
+
+	ereport(ERROR,
+			(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
+			 errmsg("invalid configuration parameter context \"%s\"", contextStr),
+			 errhint("Valid contexts are \"user\", \"superuser\" and \"sighup\".")));
+	return PGC_USERSET;			/* keep compiler quiet */
+}
+
+/*
+ * check_custom_guc_value
+ *
+ * Raise an error if the value cannot be converted to a parameter of the given type.
+ */
+static void
+check_custom_guc_value(const char *name, CustomGucType type, const char *value)
+{
+	const char *hintmsg = NULL;
+	bool		boolval;
+	int			intval;
+	double		realval;
+
+	switch (type)
+	{
+		case CUSTOM_GUC_BOOL:
+			if (!parse_bool(value, &boolval))
+				ereport(ERROR,
+						(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
+						 errmsg("parameter \"%s\" requires a Boolean value", name)));
+			break;
+		case CUSTOM_GUC_INT:
+			if (!parse_int(value, &intval, 0, &hintmsg))
+				ereport(ERROR,
+						(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
+						 errmsg("invalid value for parameter \"%s\": \"%s\"", name, value),
+						 hintmsg ? errhint("%s", _(hintmsg)) : 0));
+			break;
+		case CUSTOM_GUC_REAL:
+			if (!parse_real(value, &realval, 0, &hintmsg))
+				ereport(ERROR,
+						(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
+						 errmsg("invalid value for parameter \"%s\": \"%s\"", name, value),
+						 hintmsg ? errhint("%s", _(hintmsg)) : 0));
+			break;
+		case CUSTOM_GUC_STRING:
+			break;
+	}
+}
+
+/*
+ * define_custom_guc_variable
+ *
+ * Defines a parameter in this backend, unless it is already defined. The default value
+ * must have been checked with check_custom_guc_value.
+ *
+ * A parameter that a module loaded into this backend has already defined is skipped with
+ * a warning. Raising an error instead would make every statement fail until the
+ * parameter is removed from pgtle.custom_guc, including the one removing it.
+ */
+static void
+define_custom_guc_variable(const char *name, CustomGucType type, const char *defaultValue,
+						   const char *description, GucContext context)
+{
+	ListCell   *lc;
+	char	   *gucName;
+	char	   *gucDescription;
+	MemoryContext oldcontext;
+
+	foreach(lc, defined_custom_gucs)
+	{
+		if (pg_strcasecmp((char *) lfirst(lc), name) == 0)
+			return;
+	}
+
+	if ((GetConfigOptionFlags(name, true) & GUC_CUSTOM_PLACEHOLDER) == 0 &&
+		GetConfigOption(name, true, false) != NULL)
+	{
+		ereport(WARNING,
+				(errcode(ERRCODE_DUPLICATE_OBJECT),
+				 errmsg("configuration parameter \"%s\" is already defined by another module", name),
+				 errdetail("The parameter in \"%s.%s\" is not defined.",
+						   PG_TLE_NSPNAME, CUSTOM_GUC_TABLE)));
+		return;
+	}
+
+	/* The GUC machinery keeps pointers to these, so they must not go away */
+	gucName = MemoryContextStrdup(TopMemoryContext, name);
+	gucDescription = MemoryContextStrdup(TopMemoryContext, description);
+
+	switch (type)
+	{
+		case CUSTOM_GUC_BOOL:
+			{
+				bool	   *value = MemoryContextAlloc(TopMemoryContext, sizeof(bool));
+				bool		bootValue = false;
+
+				(void) parse_bool(defaultValue, &bootValue);
+				DefineCustomBoolVariable(gucName, gucDescription, NULL, value,
+										 bootValue, context, 0, NULL, NULL, NULL);
+				break;
+			}
+		case CUSTOM_GUC_INT:
+			{
+				int		   *value = MemoryContextAlloc(TopMemoryContext, sizeof(int));
+				int			bootValue = 0;
+
+				(void) parse_int(defaultValue, &bootValue, 0, NULL);
+				DefineCustomIntVariable(gucName, gucDescription, NULL, value,
+										bootValue, INT_MIN, INT_MAX, context, 0,
+										NULL, NULL, NULL);
+				break;
+			}
+		case CUSTOM_GUC_REAL:
+			{
+				double	   *value = MemoryContextAlloc(TopMemoryContext, sizeof(double));
+				double		bootValue = 0;
+
+				(void) parse_real(defaultValue, &bootValue, 0, NULL);
+				DefineCustomRealVariable(gucName, gucDescription, NULL, value,
+										 bootValue, -DBL_MAX, DBL_MAX, context, 0,
+										 NULL, NULL, NULL);
+				break;
+			}
+		case CUSTOM_GUC_STRING:
+			{
+				char	  **value = MemoryContextAllocZero(TopMemoryContext, sizeof(char *));
+
+				DefineCustomStringVariable(gucName, gucDescription, NULL, value,
+										   MemoryContextStrdup(TopMemoryContext, defaultValue),
+										   context, 0, NULL, NULL, NULL);
+				break;
+			}
+	}
+
+	oldcontext = MemoryContextSwitchTo(TopMemoryContext);
+	defined_custom_gucs = lappend(defined_custom_gucs, gucName);
+	MemoryContextSwitchTo(oldcontext);
+}
+
+/*
+ * pg_tle_define_custom_guc
+ *
+ * Registers a configuration parameter in pgtle.custom_guc and defines it in the current
+ * backend when the transaction commits. Other backends connected to this database define
+ * it before running their next statement.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_define_custom_guc);
+Datum
+pg_tle_define_custom_guc(PG_FUNCTION_ARGS)
+{
+	char	   *name = text_to_cstring(PG_GETARG_TEXT_PP(0));
+	char	   *typeStr = text_to_cstring(PG_GETARG_TEXT_PP(1));
+	char	   *defaultValue = text_to_cstring(PG_GETARG_TEXT_PP(2));
+	char	   *description = text_to_cstring(PG_GETARG_TEXT_PP(3));
+	char	   *contextStr = text_to_cstring(PG_GETARG_TEXT_PP(4));
+	CustomGucType type;
+	GucContext	context;
+	Oid			tleadminoid;
+	Oid			namespaceOid;
+	Oid			save_userid;
+	int			save_sec_context;
+	char	   *query;
+	int			ret;
+	Oid			argtypes[5] = {TEXTOID, TEXTOID, TEXTOID, TEXTOID, TEXTOID};
+	Datum		args[5];
+	PendingCustomGuc *pending;
+	MemoryContext oldcontext;
+
+	/*
+	 * Even though the SQL function is locked down so only a member of
+	 * pgtle_admin can run this function, let's check and make sure there is
+	 * not a way to bypass that
+	 */
+	tleadminoid = get_role_oid(PG_TLE_ADMIN, false);
+	CHECK_CAN_SET_ROLE(GetUserId(), tleadminoid);
+
+	check_custom_guc_name(name);
+	type = get_custom_guc_type(typeStr);
+	context = get_custom_guc_context(contextStr);
+	check_custom_guc_value(name, type, defaultValue);
+
+	namespaceOid = get_namespace_oid(PG_TLE_NSPNAME, false);
+	custom_guc_relid = get_relname_relid(CUSTOM_GUC_TABLE, namespaceOid);
+	if (!OidIsValid(custom_guc_relid))
+		elog(ERROR, "relation \"%s.%s\" does not exist", PG_TLE_NSPNAME, CUSTOM_GUC_TABLE);
+
+	/*
+	 * pgtle_admin cannot modify pgtle.custom_guc directly, so that every
+	 * parameter in it has been validated.
+	 */
+	GetUserIdAndSecContext(&save_userid, &save_sec_context);
+	SetUserIdAndSecContext(BOOTSTRAP_SUPERUSERID,
+						   save_sec_context | SECURITY_LOCAL_USERID_CHANGE);
+
+	if (SPI_connect() != SPI_OK_CONNECT)
+		elog(ERROR, "SPI_connect failed");
+
+	args[0] = CStringGetTextDatum(name);
+	query = psprintf("SELECT 1 FROM %s.%s WHERE name OPERATOR(pg_catalog.=) $1",
+					 quote_identifier(PG_TLE_NSPNAME), quote_identifier(CUSTOM_GUC_TABLE));
+	ret = SPI_execute_with_args(query, 1, argtypes, args, NULL, true, 0);
+	if (ret != SPI_OK_SELECT)
+		elog(ERROR, "failed to query \"%s.%s\"", PG_TLE_NSPNAME, CUSTOM_GUC_TABLE);
+
+	if (SPI_processed > 0)
+		ereport(ERROR,
+				(errcode(ERRCODE_DUPLICATE_OBJECT),
+				 errmsg("configuration parameter \"%s\" is already defined", name)));
+
+	/*
+	 * Reject a name that is already used by another module. Placeholders for
+	 * values set before the parameter is defined are not shown in
+	 * pg_settings.
+	 */
+	query = "SELECT 1 FROM pg_catalog.pg_settings "
+		"WHERE pg_catalog.lower(name) OPERATOR(pg_catalog.=) pg_catalog.lower($1)";
+	ret = SPI_execute_with_args(query, 1, argtypes, args, NULL, true, 0);
+	if (ret != SPI_OK_SELECT)
+		elog(ERROR, "failed to query \"pg_catalog.pg_settings\"");
+
+	if (SPI_processed > 0)
+		ereport(ERROR,
+				(errcode(ERRCODE_DUPLICATE_OBJECT),
+				 errmsg("configuration parameter \"%s\" is already defined", name)));
+
+	/*
+	 * Reject a prefix that is used by a module loaded into this backend, as
+	 * the module may define the same parameter in other backends.
+	 */
+	query = psprintf("SELECT 1 FROM pg_catalog.pg_settings s "
+					 "WHERE pg_catalog.lower(pg_catalog.split_part(s.name, '.', 1)) "
+					 "OPERATOR(pg_catalog.=) pg_catalog.lower(pg_catalog.split_part($1, '.', 1)) "
+					 "AND NOT EXISTS (SELECT 1 FROM %s.%s c "
+					 "WHERE pg_catalog.lower(c.name) OPERATOR(pg_catalog.=) pg_catalog.lower(s.name))",
+					 quote_identifier(PG_TLE_NSPNAME), quote_identifier(CUSTOM_GUC_TABLE));
+	ret = SPI_execute_with_args(query, 1, argtypes, args, NULL, true, 0);
+	if (ret != SPI_OK_SELECT)
+		elog(ERROR, "failed to query \"pg_catalog.pg_settings\"");
+
+	if (SPI_processed > 0)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_NAME),
+				 errmsg("invalid configuration parameter name \"%s\"", name),
+				 errdetail("The prefix is used by another module.")));
+
+	args[1] = CStringGetTextDatum(typeStr);
+	args[2] = CStringGetTextDatum(defaultValue);
+	args[3] = CStringGetTextDatum(description);
+	args[4] = CStringGetTextDatum(contextStr);
+	query = psprintf("INSERT INTO %s.%s VALUES ($1, $2, $3, $4, $5)",
+					 quote_identifier(PG_TLE_NSPNAME), quote_identifier(CUSTOM_GUC_TABLE));
+	ret = SPI_execute_with_args(query, 5, argtypes, args, NULL, false, 0);
+	if (ret != SPI_OK_INSERT)
+		elog(ERROR, "failed to insert into \"%s.%s\"", PG_TLE_NSPNAME, CUSTOM_GUC_TABLE);
+
+	SPI_finish();
+
+	SetUserIdAndSecContext(save_userid, save_sec_context);
+
+	/* Define the parameter in this backend when the transaction commits */
+	oldcontext = MemoryContextSwitchTo(TopMemoryContext);
+	pending = palloc(sizeof(PendingCustomGuc));
+	pending->name = pstrdup(name);
+	pending->type = type;
+	pending->defaultValue = pstrdup(defaultValue);
+	pending->description = pstrdup(description);
+	pending->context = context;
+	pending->subid = GetCurrentSubTransactionId();
+	pending_custom_gucs = lappend(pending_custom_gucs, pending);
+	MemoryContextSwitchTo(oldcontext);
+
+	/* Let other backends know there is a new parameter */
+	CacheInvalidateRelcacheByRelid(custom_guc_relid);
+
+	PG_RETURN_VOID();
+}
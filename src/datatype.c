@@ -15,35 +15,67 @@
  */
 #include "postgres.h"
 
+#include <math.h>
+
+#if PG_VERSION_NUM >= 130000
+#include "access/detoast.h"
+#endif
 #include "access/genam.h"
 #include "access/heapam.h"
 #include "access/htup_details.h"
+#include "access/stratnum.h"
+#if PG_VERSION_NUM < 130000
+#include "access/tuptoaster.h"
+#endif
 #include "access/xact.h"
+#include "catalog/dependency.h"
+#include "catalog/indexing.h"
+#include "catalog/pg_am.h"
 #include "catalog/pg_authid.h"
 #include "catalog/pg_cast.h"
+#include "catalog/pg_depend.h"
+#include "catalog/pg_enum.h"
 #include "catalog/pg_language.h"
 #include "catalog/pg_namespace.h"
+#include "catalog/pg_opclass.h"
+#include "catalog/pg_operator.h"
+#include "catalog/pg_opfamily.h"
 #include "catalog/pg_proc.h"
 #include "catalog/pg_type.h"
+#include "commands/defrem.h"
 #include "commands/typecmds.h"
+#include "commands/vacuum.h"
 #include "fmgr.h"
+#include "libpq/pqformat.h"
 #include "miscadmin.h"
+#include "nodes/makefuncs.h"
 #include "parser/parse_func.h"
+#include "parser/parse_node.h"
 #include "utils/acl.h"
+#include "utils/array.h"
 #include "utils/builtins.h"
 #include "utils/fmgroids.h"
+#include "utils/hsearch.h"
+#include "utils/inval.h"
 #include "utils/lsyscache.h"
+#include "utils/memutils.h"
+#include "utils/regproc.h"
 #include "utils/syscache.h"
 
 #include "compatibility.h"
 #include "constants.h"
 #include "tleextension.h"
 
+/*
+ * Values wider than this are not passed to user-defined analyze functions.
+ * Matches the threshold that analyze.c uses for the standard statistics.
+ */
+#define WIDTH_THRESHOLD  1024
 
 /* Local functions */
 static void check_is_pgtle_admin(void);
 static bool create_shell_type(Oid typeNamespace, const char *typeName, bool if_not_exists);
-static Oid	create_c_func_internal(Oid namespaceId, Oid funcid,
+static Oid	create_c_func_internal(Oid namespaceId, Oid funcid, char *funcname,
 								   oidvector *parameterTypes, Oid prorettype, char *prosrc,
 								   char *probin);
 static Oid	find_user_defined_func(List *procname, bool typeInput);
@@ -57,6 +89,26 @@ static Oid	get_type_func_argtype(bool typeInput);
 static Oid	get_type_func_rettype(bool typeInput);
 static char get_type_alignment(char *alignmentStr);
 static char get_type_storage(char *storageStr);
+static bool create_enum_type(Oid typeNamespace, const char *typeName, ArrayType *labels, bool if_not_exists);
+static bool add_enum_label(Oid typeNamespace, const char *typeName, const char *label,
+						   const char *neighbor, bool isAfter, bool if_not_exists);
+static Datum add_enum_label_internal(FunctionCallInfo fcinfo, bool if_not_exists);
+static bool create_range_type(FunctionCallInfo fcinfo, bool if_not_exists);
+static bool is_pgtle_operator_func(Oid funcid);
+static void check_user_trusted_func(Oid funcid);
+static void set_opclass_owner(Oid opclassOid, Oid ownerId);
+static bool is_pgtle_base_type(Oid typeOid);
+static CoercionContext get_cast_context(char *contextStr);
+static bool create_cast(Oid sourceTypeOid, Oid targetTypeOid, Oid funcOid,
+						char *contextStr, bool if_not_exists);
+static Oid	find_user_binary_func(List *procname, bool typeReceive);
+static void check_user_binary_func(Oid funcid, Oid typeOid, Oid expectedNamespace, bool typeReceive);
+static void check_user_typmod_func(Oid funcid, Oid expectedNamespace, bool typmodIn);
+static Oid	find_user_typmod_func(Oid cfuncid);
+static Oid	find_user_analyze_func(List *procname);
+static void check_user_analyze_func(Oid funcid, Oid expectedNamespace);
+static void tle_compute_stats(VacAttrStats *stats, AnalyzeAttrFetchFunc fetchfunc,
+							  int samplerows, double totalrows);
 static Datum
 			pg_tle_create_base_type_internal(Oid typeNamespace,
 											 char *typeName,
@@ -65,6 +117,8 @@ static Datum
 											 int16 internalLength,
 											 char *alignmentStr,
 											 char *storageStr,
+											 char *defaultValue,
+											 Oid analyzeFuncId,
 											 char *funcProbin);
 
 static void
@@ -180,7 +234,7 @@ pg_tle_create_base_type(PG_FUNCTION_ARGS)
 	char	   *storage = "plain";	/* default TOAST storage method */
 	char	   *funcProbin = get_probin(fcinfo->flinfo->fn_oid);
 
-	return pg_tle_create_base_type_internal(typeNamespace, typeName, inputFuncId, outputFuncId, internalLength, alignment, storage, funcProbin);
+	return pg_tle_create_base_type_internal(typeNamespace, typeName, inputFuncId, outputFuncId, internalLength, alignment, storage, NULL, InvalidOid, funcProbin);
 }
 
 /*
@@ -203,7 +257,51 @@ pg_tle_create_base_type_with_storage(PG_FUNCTION_ARGS)
 	char	   *storage = text_to_cstring(PG_GETARG_TEXT_P(6));
 	char	   *funcProbin = get_probin(fcinfo->flinfo->fn_oid);
 
-	return pg_tle_create_base_type_internal(typeNamespace, typeName, inputFuncId, outputFuncId, internalLength, alignment, storage, funcProbin);
+	return pg_tle_create_base_type_internal(typeNamespace, typeName, inputFuncId, outputFuncId, internalLength, alignment, storage, NULL, InvalidOid, funcProbin);
+}
+
+/*
+ * pg_tle_create_base_type_with_options
+ *
+ * Similar to pg_tle_create_base_type_with_storage, but expects additional
+ * optional arguments for the default value and the analyze function.
+ *
+ */
+PG_FUNCTION_INFO_V1(pg_tle_create_base_type_with_options);
+Datum
+pg_tle_create_base_type_with_options(PG_FUNCTION_ARGS)
+{
+	Oid			typeNamespace;
+	char	   *typeName;
+	Oid			inputFuncId;
+	Oid			outputFuncId;
+	int16		internalLength;
+	char	   *alignment;
+	char	   *storage;
+	char	   *defaultValue = NULL;
+	Oid			analyzeFuncId = InvalidOid;
+	char	   *funcProbin = get_probin(fcinfo->flinfo->fn_oid);
+
+	if (PG_ARGISNULL(0) || PG_ARGISNULL(1) || PG_ARGISNULL(2) || PG_ARGISNULL(3) ||
+		PG_ARGISNULL(4) || PG_ARGISNULL(5) || PG_ARGISNULL(6))
+		ereport(ERROR,
+				(errcode(ERRCODE_NULL_VALUE_NOT_ALLOWED),
+				 errmsg("typenamespace, typename, infunc, outfunc, internallength, alignment and storage must not be NULL")));
+
+	typeNamespace = PG_GETARG_OID(0);
+	typeName = NameStr(*PG_GETARG_NAME(1));
+	inputFuncId = PG_GETARG_OID(2);
+	outputFuncId = PG_GETARG_OID(3);
+	internalLength = PG_GETARG_INT16(4);
+	alignment = text_to_cstring(PG_GETARG_TEXT_PP(5));
+	storage = text_to_cstring(PG_GETARG_TEXT_PP(6));
+
+	if (!PG_ARGISNULL(7))
+		defaultValue = text_to_cstring(PG_GETARG_TEXT_PP(7));
+	if (!PG_ARGISNULL(8))
+		analyzeFuncId = PG_GETARG_OID(8);
+
+	return pg_tle_create_base_type_internal(typeNamespace, typeName, inputFuncId, outputFuncId, internalLength, alignment, storage, defaultValue, analyzeFuncId, funcProbin);
 }
 
 /*
@@ -220,11 +318,14 @@ pg_tle_create_base_type_internal(Oid typeNamespace,
 								 int16 internalLength,
 								 char *alignmentStr,
 								 char *storageStr,
+								 char *defaultValue,
+								 Oid analyzeFuncId,
 								 char *funcProbin)
 {
 	AclResult	aclresult;
 	Oid			inputOid;
 	Oid			outputOid;
+	Oid			analyzeOid = InvalidOid;
 	Oid			typeOid;
 	Oid			array_oid;
 	char	   *array_type;
@@ -317,12 +418,16 @@ pg_tle_create_base_type_internal(Oid typeNamespace,
 		aclcheck_error(ACLCHECK_NOT_OWNER, OBJECT_FUNCTION, get_func_name(inputFuncId));
 	if (!PG_PROC_OWNERCHECK(outputFuncId, GetUserId()))
 		aclcheck_error(ACLCHECK_NOT_OWNER, OBJECT_FUNCTION, get_func_name(outputFuncId));
+	if (OidIsValid(analyzeFuncId) && !PG_PROC_OWNERCHECK(analyzeFuncId, GetUserId()))
+		aclcheck_error(ACLCHECK_NOT_OWNER, OBJECT_FUNCTION, get_func_name(analyzeFuncId));
 
 	/*
 	 * Check the user-defined I/O functions meet pg_tle specific requirements.
 	 */
 	check_user_defined_func(inputFuncId, typeOid, typeNamespace, true);
 	check_user_defined_func(outputFuncId, typeOid, typeNamespace, false);
+	if (OidIsValid(analyzeFuncId))
+		check_user_analyze_func(analyzeFuncId, typeNamespace);
 
 	/*
 	 * Create C-version I/O functions.
@@ -332,17 +437,31 @@ pg_tle_create_base_type_internal(Oid typeNamespace,
 	 * argument of the base type and returns type CSTRING.
 	 */
 	inputFuncParamType = CSTRINGOID;
-	inputOid = create_c_func_internal(typeNamespace, inputFuncId,
+	inputOid = create_c_func_internal(typeNamespace, inputFuncId, NULL,
 									  buildoidvector(&inputFuncParamType, 1),
 									  typeOid, TLE_BASE_TYPE_IN,
 									  funcProbin);
 
 	outputFuncParamType = typeOid;
-	outputOid = create_c_func_internal(typeNamespace, outputFuncId,
+	outputOid = create_c_func_internal(typeNamespace, outputFuncId, NULL,
 									   buildoidvector(&outputFuncParamType, 1),
 									   CSTRINGOID, TLE_BASE_TYPE_OUT,
 									   funcProbin);
 
+	/*
+	 * C version analyze function accepts a single argument of type INTERNAL
+	 * and returns type BOOLEAN.
+	 */
+	if (OidIsValid(analyzeFuncId))
+	{
+		Oid			analyzeFuncParamType = INTERNALOID;
+
+		analyzeOid = create_c_func_internal(typeNamespace, analyzeFuncId, NULL,
+											buildoidvector(&analyzeFuncParamType, 1),
+											BOOLOID, TLE_BASE_TYPE_ANALYZE,
+											funcProbin);
+	}
+
 	/*
 	 * OK, we're done checking, time to make the type.  We must assign the
 	 * array type OID ahead of calling TypeCreate, since the base type and
@@ -376,12 +495,12 @@ pg_tle_create_base_type_internal(Oid typeNamespace,
 					InvalidOid, /* send procedure */
 					InvalidOid, /* typmodin procedure */
 					InvalidOid, /* typmodout procedure */
-					InvalidOid, /* analyze procedure */
+					analyzeOid, /* analyze procedure */
 					InvalidOid, /* element type ID */
 					false,		/* this is not an implicit array type */
 					array_oid,	/* array type we are about to create */
 					InvalidOid, /* base type ID (only for domains) */
-					NULL,		/* default type value */
+					defaultValue,	/* default type value */
 					NULL,		/* no binary form available */
 					false,		/* passed by value */
 					alignment,	/* required alignment */
@@ -392,6 +511,21 @@ pg_tle_create_base_type_internal(Oid typeNamespace,
 					InvalidOid);	/* type's collation */
 	Assert(typeOid == address.objectId);
 
+	/*
+	 * Unlike the I/O functions, the C analyze function does not reference the
+	 * type in its signature, so record a dependency on the type for DROP TYPE
+	 * ... CASCADE to drop it as well.
+	 */
+	if (OidIsValid(analyzeOid))
+	{
+		ObjectAddress funcAddress;
+
+		funcAddress.classId = ProcedureRelationId;
+		funcAddress.objectId = analyzeOid;
+		funcAddress.objectSubId = 0;
+		recordDependencyOn(&funcAddress, &address, DEPENDENCY_NORMAL);
+	}
+
 	/*
 	 * Create the array type that goes with it.
 	 */
@@ -699,14 +833,14 @@ pg_tle_base_type_out(PG_FUNCTION_ARGS)
 /*
  * create_c_func_internal
  *
- * Create a C function with the same name as input `funcid` (we should check the to-be-created
- * function doesn't exist yet before this function).
+ * Create a C function with the same name as input `funcid`, or with `funcname` if it is not NULL
+ * (we should check the to-be-created function doesn't exist yet before this function).
  *
  * A dependency between the newly-created C function and input `funcid` is recorded in pg_depend,
  * so that DROP ... CASCADE works as expected.
  */
 static Oid
-create_c_func_internal(Oid namespaceId, Oid funcid, oidvector *parameterTypes, Oid prorettype, char *prosrc, char *probin)
+create_c_func_internal(Oid namespaceId, Oid funcid, char *funcname, oidvector *parameterTypes, Oid prorettype, char *prosrc, char *probin)
 {
 	Oid			languageValidator;
 	HeapTuple	tuple;
@@ -714,7 +848,6 @@ create_c_func_internal(Oid namespaceId, Oid funcid, oidvector *parameterTypes, O
 	Form_pg_proc pg_func_tuple;
 	ObjectAddress address;
 	ObjectAddress userfunc;
-	char	   *funcname;
 	bool		prosecdef;
 	bool		proleakproof;
 	bool		proisstrict;
@@ -737,7 +870,8 @@ create_c_func_internal(Oid namespaceId, Oid funcid, oidvector *parameterTypes, O
 		elog(ERROR, "cache lookup failed for function %u", funcid);
 
 	pg_func_tuple = (Form_pg_proc) GETSTRUCT(tuple);
-	funcname = pstrdup(NameStr(pg_func_tuple->proname));
+	if (funcname == NULL)
+		funcname = pstrdup(NameStr(pg_func_tuple->proname));
 	prosecdef = pg_func_tuple->prosecdef;
 	proleakproof = pg_func_tuple->proleakproof;
 	proisstrict = pg_func_tuple->proisstrict;
@@ -908,7 +1042,7 @@ pg_tle_create_operator_func(PG_FUNCTION_ARGS)
 	for (i = 0; i < nargs; ++i)
 		argTypes[i] = typeOid;
 
-	create_c_func_internal(typeNamespace, funcOid,
+	create_c_func_internal(typeNamespace, funcOid, NULL,
 						   buildoidvector(argTypes, nargs),
 						   get_func_rettype(funcOid), TLE_OPERATOR_FUNC,
 						   get_probin(fcinfo->flinfo->fn_oid));
@@ -1160,3 +1294,2179 @@ get_type_storage(char *storageStr)
 			(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
 			 errmsg("storage \"%s\" not recognized", storageStr)));
 }
+
+/*
+ * create_enum_type
+ *
+ * Creates a new enum type with the given labels, returns true when the enum type is successfully created.
+ *
+ * if_not_exists: if true, don't fail on duplicate name, just print a notice and return false.
+ * Otherwise, fail on duplicate name.
+ */
+static bool
+create_enum_type(Oid typeNamespace, const char *typeName, ArrayType *labels, bool if_not_exists)
+{
+	AclResult	aclresult;
+	Oid			typeOid;
+	CreateEnumStmt *stmt;
+	Datum	   *elems;
+	bool	   *nulls;
+	int			nelems;
+	int			i;
+	List	   *vals = NIL;
+
+	/*
+	 * Even though the SQL function is locked down so only a member of
+	 * pgtle_admin can run this function, let's check and make sure there is
+	 * not a way to bypass that
+	 */
+	check_is_pgtle_admin();
+
+	/*
+	 * Check we have creation rights in target namespace
+	 */
+	aclresult = PG_NAMESPACE_ACLCHECK(typeNamespace, GetUserId(), ACL_CREATE);
+	if (aclresult != ACLCHECK_OK)
+		aclcheck_error(aclresult, OBJECT_SCHEMA,
+					   get_namespace_name(typeNamespace));
+
+	/*
+	 * Look to see if type already exists
+	 */
+	typeOid = GET_TYPE_OID(TYPENAMENSP,
+						   CStringGetDatum(typeName),
+						   ObjectIdGetDatum(typeNamespace));
+
+	if (OidIsValid(typeOid))
+	{
+		if (if_not_exists)
+		{
+			ereport(NOTICE,
+					(errcode(ERRCODE_DUPLICATE_OBJECT),
+					 errmsg("type \"%s\" already exists, skipping", typeName)));
+
+			return false;
+		}
+		else
+			ereport(ERROR,
+					(errcode(ERRCODE_DUPLICATE_OBJECT),
+					 errmsg("type \"%s\" already exists", typeName)));
+	}
+
+	deconstruct_array(labels,
+					  TEXTOID, -1, false, TYPALIGN_INT,
+					  &elems, &nulls, &nelems);
+
+	for (i = 0; i < nelems; i++)
+	{
+		if (nulls[i])
+			ereport(ERROR,
+					(errcode(ERRCODE_NULL_VALUE_NOT_ALLOWED),
+					 errmsg("enum label cannot be NULL")));
+
+		vals = lappend(vals, makeString(TextDatumGetCString(elems[i])));
+	}
+
+	/*
+	 * Let DefineEnum do the real work, it performs label validation and
+	 * creates the array type that goes with the enum.
+	 */
+	stmt = makeNode(CreateEnumStmt);
+	stmt->typeName = list_make2(makeString(get_namespace_name(typeNamespace)),
+								makeString(pstrdup(typeName)));
+	stmt->vals = vals;
+
+	DefineEnum(stmt);
+
+	return true;
+}
+
+/*
+ * Registers a new enum type, fail if the type already exists.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_create_enum_type);
+Datum
+pg_tle_create_enum_type(PG_FUNCTION_ARGS)
+{
+	create_enum_type(PG_GETARG_OID(0), NameStr(*PG_GETARG_NAME(1)), PG_GETARG_ARRAYTYPE_P(2), false);
+	PG_RETURN_VOID();
+}
+
+/*
+ * Registers a new enum type if not exists; Otherwise do nothing.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_create_enum_type_if_not_exists);
+Datum
+pg_tle_create_enum_type_if_not_exists(PG_FUNCTION_ARGS)
+{
+	PG_RETURN_BOOL(create_enum_type(PG_GETARG_OID(0), NameStr(*PG_GETARG_NAME(1)), PG_GETARG_ARRAYTYPE_P(2), true));
+}
+
+/*
+ * add_enum_label
+ *
+ * Adds a new label to an existing enum type, returns true when the label is successfully added.
+ * The new label is placed before or after `neighbor` if provided; otherwise it is placed at the end.
+ *
+ * if_not_exists: if true, don't fail on duplicate label, just print a notice and return false.
+ * Otherwise, fail on duplicate label.
+ */
+static bool
+add_enum_label(Oid typeNamespace, const char *typeName, const char *label,
+			   const char *neighbor, bool isAfter, bool if_not_exists)
+{
+	Oid			typeOid;
+#if PG_VERSION_NUM >= 120000
+	AlterEnumStmt *stmt;
+#endif
+
+	/*
+	 * Even though the SQL function is locked down so only a member of
+	 * pgtle_admin can run this function, let's check and make sure there is
+	 * not a way to bypass that
+	 */
+	check_is_pgtle_admin();
+
+	typeOid = GET_TYPE_OID(TYPENAMENSP,
+						   CStringGetDatum(typeName),
+						   ObjectIdGetDatum(typeNamespace));
+
+	if (!OidIsValid(typeOid))
+		ereport(ERROR,
+				(errcode(ERRCODE_UNDEFINED_OBJECT),
+				 errmsg("type \"%s\" does not exist", typeName)));
+
+	if (get_typtype(typeOid) != TYPTYPE_ENUM)
+		ereport(ERROR,
+				(errcode(ERRCODE_WRONG_OBJECT_TYPE),
+				 errmsg("%s is not an enum", format_type_be(typeOid))));
+
+	/*
+	 * Check we are the owner of the enum type.
+	 */
+	if (!PG_TYPE_OWNERCHECK(typeOid, GetUserId()))
+		aclcheck_error_type(ACLCHECK_NOT_OWNER, typeOid);
+
+	if (SearchSysCacheExists2(ENUMTYPOIDNAME,
+							  ObjectIdGetDatum(typeOid),
+							  CStringGetDatum(label)))
+	{
+		if (if_not_exists)
+		{
+			ereport(NOTICE,
+					(errcode(ERRCODE_DUPLICATE_OBJECT),
+					 errmsg("enum label \"%s\" already exists, skipping", label)));
+
+			return false;
+		}
+		else
+			ereport(ERROR,
+					(errcode(ERRCODE_DUPLICATE_OBJECT),
+					 errmsg("enum label \"%s\" already exists", label)));
+	}
+
+#if PG_VERSION_NUM < 120000
+	ereport(ERROR,
+			(errcode(ERRCODE_FEATURE_NOT_SUPPORTED),
+			 errmsg("adding enum labels requires PostgreSQL 12 or later")));
+
+	return false;
+#else
+	stmt = makeNode(AlterEnumStmt);
+	stmt->typeName = list_make2(makeString(get_namespace_name(typeNamespace)),
+								makeString(pstrdup(typeName)));
+	stmt->oldVal = NULL;
+	stmt->newVal = pstrdup(label);
+	stmt->newValNeighbor = neighbor ? pstrdup(neighbor) : NULL;
+	stmt->newValIsAfter = isAfter;
+	stmt->skipIfNewValExists = false;
+
+	AlterEnum(stmt);
+
+	return true;
+#endif
+}
+
+/*
+ * add_enum_label_internal
+ *
+ * Unpacks the arguments of pgtle.add_enum_label and pgtle.add_enum_label_if_not_exists.
+ * The SQL functions are not strict because `before_label` and `after_label` default to NULL.
+ */
+static Datum
+add_enum_label_internal(FunctionCallInfo fcinfo, bool if_not_exists)
+{
+	char	   *neighbor = NULL;
+	bool		isAfter = true;
+
+	if (PG_ARGISNULL(0) || PG_ARGISNULL(1) || PG_ARGISNULL(2))
+		ereport(ERROR,
+				(errcode(ERRCODE_NULL_VALUE_NOT_ALLOWED),
+				 errmsg("typenamespace, typename and label must not be NULL")));
+
+	if (!PG_ARGISNULL(3) && !PG_ARGISNULL(4))
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
+				 errmsg("cannot specify both before_label and after_label")));
+
+	if (!PG_ARGISNULL(3))
+	{
+		neighbor = text_to_cstring(PG_GETARG_TEXT_PP(3));
+		isAfter = false;
+	}
+	else if (!PG_ARGISNULL(4))
+		neighbor = text_to_cstring(PG_GETARG_TEXT_PP(4));
+
+	PG_RETURN_BOOL(add_enum_label(PG_GETARG_OID(0),
+								  NameStr(*PG_GETARG_NAME(1)),
+								  text_to_cstring(PG_GETARG_TEXT_PP(2)),
+								  neighbor, isAfter, if_not_exists));
+}
+
+/*
+ * Adds a new label to an enum type, fail if the label already exists.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_add_enum_label);
+Datum
+pg_tle_add_enum_label(PG_FUNCTION_ARGS)
+{
+	add_enum_label_internal(fcinfo, false);
+	PG_RETURN_VOID();
+}
+
+/*
+ * Adds a new label to an enum type if not exists; Otherwise do nothing.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_add_enum_label_if_not_exists);
+Datum
+pg_tle_add_enum_label_if_not_exists(PG_FUNCTION_ARGS)
+{
+	return add_enum_label_internal(fcinfo, true);
+}
+
+/*
+ * create_range_type
+ *
+ * Creates a new range type over an existing subtype, returns true when the range type is successfully created.
+ * The optional subtype_diff function may be written in any language the caller is allowed to execute,
+ * including trusted languages.
+ *
+ * if_not_exists: if true, don't fail on duplicate name, just print a notice and return false.
+ * Otherwise, fail on duplicate name.
+ */
+static bool
+create_range_type(FunctionCallInfo fcinfo, bool if_not_exists)
+{
+	Oid			typeNamespace;
+	char	   *typeName;
+	Oid			subtypeOid;
+	AclResult	aclresult;
+	Oid			typeOid;
+	CreateRangeStmt *stmt;
+	List	   *params = NIL;
+
+	if (PG_ARGISNULL(0) || PG_ARGISNULL(1) || PG_ARGISNULL(2))
+		ereport(ERROR,
+				(errcode(ERRCODE_NULL_VALUE_NOT_ALLOWED),
+				 errmsg("typenamespace, typename and subtype must not be NULL")));
+
+	typeNamespace = PG_GETARG_OID(0);
+	typeName = NameStr(*PG_GETARG_NAME(1));
+	subtypeOid = PG_GETARG_OID(2);
+
+	/*
+	 * Even though the SQL function is locked down so only a member of
+	 * pgtle_admin can run this function, let's check and make sure there is
+	 * not a way to bypass that
+	 */
+	check_is_pgtle_admin();
+
+	/*
+	 * Check we have creation rights in target namespace
+	 */
+	aclresult = PG_NAMESPACE_ACLCHECK(typeNamespace, GetUserId(), ACL_CREATE);
+	if (aclresult != ACLCHECK_OK)
+		aclcheck_error(aclresult, OBJECT_SCHEMA,
+					   get_namespace_name(typeNamespace));
+
+	/*
+	 * Look to see if type already exists
+	 */
+	typeOid = GET_TYPE_OID(TYPENAMENSP,
+						   CStringGetDatum(typeName),
+						   ObjectIdGetDatum(typeNamespace));
+
+	if (OidIsValid(typeOid))
+	{
+		if (if_not_exists)
+		{
+			ereport(NOTICE,
+					(errcode(ERRCODE_DUPLICATE_OBJECT),
+					 errmsg("type \"%s\" already exists, skipping", typeName)));
+
+			return false;
+		}
+		else
+			ereport(ERROR,
+					(errcode(ERRCODE_DUPLICATE_OBJECT),
+					 errmsg("type \"%s\" already exists", typeName)));
+	}
+
+	params = lappend(params, makeDefElem("subtype",
+										 (Node *) makeTypeNameFromOid(subtypeOid, -1),
+										 -1));
+
+	if (!PG_ARGISNULL(3))
+	{
+		Oid			diffFuncId = PG_GETARG_OID(3);
+		Oid		   *argTypes;
+		int			nargs;
+
+		/*
+		 * DefineRange looks up the subtype_diff function by name, so make
+		 * sure the function we were given is the one it will find.
+		 */
+		get_func_signature(diffFuncId, &argTypes, &nargs);
+		if (nargs != 2 || argTypes[0] != subtypeOid || argTypes[1] != subtypeOid)
+			ereport(ERROR,
+					(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+					 errmsg("range subtype diff function must accept two arguments of type %s",
+							format_type_be(subtypeOid))));
+
+		params = lappend(params, makeDefElem("subtype_diff",
+											 (Node *) get_qualified_funcname(diffFuncId),
+											 -1));
+	}
+
+	if (!PG_ARGISNULL(4))
+	{
+		char	   *opclass = text_to_cstring(PG_GETARG_TEXT_PP(4));
+
+		params = lappend(params, makeDefElem("subtype_opclass",
+											 (Node *) STRING_TO_QUALIFIED_NAME_LIST(opclass),
+											 -1));
+	}
+
+	/*
+	 * Let DefineRange do the real work, it creates the range constructor
+	 * functions and the array (and multirange) types that go with it.
+	 */
+	stmt = makeNode(CreateRangeStmt);
+	stmt->typeName = list_make2(makeString(get_namespace_name(typeNamespace)),
+								makeString(pstrdup(typeName)));
+	stmt->params = params;
+
+	DEFINE_RANGE(make_parsestate(NULL), stmt);
+
+	return true;
+}
+
+/*
+ * Registers a new range type, fail if the type already exists.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_create_range_type);
+Datum
+pg_tle_create_range_type(PG_FUNCTION_ARGS)
+{
+	create_range_type(fcinfo, false);
+	PG_RETURN_VOID();
+}
+
+/*
+ * Registers a new range type if not exists; Otherwise do nothing.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_create_range_type_if_not_exists);
+Datum
+pg_tle_create_range_type_if_not_exists(PG_FUNCTION_ARGS)
+{
+	PG_RETURN_BOOL(create_range_type(fcinfo, true));
+}
+
+/*
+ * pg_tle_create_operator
+ *
+ * Creates a new operator, this is similar to CREATE OPERATOR but the operator function
+ * is given by its signature so it can be checked against the argument types up front.
+ * A NULL leftarg creates a prefix operator.
+ *
+ * The SQL function is not strict because most of the arguments default to NULL.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_create_operator);
+Datum
+pg_tle_create_operator(PG_FUNCTION_ARGS)
+{
+	Oid			opNamespace;
+	char	   *opName;
+	Oid			leftType = InvalidOid;
+	Oid			rightType;
+	Oid			funcOid;
+	Oid		   *argTypes;
+	int			nargs;
+	AclResult	aclresult;
+	char	   *namespaceName;
+	List	   *params = NIL;
+
+	if (PG_ARGISNULL(0) || PG_ARGISNULL(1) || PG_ARGISNULL(3) || PG_ARGISNULL(4))
+		ereport(ERROR,
+				(errcode(ERRCODE_NULL_VALUE_NOT_ALLOWED),
+				 errmsg("opnamespace, opname, rightarg and opfunc must not be NULL")));
+
+	opNamespace = PG_GETARG_OID(0);
+	opName = text_to_cstring(PG_GETARG_TEXT_PP(1));
+	if (!PG_ARGISNULL(2))
+		leftType = PG_GETARG_OID(2);
+	rightType = PG_GETARG_OID(3);
+	funcOid = PG_GETARG_OID(4);
+
+	/*
+	 * Even though the SQL function is locked down so only a member of
+	 * pgtle_admin can run this function, let's check and make sure there is
+	 * not a way to bypass that
+	 */
+	check_is_pgtle_admin();
+
+	/*
+	 * Check we have creation rights in target namespace
+	 */
+	aclresult = PG_NAMESPACE_ACLCHECK(opNamespace, GetUserId(), ACL_CREATE);
+	namespaceName = get_namespace_name(opNamespace);
+	if (aclresult != ACLCHECK_OK)
+		aclcheck_error(aclresult, OBJECT_SCHEMA, namespaceName);
+
+	/*
+	 * DefineOperator looks up the operator function by name and argument
+	 * types, so make sure the function we were given is the one it will
+	 * find.
+	 */
+	get_func_signature(funcOid, &argTypes, &nargs);
+	if (OidIsValid(leftType))
+	{
+		if (nargs != 2 || argTypes[0] != leftType || argTypes[1] != rightType)
+			ereport(ERROR,
+					(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+					 errmsg("operator function must accept arguments of type %s and %s",
+							format_type_be(leftType), format_type_be(rightType))));
+
+		params = lappend(params, makeDefElem("leftarg",
+											 (Node *) makeTypeNameFromOid(leftType, -1),
+											 -1));
+	}
+	else if (nargs != 1 || argTypes[0] != rightType)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("operator function must accept one argument of type %s",
+						format_type_be(rightType))));
+
+	params = lappend(params, makeDefElem("rightarg",
+										 (Node *) makeTypeNameFromOid(rightType, -1),
+										 -1));
+	params = lappend(params, makeDefElem("procedure",
+										 (Node *) get_qualified_funcname(funcOid),
+										 -1));
+
+	/*
+	 * Commutator and negator are qualified with the target namespace so that
+	 * any shell operators get created next to the new operator.
+	 */
+	if (!PG_ARGISNULL(5))
+		params = lappend(params, makeDefElem("commutator",
+											 (Node *) list_make2(makeString(namespaceName),
+																 makeString(text_to_cstring(PG_GETARG_TEXT_PP(5)))),
+											 -1));
+
+	if (!PG_ARGISNULL(6))
+		params = lappend(params, makeDefElem("negator",
+											 (Node *) list_make2(makeString(namespaceName),
+																 makeString(text_to_cstring(PG_GETARG_TEXT_PP(6)))),
+											 -1));
+
+	if (!PG_ARGISNULL(7))
+		params = lappend(params, makeDefElem("restrict",
+											 (Node *) get_qualified_funcname(PG_GETARG_OID(7)),
+											 -1));
+
+	if (!PG_ARGISNULL(8))
+		params = lappend(params, makeDefElem("join",
+											 (Node *) get_qualified_funcname(PG_GETARG_OID(8)),
+											 -1));
+
+	if (!PG_ARGISNULL(9) && PG_GETARG_BOOL(9))
+		params = lappend(params, makeDefElem("hashes", NULL, -1));
+
+	if (!PG_ARGISNULL(10) && PG_GETARG_BOOL(10))
+		params = lappend(params, makeDefElem("merges", NULL, -1));
+
+	DefineOperator(list_make2(makeString(namespaceName), makeString(opName)), params);
+
+	PG_RETURN_VOID();
+}
+
+/*
+ * is_pgtle_operator_func
+ *
+ * Returns whether a given function is a C operator function created by pgtle.create_operator_func.
+ */
+static bool
+is_pgtle_operator_func(Oid funcid)
+{
+	HeapTuple	tuple;
+	Form_pg_proc proc;
+	Datum		prosrcattr;
+	char	   *prosrcstring;
+	bool		isnull;
+
+	tuple = SearchSysCache1(PROCOID, ObjectIdGetDatum(funcid));
+	if (!HeapTupleIsValid(tuple))
+		elog(ERROR, "cache lookup failed for function %u", funcid);
+
+	proc = (Form_pg_proc) GETSTRUCT(tuple);
+	if (proc->prolang != ClanguageId)
+	{
+		ReleaseSysCache(tuple);
+		return false;
+	}
+
+	prosrcattr = SysCacheGetAttr(PROCOID, tuple,
+								 Anum_pg_proc_prosrc, &isnull);
+	Assert(!isnull);
+
+	prosrcstring = TextDatumGetCString(prosrcattr);
+	ReleaseSysCache(tuple);
+	return strcmp(prosrcstring, TLE_OPERATOR_FUNC) == 0;
+}
+
+/*
+ * check_user_trusted_func
+ *
+ * Check a function used by an operator class, a cast or an aggregate created through pg_tle:
+ * 1. must be owned by the current user;
+ * 2. must be defined in a trusted language (We check it's not in C or internal for now),
+ *    or be a C operator function created by pgtle.create_operator_func.
+ *
+ * Raise an error if any requirement is not met.
+ */
+static void
+check_user_trusted_func(Oid funcid)
+{
+	HeapTuple	tuple;
+	Oid			lang;
+
+	if (!PG_PROC_OWNERCHECK(funcid, GetUserId()))
+		aclcheck_error(ACLCHECK_NOT_OWNER, OBJECT_FUNCTION, get_func_name(funcid));
+
+	tuple = SearchSysCache1(PROCOID, ObjectIdGetDatum(funcid));
+	if (!HeapTupleIsValid(tuple))
+		elog(ERROR, "cache lookup failed for function %u", funcid);
+	lang = ((Form_pg_proc) GETSTRUCT(tuple))->prolang;
+	ReleaseSysCache(tuple);
+
+	if ((lang == INTERNALlanguageId || lang == ClanguageId) &&
+		!is_pgtle_operator_func(funcid))
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("function %s cannot be defined in C or internal",
+						format_procedure(funcid))));
+}
+
+/*
+ * set_opclass_owner
+ *
+ * Hands the operator class, and the operator family created along with it, over to ownerId.
+ */
+static void
+set_opclass_owner(Oid opclassOid, Oid ownerId)
+{
+	Relation	rel;
+	HeapTuple	tuple;
+	Oid			opfamilyOid;
+
+	rel = table_open(OperatorClassRelationId, RowExclusiveLock);
+	tuple = SearchSysCacheCopy1(CLAOID, ObjectIdGetDatum(opclassOid));
+	if (!HeapTupleIsValid(tuple))
+		elog(ERROR, "cache lookup failed for operator class %u", opclassOid);
+
+	opfamilyOid = ((Form_pg_opclass) GETSTRUCT(tuple))->opcfamily;
+	((Form_pg_opclass) GETSTRUCT(tuple))->opcowner = ownerId;
+	CatalogTupleUpdate(rel, &tuple->t_self, tuple);
+	heap_freetuple(tuple);
+	table_close(rel, RowExclusiveLock);
+	changeDependencyOnOwner(OperatorClassRelationId, opclassOid, ownerId);
+
+	rel = table_open(OperatorFamilyRelationId, RowExclusiveLock);
+	tuple = SearchSysCacheCopy1(OPFAMILYOID, ObjectIdGetDatum(opfamilyOid));
+	if (!HeapTupleIsValid(tuple))
+		elog(ERROR, "cache lookup failed for operator family %u", opfamilyOid);
+
+	((Form_pg_opfamily) GETSTRUCT(tuple))->opfowner = ownerId;
+	CatalogTupleUpdate(rel, &tuple->t_self, tuple);
+	heap_freetuple(tuple);
+	table_close(rel, RowExclusiveLock);
+	changeDependencyOnOwner(OperatorFamilyRelationId, opfamilyOid, ownerId);
+}
+
+/*
+ * pg_tle_create_operator_class
+ *
+ * Creates a btree or hash operator class for a pg_tle base type so the type can be
+ * indexed and used in ORDER BY, DISTINCT, GROUP BY and hash joins.
+ *
+ * For btree, `operators` lists the <, <=, =, >= and > operators (strategies 1 to 5) and
+ * `functions` holds the comparison function returning integer.
+ * For hash, `operators` holds the = operator and `functions` holds the hash function
+ * returning integer.
+ *
+ * CREATE OPERATOR CLASS requires superuser, so the operator class is created as the
+ * bootstrap superuser after checking that the caller owns the type, the operators and
+ * all of the functions involved, and that none of the functions are written in C or
+ * internal other than those created by pgtle.create_operator_func. The resulting
+ * operator class and operator family are then owned by the caller.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_create_operator_class);
+Datum
+pg_tle_create_operator_class(PG_FUNCTION_ARGS)
+{
+	Oid			opcNamespace = PG_GETARG_OID(0);
+	char	   *opcName = NameStr(*PG_GETARG_NAME(1));
+	Oid			typeOid = PG_GETARG_OID(2);
+	char	   *method = text_to_cstring(PG_GETARG_TEXT_PP(3));
+	ArrayType  *operators = PG_GETARG_ARRAYTYPE_P(4);
+	ArrayType  *functions = PG_GETARG_ARRAYTYPE_P(5);
+	bool		isDefault = PG_GETARG_BOOL(6);
+	Datum	   *operOids;
+	bool	   *operNulls;
+	int			nopers;
+	Datum	   *funcOids;
+	bool	   *funcNulls;
+	int			nfuncs;
+	int			expectedOpers;
+	int			expectedFuncArgs;
+	Oid			amOid;
+	AclResult	aclresult;
+	char	   *namespaceName;
+	List	   *opcNameList;
+	List	   *items = NIL;
+	CreateOpClassStmt *stmt;
+	ObjectAddress address;
+	Oid			opfamilyOid;
+	Oid			save_userid;
+	int			save_sec_context;
+	int			i;
+
+	/*
+	 * Even though the SQL function is locked down so only a member of
+	 * pgtle_admin can run this function, let's check and make sure there is
+	 * not a way to bypass that
+	 */
+	check_is_pgtle_admin();
+
+	if (strcmp(method, "btree") == 0)
+	{
+		expectedOpers = BTMaxStrategyNumber;
+		expectedFuncArgs = 2;
+	}
+	else if (strcmp(method, "hash") == 0)
+	{
+		expectedOpers = HTMaxStrategyNumber;
+		expectedFuncArgs = 1;
+	}
+	else
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
+				 errmsg("invalid access method \"%s\"", method),
+				 errhint("Valid access methods are \"btree\" and \"hash\".")));
+
+	amOid = get_am_oid(method, false);
+
+	/*
+	 * Check we have creation rights in target namespace
+	 */
+	aclresult = PG_NAMESPACE_ACLCHECK(opcNamespace, GetUserId(), ACL_CREATE);
+	namespaceName = get_namespace_name(opcNamespace);
+	if (aclresult != ACLCHECK_OK)
+		aclcheck_error(aclresult, OBJECT_SCHEMA, namespaceName);
+
+	/*
+	 * Check we are the owner of the base type, and that it is a pg_tle base
+	 * type.
+	 */
+	if (!PG_TYPE_OWNERCHECK(typeOid, GetUserId()))
+		aclcheck_error_type(ACLCHECK_NOT_OWNER, typeOid);
+
+	check_pgtle_base_type(typeOid);
+
+	opcNameList = list_make2(makeString(namespaceName), makeString(pstrdup(opcName)));
+
+	/*
+	 * Look to see if the operator class already exists. DefineOpClass would
+	 * reuse an existing operator family of the same name, which may not be
+	 * ours, so refuse that too.
+	 */
+	if (SearchSysCacheExists3(CLAAMNAMENSP,
+							  ObjectIdGetDatum(amOid),
+							  CStringGetDatum(opcName),
+							  ObjectIdGetDatum(opcNamespace)))
+		ereport(ERROR,
+				(errcode(ERRCODE_DUPLICATE_OBJECT),
+				 errmsg("operator class \"%s\" for access method \"%s\" already exists",
+						opcName, method)));
+
+	if (OidIsValid(get_opfamily_oid(amOid, opcNameList, true)))
+		ereport(ERROR,
+				(errcode(ERRCODE_DUPLICATE_OBJECT),
+				 errmsg("operator family \"%s\" for access method \"%s\" already exists",
+						opcName, method)));
+
+	deconstruct_array(operators, REGOPERATOROID, sizeof(Oid), true, TYPALIGN_INT,
+					  &operOids, &operNulls, &nopers);
+	deconstruct_array(functions, REGPROCEDUREOID, sizeof(Oid), true, TYPALIGN_INT,
+					  &funcOids, &funcNulls, &nfuncs);
+
+	if (nopers != expectedOpers || nfuncs != 1)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_OBJECT_DEFINITION),
+				 errmsg("%s operator classes require %d operators and 1 support function",
+						method, expectedOpers)));
+
+	for (i = 0; i < nopers; i++)
+	{
+		Oid			operOid;
+		HeapTuple	tuple;
+		Form_pg_operator oper;
+		CreateOpClassItem *item;
+
+		if (operNulls[i])
+			ereport(ERROR,
+					(errcode(ERRCODE_NULL_VALUE_NOT_ALLOWED),
+					 errmsg("operator cannot be NULL")));
+
+		operOid = DatumGetObjectId(operOids[i]);
+		tuple = SearchSysCache1(OPEROID, ObjectIdGetDatum(operOid));
+		if (!HeapTupleIsValid(tuple))
+			elog(ERROR, "cache lookup failed for operator %u", operOid);
+		oper = (Form_pg_operator) GETSTRUCT(tuple);
+
+		if (oper->oprleft != typeOid || oper->oprright != typeOid ||
+			oper->oprresult != BOOLOID)
+		{
+			ReleaseSysCache(tuple);
+			ereport(ERROR,
+					(errcode(ERRCODE_INVALID_OBJECT_DEFINITION),
+					 errmsg("operator %s must accept two arguments of type %s and return boolean",
+							format_operator(operOid), format_type_be(typeOid))));
+		}
+
+		item = makeNode(CreateOpClassItem);
+		item->itemtype = OPCLASS_ITEM_OPERATOR;
+		item->name = makeNode(ObjectWithArgs);
+		item->name->objname = list_make2(makeString(get_namespace_name(oper->oprnamespace)),
+										 makeString(pstrdup(NameStr(oper->oprname))));
+		item->name->objargs = list_make2(makeTypeNameFromOid(typeOid, -1),
+										 makeTypeNameFromOid(typeOid, -1));
+		item->number = i + 1;
+		ReleaseSysCache(tuple);
+
+		/*
+		 * Check we are the owner of the operator and its underlying function.
+		 */
+		if (!PG_OPER_OWNERCHECK(operOid, GetUserId()))
+			aclcheck_error(ACLCHECK_NOT_OWNER, OBJECT_OPERATOR, format_operator(operOid));
+
+		check_user_trusted_func(get_opcode(operOid));
+
+		items = lappend(items, item);
+	}
+
+	for (i = 0; i < nfuncs; i++)
+	{
+		Oid			funcOid;
+		Oid		   *argTypes;
+		int			nargs;
+		int			j;
+		List	   *funcArgs = NIL;
+		CreateOpClassItem *item;
+
+		if (funcNulls[i])
+			ereport(ERROR,
+					(errcode(ERRCODE_NULL_VALUE_NOT_ALLOWED),
+					 errmsg("support function cannot be NULL")));
+
+		funcOid = DatumGetObjectId(funcOids[i]);
+		get_func_signature(funcOid, &argTypes, &nargs);
+		if (nargs != expectedFuncArgs || get_func_rettype(funcOid) != INT4OID)
+			ereport(ERROR,
+					(errcode(ERRCODE_INVALID_OBJECT_DEFINITION),
+					 errmsg("%s support function must accept arguments of type %s and return integer",
+							method, format_type_be(typeOid))));
+
+		for (j = 0; j < nargs; j++)
+		{
+			if (argTypes[j] != typeOid)
+				ereport(ERROR,
+						(errcode(ERRCODE_INVALID_OBJECT_DEFINITION),
+						 errmsg("%s support function must accept arguments of type %s and return integer",
+								method, format_type_be(typeOid))));
+			funcArgs = lappend(funcArgs, makeTypeNameFromOid(typeOid, -1));
+		}
+
+		check_user_trusted_func(funcOid);
+
+		item = makeNode(CreateOpClassItem);
+		item->itemtype = OPCLASS_ITEM_FUNCTION;
+		item->name = makeNode(ObjectWithArgs);
+		item->name->objname = get_qualified_funcname(funcOid);
+		item->name->objargs = funcArgs;
+		item->number = i + 1;
+
+		items = lappend(items, item);
+	}
+
+	stmt = makeNode(CreateOpClassStmt);
+	stmt->opclassname = opcNameList;
+	stmt->opfamilyname = NIL;
+	stmt->amname = method;
+	stmt->datatype = makeTypeNameFromOid(typeOid, -1);
+	stmt->items = items;
+	stmt->isDefault = isDefault;
+
+	/*
+	 * DefineOpClass only checks for superuser, and that the operator class
+	 * could be the default for the type. The ownership checks a non-superuser
+	 * would need, and the checks on the operator family, have been done above,
+	 * so switch to the bootstrap superuser to create the operator class and
+	 * then give it back to the caller.
+	 */
+	GetUserIdAndSecContext(&save_userid, &save_sec_context);
+	SetUserIdAndSecContext(BOOTSTRAP_SUPERUSERID,
+						   save_sec_context | SECURITY_LOCAL_USERID_CHANGE |
+						   SECURITY_RESTRICTED_OPERATION);
+
+	address = DefineOpClass(stmt);
+
+	SetUserIdAndSecContext(save_userid, save_sec_context);
+
+	CommandCounterIncrement();
+
+	/*
+	 * DefineOpClass looks up the members by name, so make sure it found
+	 * exactly the operators and the function checked above.
+	 */
+	opfamilyOid = get_opclass_family(address.objectId);
+	for (i = 0; i < nopers; i++)
+	{
+		if (get_opfamily_member(opfamilyOid, typeOid, typeOid, i + 1) !=
+			DatumGetObjectId(operOids[i]))
+			elog(ERROR, "operator class \"%s\" does not contain the expected operators", opcName);
+	}
+	if (get_opfamily_proc(opfamilyOid, typeOid, typeOid, 1) != DatumGetObjectId(funcOids[0]))
+		elog(ERROR, "operator class \"%s\" does not contain the expected support function", opcName);
+
+	set_opclass_owner(address.objectId, save_userid);
+
+	PG_RETURN_VOID();
+}
+
+/*
+ * is_pgtle_base_type
+ *
+ * Returns whether the input type is a base type whose I/O functions are created by pg_tle.
+ */
+static bool
+is_pgtle_base_type(Oid typeOid)
+{
+	HeapTuple	tuple;
+	Form_pg_type typeForm;
+	bool		isBaseType;
+	Oid			inputOid;
+	Oid			outputOid;
+
+	tuple = SearchSysCache1(TYPEOID, ObjectIdGetDatum(typeOid));
+	if (!HeapTupleIsValid(tuple))
+		elog(ERROR, "cache lookup failed for type %u", typeOid);
+	typeForm = (Form_pg_type) GETSTRUCT(tuple);
+
+	isBaseType = typeForm->typtype == TYPTYPE_BASE && typeForm->typisdefined;
+	inputOid = typeForm->typinput;
+	outputOid = typeForm->typoutput;
+	ReleaseSysCache(tuple);
+
+	return isBaseType && is_pgtle_io_func(inputOid, true) && is_pgtle_io_func(outputOid, false);
+}
+
+/*
+ * get_cast_context
+ *
+ * Get the cast context from input string, valid options are 'explicit', 'assignment' and 'implicit'.
+ * Report an error if input is not valid.
+ */
+static CoercionContext
+get_cast_context(char *contextStr)
+{
+	if (pg_strcasecmp(contextStr, "explicit") == 0)
+		return COERCION_EXPLICIT;
+	if (pg_strcasecmp(contextStr, "assignment") == 0)
+		return COERCION_ASSIGNMENT;
+	if (pg_strcasecmp(contextStr, "implicit") == 0)
+		return COERCION_IMPLICIT;
+
+	ereport(ERROR,
+			(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
+			 errmsg("cast context \"%s\" not recognized", contextStr)));
+}
+
+/*
+ * create_cast
+ *
+ * Creates a cast between a pg_tle base type and another type, returns true when the cast is
+ * successfully created. This is similar to CREATE CAST ... WITH FUNCTION.
+ *
+ * The cast function must be owned by the current user and must not be defined in C or internal,
+ * unless it is an operator function created by pgtle.create_operator_func. This allows casting
+ * a base type to another type using a function written against bytea.
+ *
+ * if_not_exists: if true, don't fail on duplicate cast, just print a notice and return false.
+ * Otherwise, fail on duplicate cast.
+ */
+static bool
+create_cast(Oid sourceTypeOid, Oid targetTypeOid, Oid funcOid,
+			char *contextStr, bool if_not_exists)
+{
+	CoercionContext context;
+	Oid			tleTypeOid;
+	Oid		   *argTypes;
+	int			nargs;
+	int			i;
+	List	   *funcArgs = NIL;
+	CreateCastStmt *stmt;
+
+	/*
+	 * Even though the SQL function is locked down so only a member of
+	 * pgtle_admin can run this function, let's check and make sure there is
+	 * not a way to bypass that
+	 */
+	check_is_pgtle_admin();
+
+	context = get_cast_context(contextStr);
+
+	if (is_pgtle_base_type(sourceTypeOid))
+		tleTypeOid = sourceTypeOid;
+	else if (is_pgtle_base_type(targetTypeOid))
+		tleTypeOid = targetTypeOid;
+	else
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_OBJECT_DEFINITION),
+				 errmsg("cast from type %s to type %s does not involve a pg_tle defined base type",
+						format_type_be(sourceTypeOid), format_type_be(targetTypeOid))));
+
+	/*
+	 * Check we are the owner of the base type.
+	 */
+	if (!PG_TYPE_OWNERCHECK(tleTypeOid, GetUserId()))
+		aclcheck_error_type(ACLCHECK_NOT_OWNER, tleTypeOid);
+
+	check_pgtle_base_type(tleTypeOid);
+
+	if (SearchSysCacheExists2(CASTSOURCETARGET,
+							  ObjectIdGetDatum(sourceTypeOid),
+							  ObjectIdGetDatum(targetTypeOid)))
+	{
+		if (if_not_exists)
+		{
+			ereport(NOTICE,
+					(errcode(ERRCODE_DUPLICATE_OBJECT),
+					 errmsg("cast from type %s to type %s already exists, skipping",
+							format_type_be(sourceTypeOid), format_type_be(targetTypeOid))));
+
+			return false;
+		}
+		else
+			ereport(ERROR,
+					(errcode(ERRCODE_DUPLICATE_OBJECT),
+					 errmsg("cast from type %s to type %s already exists",
+							format_type_be(sourceTypeOid), format_type_be(targetTypeOid))));
+	}
+
+	check_user_trusted_func(funcOid);
+
+	get_func_signature(funcOid, &argTypes, &nargs);
+	for (i = 0; i < nargs; i++)
+		funcArgs = lappend(funcArgs, makeTypeNameFromOid(argTypes[i], -1));
+
+	/*
+	 * Let CreateCast do the rest of the checks on the cast function, such as
+	 * its argument and return types and volatility.
+	 */
+	stmt = makeNode(CreateCastStmt);
+	stmt->sourcetype = makeTypeNameFromOid(sourceTypeOid, -1);
+	stmt->targettype = makeTypeNameFromOid(targetTypeOid, -1);
+	stmt->func = makeNode(ObjectWithArgs);
+	stmt->func->objname = get_qualified_funcname(funcOid);
+	stmt->func->objargs = funcArgs;
+	stmt->context = context;
+	stmt->inout = false;
+
+	CreateCast(stmt);
+
+	return true;
+}
+
+/*
+ * Registers a new cast, fail if the cast already exists.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_create_cast);
+Datum
+pg_tle_create_cast(PG_FUNCTION_ARGS)
+{
+	create_cast(PG_GETARG_OID(0), PG_GETARG_OID(1), PG_GETARG_OID(2),
+				text_to_cstring(PG_GETARG_TEXT_PP(3)), false);
+	PG_RETURN_VOID();
+}
+
+/*
+ * Registers a new cast if not exists; Otherwise do nothing.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_create_cast_if_not_exists);
+Datum
+pg_tle_create_cast_if_not_exists(PG_FUNCTION_ARGS)
+{
+	PG_RETURN_BOOL(create_cast(PG_GETARG_OID(0), PG_GETARG_OID(1), PG_GETARG_OID(2),
+							   text_to_cstring(PG_GETARG_TEXT_PP(3)), true));
+}
+
+/*
+ * pg_tle_set_base_type_binary_io
+ *
+ * Adds binary receive and send functions to a pg_tle base type, so the type can be
+ * used with the binary wire protocol and COPY BINARY.
+ *
+ * The user-defined receive function takes the wire format of a value as bytea and returns
+ * its internal representation as bytea; the user-defined send function does the opposite.
+ * Similar to create_base_type, C-version receive and send functions with the same names are
+ * created and set as the type's receive and send functions.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_set_base_type_binary_io);
+Datum
+pg_tle_set_base_type_binary_io(PG_FUNCTION_ARGS)
+{
+	Oid			typeNamespace = PG_GETARG_OID(0);
+	char	   *typeName = NameStr(*PG_GETARG_NAME(1));
+	Oid			receiveFuncId = PG_GETARG_OID(2);
+	Oid			sendFuncId = PG_GETARG_OID(3);
+	char	   *funcProbin = get_probin(fcinfo->flinfo->fn_oid);
+	AclResult	aclresult;
+	Oid			typeOid;
+	Oid			receiveOid;
+	Oid			sendOid;
+	Oid			receiveFuncParamType;
+	Relation	rel;
+	HeapTuple	tuple;
+	Form_pg_type typeForm;
+	ObjectAddress typeAddress;
+	ObjectAddress funcAddress;
+
+	/*
+	 * Even though the SQL function is locked down so only a member of
+	 * pgtle_admin can run this function, let's check and make sure there is
+	 * not a way to bypass that
+	 */
+	check_is_pgtle_admin();
+
+	/* Check we have creation rights in target namespace */
+	aclresult = PG_NAMESPACE_ACLCHECK(typeNamespace, GetUserId(), ACL_CREATE);
+	if (aclresult != ACLCHECK_OK)
+		aclcheck_error(aclresult, OBJECT_SCHEMA,
+					   get_namespace_name(typeNamespace));
+
+	typeOid = GET_TYPE_OID(TYPENAMENSP,
+						   CStringGetDatum(typeName),
+						   ObjectIdGetDatum(typeNamespace));
+
+	if (!OidIsValid(typeOid))
+		ereport(ERROR,
+				(errcode(ERRCODE_UNDEFINED_OBJECT),
+				 errmsg("type \"%s\" does not exist", typeName)));
+
+	/*
+	 * Check we are the owner of the base type.
+	 */
+	if (!PG_TYPE_OWNERCHECK(typeOid, GetUserId()))
+		aclcheck_error_type(ACLCHECK_NOT_OWNER, typeOid);
+
+	check_pgtle_base_type(typeOid);
+
+	tuple = SearchSysCache1(TYPEOID, ObjectIdGetDatum(typeOid));
+	if (!HeapTupleIsValid(tuple))
+		elog(ERROR, "cache lookup failed for type %u", typeOid);
+	typeForm = (Form_pg_type) GETSTRUCT(tuple);
+	if (OidIsValid(typeForm->typreceive) || OidIsValid(typeForm->typsend))
+	{
+		ReleaseSysCache(tuple);
+		ereport(ERROR,
+				(errcode(ERRCODE_DUPLICATE_OBJECT),
+				 errmsg("type %s already has receive and send functions",
+						format_type_be(typeOid))));
+	}
+	ReleaseSysCache(tuple);
+
+	/*
+	 * Same as create_base_type, require the owner of the type to also own the
+	 * underlying functions.
+	 */
+	if (!PG_PROC_OWNERCHECK(receiveFuncId, GetUserId()))
+		aclcheck_error(ACLCHECK_NOT_OWNER, OBJECT_FUNCTION, get_func_name(receiveFuncId));
+	if (!PG_PROC_OWNERCHECK(sendFuncId, GetUserId()))
+		aclcheck_error(ACLCHECK_NOT_OWNER, OBJECT_FUNCTION, get_func_name(sendFuncId));
+
+	check_user_binary_func(receiveFuncId, typeOid, typeNamespace, true);
+	check_user_binary_func(sendFuncId, typeOid, typeNamespace, false);
+
+	/*
+	 * C version receive function accepts a single argument of type INTERNAL
+	 * and returns the base type; C version send function accepts a single
+	 * argument of the base type and returns type BYTEA.
+	 */
+	receiveFuncParamType = INTERNALOID;
+	receiveOid = create_c_func_internal(typeNamespace, receiveFuncId, NULL,
+										buildoidvector(&receiveFuncParamType, 1),
+										typeOid, TLE_BASE_TYPE_RECV,
+										funcProbin);
+
+	sendOid = create_c_func_internal(typeNamespace, sendFuncId, NULL,
+									 buildoidvector(&typeOid, 1),
+									 BYTEAOID, TLE_BASE_TYPE_SEND,
+									 funcProbin);
+
+	rel = table_open(TypeRelationId, RowExclusiveLock);
+	tuple = SearchSysCacheCopy1(TYPEOID, ObjectIdGetDatum(typeOid));
+	if (!HeapTupleIsValid(tuple))
+		elog(ERROR, "cache lookup failed for type %u", typeOid);
+
+	typeForm = (Form_pg_type) GETSTRUCT(tuple);
+	typeForm->typreceive = receiveOid;
+	typeForm->typsend = sendOid;
+	CatalogTupleUpdate(rel, &tuple->t_self, tuple);
+	heap_freetuple(tuple);
+	table_close(rel, RowExclusiveLock);
+
+	/*
+	 * Record the same dependencies TypeCreate would have recorded for the
+	 * receive and send functions.
+	 */
+	typeAddress.classId = TypeRelationId;
+	typeAddress.objectId = typeOid;
+	typeAddress.objectSubId = 0;
+
+	funcAddress.classId = ProcedureRelationId;
+	funcAddress.objectId = receiveOid;
+	funcAddress.objectSubId = 0;
+	recordDependencyOn(&typeAddress, &funcAddress, DEPENDENCY_NORMAL);
+
+	funcAddress.objectId = sendOid;
+	recordDependencyOn(&typeAddress, &funcAddress, DEPENDENCY_NORMAL);
+
+	PG_RETURN_VOID();
+}
+
+/*
+ * find_user_binary_func
+ *
+ * Given a qualified user defined receive/send C function name, find the corresponding
+ * user-defined receive/send function.
+ * Raise an error if such function cannot be found.
+ */
+static Oid
+find_user_binary_func(List *procname, bool typeReceive)
+{
+	Oid			argList[1];
+	Oid			procOid;
+
+	/*
+	 * User-defined receive and send functions always take a single argument
+	 * of the bytea and return bytea.
+	 */
+	argList[0] = BYTEAOID;
+	procOid = LookupFuncName(procname, 1, argList, true);
+
+	if (!OidIsValid(procOid))
+		ereport(ERROR,
+				(errcode(ERRCODE_UNDEFINED_FUNCTION),
+				 errmsg("function %s does not exist",
+						func_signature_string(procname, 1, NIL, argList))));
+
+	if (get_func_rettype(procOid) != BYTEAOID)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_OBJECT_DEFINITION),
+				 errmsg("type %s function %s must return type %s",
+						typeReceive ? TLE_RECEIVE_FUNC_STR : TLE_SEND_FUNC_STR,
+						NameListToString(procname), format_type_be(BYTEAOID))));
+
+	return procOid;
+}
+
+/*
+ * check_user_binary_func
+ *
+ * Check a user-defined type receive/send function meets pg_tle specific requirements:
+ * 1. must be defined in a trusted language (We check it's not in C or internal for now);
+ * 2. must accept a single argument of type bytea and return type bytea;
+ * 3. must be in the same namespace as the base type;
+ * 4. must be strict and immutable;
+ * 5. the to-be-created C function must not exist yet.
+ *
+ * Raise an error if any requirement is not met.
+ */
+static void
+check_user_binary_func(Oid funcid, Oid typeOid, Oid expectedNamespace, bool typeReceive)
+{
+	HeapTuple	tuple;
+	Form_pg_proc proc;
+	Oid			funcArgList[1];
+	List	   *funcNameList;
+	Oid			prolang;
+	Oid			prorettype;
+	Oid			namespace;
+	bool		proisstrict;
+	char		provolatile;
+	char	   *proname;
+	char	   *funcType;
+
+	tuple = SearchSysCache1(PROCOID, ObjectIdGetDatum(funcid));
+	if (!HeapTupleIsValid(tuple))
+		elog(ERROR, "cache lookup failed for function %u", funcid);
+	proc = (Form_pg_proc) GETSTRUCT(tuple);
+
+	funcType = typeReceive ? TLE_RECEIVE_FUNC_STR : TLE_SEND_FUNC_STR;
+	if (proc->pronargs != 1 || proc->proargtypes.values[0] != BYTEAOID)
+	{
+		ReleaseSysCache(tuple);
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s function must accept one argument of type %s",
+						funcType, format_type_be(BYTEAOID))));
+	}
+
+	prolang = proc->prolang;
+	prorettype = proc->prorettype;
+	namespace = proc->pronamespace;
+	proisstrict = proc->proisstrict;
+	provolatile = proc->provolatile;
+	proname = pstrdup(NameStr(proc->proname));
+	ReleaseSysCache(tuple);
+
+	if (prolang == INTERNALlanguageId || prolang == ClanguageId)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s function cannot be defined in C or internal",
+						funcType)));
+
+	if (prorettype != BYTEAOID)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s functions must return type %s",
+						funcType, format_type_be(BYTEAOID))));
+
+	if (namespace != expectedNamespace)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s functions must exist in the same namespace as the type",
+						funcType)));
+
+	if (!proisstrict)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s functions must be strict",
+						funcType)));
+
+	if (provolatile != PROVOLATILE_IMMUTABLE)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s functions must be immutable",
+						funcType)));
+
+	funcArgList[0] = typeReceive ? INTERNALOID : typeOid;
+	funcNameList = list_make2(makeString(get_namespace_name(expectedNamespace)),
+							  makeString(proname));
+
+	if (OidIsValid(LookupFuncName(funcNameList, 1, funcArgList, true)))
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("function \"%s\" already exists", NameListToString(funcNameList))));
+}
+
+/*
+ * pg_tle_base_type_recv
+ *
+ * This function is used by pg_tle base type C receive function. Based on the C receive function,
+ * we can find the corresponding user-defined receive function, and calls the user-defined receive
+ * function with the remaining bytes of the message.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_base_type_recv);
+Datum
+pg_tle_base_type_recv(PG_FUNCTION_ARGS)
+{
+	StringInfo	buf = (StringInfo) PG_GETARG_POINTER(0);
+	bytea	   *wire;
+	bytea	   *result;
+	int			nbytes;
+	Oid			user_receive_function;
+	Oid			typeOid;
+	int			typeLen;
+	Datum		datum;
+
+	user_receive_function = find_user_binary_func(get_qualified_funcname(fcinfo->flinfo->fn_oid), true);
+	typeOid = get_func_rettype(fcinfo->flinfo->fn_oid);
+	typeLen = get_typlen(typeOid);
+
+	nbytes = buf->len - buf->cursor;
+	wire = (bytea *) palloc(nbytes + VARHDRSZ);
+	SET_VARSIZE(wire, nbytes + VARHDRSZ);
+	pq_copymsgbytes(buf, VARDATA(wire), nbytes);
+
+	/*
+	 * Call the user-defined receive function.
+	 */
+	datum = OidFunctionCall1Coll(user_receive_function, InvalidOid, PointerGetDatum(wire));
+	result = DatumGetByteaPP(datum);
+
+	if (typeLen >= 0)
+	{
+		int			inputLen = VARSIZE_ANY_EXHDR(result) + VARHDRSZ;
+
+		if (typeLen != inputLen)
+			elog(ERROR, "type %s is defined as fixed-size %d, but actual data length is %d",
+				 format_type_be(typeOid), typeLen, inputLen);
+	}
+	PG_RETURN_POINTER(result);
+}
+
+/*
+ * pg_tle_base_type_send
+ *
+ * This function is used by pg_tle base type C send function. Based on the C send function,
+ * we can find the corresponding user-defined send function, and calls the user-defined send function.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_base_type_send);
+Datum
+pg_tle_base_type_send(PG_FUNCTION_ARGS)
+{
+	Datum		datum = PG_GETARG_DATUM(0);
+	Oid			user_send_function;
+
+	user_send_function = find_user_binary_func(get_qualified_funcname(fcinfo->flinfo->fn_oid), false);
+
+	/*
+	 * Call the user-defined send function.
+	 */
+	PG_RETURN_DATUM(OidFunctionCall1Coll(user_send_function, InvalidOid, datum));
+}
+
+/*
+ * TleAnalyzeData
+ *
+ * State of the analyze function of a pg_tle base type, kept in the extra data
+ * of the column statistics while ANALYZE computes them.
+ */
+typedef struct TleAnalyzeData
+{
+	AnalyzeAttrComputeStatsFunc std_compute_stats;	/* set by std_typanalyze */
+	void	   *std_extra_data; /* set by std_typanalyze */
+	Oid			user_analyze_function;
+} TleAnalyzeData;
+
+/*
+ * find_user_analyze_func
+ *
+ * Given a qualified user defined analyze C function name, find the corresponding
+ * user-defined analyze function.
+ * Raise an error if such function cannot be found.
+ */
+static Oid
+find_user_analyze_func(List *procname)
+{
+	Oid			argList[1];
+	Oid			procOid;
+
+	/*
+	 * User-defined analyze functions always take a single argument of the
+	 * bytea[] and return real.
+	 */
+	argList[0] = get_array_type(BYTEAOID);
+	procOid = LookupFuncName(procname, 1, argList, true);
+
+	if (!OidIsValid(procOid))
+		ereport(ERROR,
+				(errcode(ERRCODE_UNDEFINED_FUNCTION),
+				 errmsg("function %s does not exist",
+						func_signature_string(procname, 1, NIL, argList))));
+
+	if (get_func_rettype(procOid) != FLOAT4OID)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_OBJECT_DEFINITION),
+				 errmsg("type %s function %s must return type %s",
+						TLE_ANALYZE_FUNC_STR, NameListToString(procname),
+						format_type_be(FLOAT4OID))));
+
+	return procOid;
+}
+
+/*
+ * check_user_analyze_func
+ *
+ * Check a user-defined type analyze function meets pg_tle specific requirements:
+ * 1. must be defined in a trusted language (We check it's not in C or internal for now);
+ * 2. must accept a single argument of type bytea[] and return type real;
+ * 3. must be in the same namespace as the base type;
+ * 4. must be strict and immutable;
+ * 5. the to-be-created C function must not exist yet.
+ *
+ * Raise an error if any requirement is not met.
+ */
+static void
+check_user_analyze_func(Oid funcid, Oid expectedNamespace)
+{
+	HeapTuple	tuple;
+	Form_pg_proc proc;
+	Oid			funcArgList[1];
+	List	   *funcNameList;
+	Oid			expectedArgType = get_array_type(BYTEAOID);
+	Oid			prolang;
+	Oid			prorettype;
+	Oid			namespace;
+	bool		proisstrict;
+	char		provolatile;
+	char	   *proname;
+
+	tuple = SearchSysCache1(PROCOID, ObjectIdGetDatum(funcid));
+	if (!HeapTupleIsValid(tuple))
+		elog(ERROR, "cache lookup failed for function %u", funcid);
+	proc = (Form_pg_proc) GETSTRUCT(tuple);
+
+	if (proc->pronargs != 1 || proc->proargtypes.values[0] != expectedArgType)
+	{
+		ReleaseSysCache(tuple);
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s function must accept one argument of type %s",
+						TLE_ANALYZE_FUNC_STR, format_type_be(expectedArgType))));
+	}
+
+	prolang = proc->prolang;
+	prorettype = proc->prorettype;
+	namespace = proc->pronamespace;
+	proisstrict = proc->proisstrict;
+	provolatile = proc->provolatile;
+	proname = pstrdup(NameStr(proc->proname));
+	ReleaseSysCache(tuple);
+
+	if (prolang == INTERNALlanguageId || prolang == ClanguageId)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s function cannot be defined in C or internal",
+						TLE_ANALYZE_FUNC_STR)));
+
+	if (prorettype != FLOAT4OID)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s functions must return type %s",
+						TLE_ANALYZE_FUNC_STR, format_type_be(FLOAT4OID))));
+
+	if (namespace != expectedNamespace)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s functions must exist in the same namespace as the type",
+						TLE_ANALYZE_FUNC_STR)));
+
+	if (!proisstrict)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s functions must be strict",
+						TLE_ANALYZE_FUNC_STR)));
+
+	if (provolatile != PROVOLATILE_IMMUTABLE)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s functions must be immutable",
+						TLE_ANALYZE_FUNC_STR)));
+
+	funcArgList[0] = INTERNALOID;
+	funcNameList = list_make2(makeString(get_namespace_name(expectedNamespace)),
+							  makeString(proname));
+
+	if (OidIsValid(LookupFuncName(funcNameList, 1, funcArgList, true)))
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("function \"%s\" already exists", NameListToString(funcNameList))));
+}
+
+/*
+ * pg_tle_base_type_analyze
+ *
+ * This function is used by pg_tle base type C analyze function. The standard statistics
+ * are computed as for any other type, then the sampled values are passed as bytea[] to the
+ * corresponding user-defined analyze function, which returns the number of distinct values
+ * in the column.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_base_type_analyze);
+Datum
+pg_tle_base_type_analyze(PG_FUNCTION_ARGS)
+{
+	VacAttrStats *stats = (VacAttrStats *) PG_GETARG_POINTER(0);
+	TleAnalyzeData *data;
+	Oid			user_analyze_function;
+
+	user_analyze_function = find_user_analyze_func(get_qualified_funcname(fcinfo->flinfo->fn_oid));
+
+	if (!std_typanalyze(stats))
+		PG_RETURN_BOOL(false);
+
+	data = (TleAnalyzeData *) palloc(sizeof(TleAnalyzeData));
+	data->std_compute_stats = stats->compute_stats;
+	data->std_extra_data = stats->extra_data;
+	data->user_analyze_function = user_analyze_function;
+
+	stats->compute_stats = tle_compute_stats;
+	stats->extra_data = data;
+
+	PG_RETURN_BOOL(true);
+}
+
+/*
+ * tle_compute_stats
+ *
+ * Compute the standard statistics of a pg_tle base type column, then override the number of
+ * distinct values with the one returned by the user-defined analyze function.
+ *
+ * The user-defined analyze function returns a value with the same meaning as
+ * pg_statistic.stadistinct: a positive number of distinct values, a negative fraction of the
+ * number of rows, or 0 to keep the standard estimate.
+ */
+static void
+tle_compute_stats(VacAttrStats *stats, AnalyzeAttrFetchFunc fetchfunc,
+				  int samplerows, double totalrows)
+{
+	TleAnalyzeData *data = (TleAnalyzeData *) stats->extra_data;
+	Datum	   *values;
+	int			nvalues = 0;
+	ArrayType  *sample;
+	float4		ndistinct;
+	int			i;
+
+	/* the standard compute function expects its own extra data */
+	stats->extra_data = data->std_extra_data;
+	data->std_compute_stats(stats, fetchfunc, samplerows, totalrows);
+
+	if (!stats->stats_valid)
+		return;
+
+	/*
+	 * pg_tle base types are stored as bytea, so the sampled values can be
+	 * passed to the user-defined analyze function as is. As in
+	 * compute_scalar_stats, values wider than WIDTH_THRESHOLD are left out so
+	 * that the sample does not have to be detoasted and copied in full.
+	 */
+	values = (Datum *) palloc(samplerows * sizeof(Datum));
+	for (i = 0; i < samplerows; i++)
+	{
+		bool		isnull;
+		Datum		value = fetchfunc(stats, i, &isnull);
+
+		if (isnull)
+			continue;
+
+		if (toast_raw_datum_size(value) > WIDTH_THRESHOLD)
+			continue;
+
+		values[nvalues++] = value;
+	}
+
+	if (nvalues == 0)
+		return;
+
+	sample = construct_array(values, nvalues, BYTEAOID, -1, false, TYPALIGN_INT);
+
+	/*
+	 * Call the user-defined analyze function.
+	 */
+	ndistinct = DatumGetFloat4(OidFunctionCall1Coll(data->user_analyze_function, InvalidOid,
+												   PointerGetDatum(sample)));
+
+	if (isnan(ndistinct) || ndistinct < -1.0)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
+				 errmsg("type %s function %s returned invalid number of distinct values %g",
+						TLE_ANALYZE_FUNC_STR, format_procedure(data->user_analyze_function),
+						ndistinct),
+				 errhint("The number of distinct values must be a positive number, a negative fraction greater than or equal to -1, or 0 if unknown.")));
+
+	if (ndistinct != 0)
+		stats->stadistinct = ndistinct;
+}
+
+/*
+ * pg_tle_set_base_type_typmod_io
+ *
+ * Adds type modifier input and output functions to a pg_tle base type, so the type can be
+ * declared with modifiers such as mytype(10,2).
+ *
+ * The user-defined typmod_in function takes the modifiers as text[] and returns the encoded
+ * type modifier as a non-negative integer; the user-defined typmod_out function takes the
+ * encoded type modifier and returns its text representation, e.g. "(10,2)".
+ * Unlike the I/O functions, the C-version functions are named after the type, as the C-version
+ * typmod_out function would otherwise clash with the user-defined one.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_set_base_type_typmod_io);
+Datum
+pg_tle_set_base_type_typmod_io(PG_FUNCTION_ARGS)
+{
+	Oid			typeNamespace = PG_GETARG_OID(0);
+	char	   *typeName = NameStr(*PG_GETARG_NAME(1));
+	Oid			typmodInFuncId = PG_GETARG_OID(2);
+	Oid			typmodOutFuncId = PG_GETARG_OID(3);
+	char	   *funcProbin = get_probin(fcinfo->flinfo->fn_oid);
+	AclResult	aclresult;
+	Oid			typeOid;
+	Oid			typmodInOid;
+	Oid			typmodOutOid;
+	Oid			typmodInParamType;
+	Oid			typmodOutParamType;
+	char	   *typmodInName;
+	char	   *typmodOutName;
+	Relation	rel;
+	HeapTuple	tuple;
+	Form_pg_type typeForm;
+	ObjectAddress typeAddress;
+	ObjectAddress funcAddress;
+
+	/*
+	 * Even though the SQL function is locked down so only a member of
+	 * pgtle_admin can run this function, let's check and make sure there is
+	 * not a way to bypass that
+	 */
+	check_is_pgtle_admin();
+
+	/* Check we have creation rights in target namespace */
+	aclresult = PG_NAMESPACE_ACLCHECK(typeNamespace, GetUserId(), ACL_CREATE);
+	if (aclresult != ACLCHECK_OK)
+		aclcheck_error(aclresult, OBJECT_SCHEMA,
+					   get_namespace_name(typeNamespace));
+
+	typeOid = GET_TYPE_OID(TYPENAMENSP,
+						   CStringGetDatum(typeName),
+						   ObjectIdGetDatum(typeNamespace));
+
+	if (!OidIsValid(typeOid))
+		ereport(ERROR,
+				(errcode(ERRCODE_UNDEFINED_OBJECT),
+				 errmsg("type \"%s\" does not exist", typeName)));
+
+	/*
+	 * Check we are the owner of the base type.
+	 */
+	if (!PG_TYPE_OWNERCHECK(typeOid, GetUserId()))
+		aclcheck_error_type(ACLCHECK_NOT_OWNER, typeOid);
+
+	check_pgtle_base_type(typeOid);
+
+	tuple = SearchSysCache1(TYPEOID, ObjectIdGetDatum(typeOid));
+	if (!HeapTupleIsValid(tuple))
+		elog(ERROR, "cache lookup failed for type %u", typeOid);
+	typeForm = (Form_pg_type) GETSTRUCT(tuple);
+	if (OidIsValid(typeForm->typmodin) || OidIsValid(typeForm->typmodout))
+	{
+		ReleaseSysCache(tuple);
+		ereport(ERROR,
+				(errcode(ERRCODE_DUPLICATE_OBJECT),
+				 errmsg("type %s already has typmod_in and typmod_out functions",
+						format_type_be(typeOid))));
+	}
+	ReleaseSysCache(tuple);
+
+	/*
+	 * Same as create_base_type, require the owner of the type to also own the
+	 * underlying functions.
+	 */
+	if (!PG_PROC_OWNERCHECK(typmodInFuncId, GetUserId()))
+		aclcheck_error(ACLCHECK_NOT_OWNER, OBJECT_FUNCTION, get_func_name(typmodInFuncId));
+	if (!PG_PROC_OWNERCHECK(typmodOutFuncId, GetUserId()))
+		aclcheck_error(ACLCHECK_NOT_OWNER, OBJECT_FUNCTION, get_func_name(typmodOutFuncId));
+
+	check_user_typmod_func(typmodInFuncId, typeNamespace, true);
+	check_user_typmod_func(typmodOutFuncId, typeNamespace, false);
+
+	/*
+	 * C version typmod_in function accepts a single argument of type CSTRING[]
+	 * and returns type INT4; C version typmod_out function accepts a single
+	 * argument of type INT4 and returns type CSTRING.
+	 */
+	typmodInParamType = CSTRINGARRAYOID;
+	typmodOutParamType = INT4OID;
+	typmodInName = makeObjectName(typeName, NULL, TLE_TYPMOD_IN_FUNC_STR);
+	typmodOutName = makeObjectName(typeName, NULL, TLE_TYPMOD_OUT_FUNC_STR);
+
+	if (OidIsValid(LookupFuncName(list_make2(makeString(get_namespace_name(typeNamespace)),
+											 makeString(typmodInName)),
+								  1, &typmodInParamType, true)))
+		ereport(ERROR,
+				(errcode(ERRCODE_DUPLICATE_FUNCTION),
+				 errmsg("function \"%s\" already exists", typmodInName)));
+
+	if (OidIsValid(LookupFuncName(list_make2(makeString(get_namespace_name(typeNamespace)),
+											 makeString(typmodOutName)),
+								  1, &typmodOutParamType, true)))
+		ereport(ERROR,
+				(errcode(ERRCODE_DUPLICATE_FUNCTION),
+				 errmsg("function \"%s\" already exists", typmodOutName)));
+
+	typmodInOid = create_c_func_internal(typeNamespace, typmodInFuncId, typmodInName,
+										 buildoidvector(&typmodInParamType, 1),
+										 INT4OID, TLE_BASE_TYPE_TYPMOD_IN,
+										 funcProbin);
+
+	typmodOutOid = create_c_func_internal(typeNamespace, typmodOutFuncId, typmodOutName,
+										  buildoidvector(&typmodOutParamType, 1),
+										  CSTRINGOID, TLE_BASE_TYPE_TYPMOD_OUT,
+										  funcProbin);
+
+	rel = table_open(TypeRelationId, RowExclusiveLock);
+	tuple = SearchSysCacheCopy1(TYPEOID, ObjectIdGetDatum(typeOid));
+	if (!HeapTupleIsValid(tuple))
+		elog(ERROR, "cache lookup failed for type %u", typeOid);
+
+	typeForm = (Form_pg_type) GETSTRUCT(tuple);
+	typeForm->typmodin = typmodInOid;
+	typeForm->typmodout = typmodOutOid;
+	CatalogTupleUpdate(rel, &tuple->t_self, tuple);
+	heap_freetuple(tuple);
+	table_close(rel, RowExclusiveLock);
+
+	/*
+	 * Record the same dependencies TypeCreate would have recorded for the
+	 * typmod_in and typmod_out functions.
+	 */
+	typeAddress.classId = TypeRelationId;
+	typeAddress.objectId = typeOid;
+	typeAddress.objectSubId = 0;
+
+	funcAddress.classId = ProcedureRelationId;
+	funcAddress.objectId = typmodInOid;
+	funcAddress.objectSubId = 0;
+	recordDependencyOn(&typeAddress, &funcAddress, DEPENDENCY_NORMAL);
+
+	funcAddress.objectId = typmodOutOid;
+	recordDependencyOn(&typeAddress, &funcAddress, DEPENDENCY_NORMAL);
+
+	PG_RETURN_VOID();
+}
+
+/*
+ * check_user_typmod_func
+ *
+ * Check a user-defined typmod_in/typmod_out function meets pg_tle specific requirements:
+ * 1. must be defined in a trusted language (We check it's not in C or internal for now);
+ * 2. typmod_in must accept a single argument of type text[] and return type int4,
+ *    typmod_out must accept a single argument of type int4 and return type text;
+ * 3. must be in the same namespace as the base type;
+ * 4. must be strict and immutable.
+ *
+ * Raise an error if any requirement is not met.
+ */
+static void
+check_user_typmod_func(Oid funcid, Oid expectedNamespace, bool typmodIn)
+{
+	HeapTuple	tuple;
+	Form_pg_proc proc;
+	Oid			prolang;
+	Oid			prorettype;
+	Oid			namespace;
+	bool		proisstrict;
+	char		provolatile;
+	char	   *funcType;
+	Oid			expectedArgType;
+	Oid			expectedRetType;
+
+	tuple = SearchSysCache1(PROCOID, ObjectIdGetDatum(funcid));
+	if (!HeapTupleIsValid(tuple))
+		elog(ERROR, "cache lookup failed for function %u", funcid);
+	proc = (Form_pg_proc) GETSTRUCT(tuple);
+
+	funcType = typmodIn ? TLE_TYPMOD_IN_FUNC_STR : TLE_TYPMOD_OUT_FUNC_STR;
+	expectedArgType = typmodIn ? TEXTARRAYOID : INT4OID;
+	expectedRetType = typmodIn ? INT4OID : TEXTOID;
+	if (proc->pronargs != 1 || proc->proargtypes.values[0] != expectedArgType)
+	{
+		ReleaseSysCache(tuple);
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s function must accept one argument of type %s",
+						funcType, format_type_be(expectedArgType))));
+	}
+
+	prolang = proc->prolang;
+	prorettype = proc->prorettype;
+	namespace = proc->pronamespace;
+	proisstrict = proc->proisstrict;
+	provolatile = proc->provolatile;
+	ReleaseSysCache(tuple);
+
+	if (prolang == INTERNALlanguageId || prolang == ClanguageId)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s function cannot be defined in C or internal",
+						funcType)));
+
+	if (prorettype != expectedRetType)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s functions must return type %s",
+						funcType, format_type_be(expectedRetType))));
+
+	if (namespace != expectedNamespace)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s functions must exist in the same namespace as the type",
+						funcType)));
+
+	if (!proisstrict)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s functions must be strict",
+						funcType)));
+
+	if (provolatile != PROVOLATILE_IMMUTABLE)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("type %s functions must be immutable",
+						funcType)));
+}
+
+/*
+ * Cache of the user-defined function of each C-version typmod_in/typmod_out
+ * function, so that pg_depend is not scanned every time a type modifier is
+ * parsed or printed.
+ */
+typedef struct TypmodFuncCacheEntry
+{
+	Oid			cfuncid;		/* hash key, must be first */
+	Oid			user_funcid;
+} TypmodFuncCacheEntry;
+
+static HTAB *typmod_func_cache = NULL;
+
+/*
+ * invalidate_typmod_func_cache
+ *
+ * Syscache callback, empties the cache whenever a function changes.
+ */
+static void
+invalidate_typmod_func_cache(Datum arg, int cacheid, uint32 hashvalue)
+{
+	HASH_SEQ_STATUS status;
+	TypmodFuncCacheEntry *entry;
+
+	if (typmod_func_cache == NULL)
+		return;
+
+	hash_seq_init(&status, typmod_func_cache);
+	while ((entry = (TypmodFuncCacheEntry *) hash_seq_search(&status)) != NULL)
+		hash_search(typmod_func_cache, &entry->cfuncid, HASH_REMOVE, NULL);
+}
+
+/*
+ * find_user_typmod_func
+ *
+ * Given a C-version typmod_in/typmod_out function, find the corresponding user-defined
+ * function through the dependency recorded by create_c_func_internal.
+ * Raise an error if such function cannot be found.
+ */
+static Oid
+find_user_typmod_func(Oid cfuncid)
+{
+	Relation	depRel;
+	ScanKeyData key[3];
+	SysScanDesc depScan;
+	HeapTuple	depTup;
+	Oid			procOid = InvalidOid;
+	TypmodFuncCacheEntry *entry;
+
+	if (typmod_func_cache == NULL)
+	{
+		HASHCTL		ctl;
+
+		ctl.keysize = sizeof(Oid);
+		ctl.entrysize = sizeof(TypmodFuncCacheEntry);
+		ctl.hcxt = CacheMemoryContext;
+		typmod_func_cache = hash_create("pg_tle typmod function cache", 16, &ctl,
+										HASH_ELEM | HASH_BLOBS | HASH_CONTEXT);
+		CacheRegisterSyscacheCallback(PROCOID, invalidate_typmod_func_cache, (Datum) 0);
+	}
+
+	entry = (TypmodFuncCacheEntry *) hash_search(typmod_func_cache, &cfuncid, HASH_FIND, NULL);
+	if (entry != NULL)
+		return entry->user_funcid;
+
+	depRel = table_open(DependRelationId, AccessShareLock);
+
+	ScanKeyInit(&key[0],
+				Anum_pg_depend_classid,
+				BTEqualStrategyNumber, F_OIDEQ,
+				ObjectIdGetDatum(ProcedureRelationId));
+	ScanKeyInit(&key[1],
+				Anum_pg_depend_objid,
+				BTEqualStrategyNumber, F_OIDEQ,
+				ObjectIdGetDatum(cfuncid));
+	ScanKeyInit(&key[2],
+				Anum_pg_depend_objsubid,
+				BTEqualStrategyNumber, F_INT4EQ,
+				Int32GetDatum(0));
+
+	depScan = systable_beginscan(depRel, DependDependerIndexId, true,
+								 NULL, 3, key);
+
+	while (HeapTupleIsValid(depTup = systable_getnext(depScan)))
+	{
+		Form_pg_depend pg_depend = (Form_pg_depend) GETSTRUCT(depTup);
+
+		if (pg_depend->refclassid == ProcedureRelationId &&
+			pg_depend->deptype == DEPENDENCY_NORMAL)
+		{
+			procOid = pg_depend->refobjid;
+			break;
+		}
+	}
+
+	systable_endscan(depScan);
+	table_close(depRel, AccessShareLock);
+
+	if (!OidIsValid(procOid))
+		elog(ERROR, "could not find user-defined function for function %u", cfuncid);
+
+	entry = (TypmodFuncCacheEntry *) hash_search(typmod_func_cache, &cfuncid, HASH_ENTER, NULL);
+	entry->user_funcid = procOid;
+
+	return procOid;
+}
+
+/*
+ * pg_tle_base_type_typmod_in
+ *
+ * This function is used by pg_tle base type C typmod_in function. It converts the type
+ * modifiers to text[] and calls the corresponding user-defined typmod_in function.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_base_type_typmod_in);
+Datum
+pg_tle_base_type_typmod_in(PG_FUNCTION_ARGS)
+{
+	ArrayType  *ta = PG_GETARG_ARRAYTYPE_P(0);
+	Datum	   *elems;
+	int			nelems;
+	int			i;
+	ArrayType  *textArray;
+	Oid			user_typmod_in_function;
+	int32		typmod;
+
+	user_typmod_in_function = find_user_typmod_func(fcinfo->flinfo->fn_oid);
+
+	deconstruct_array(ta, CSTRINGOID, -2, false, TYPALIGN_CHAR,
+					  &elems, NULL, &nelems);
+
+	for (i = 0; i < nelems; i++)
+		elems[i] = CStringGetTextDatum(DatumGetCString(elems[i]));
+
+	textArray = construct_array(elems, nelems, TEXTOID, -1, false, TYPALIGN_INT);
+
+	/*
+	 * Call the user-defined typmod_in function.
+	 */
+	typmod = DatumGetInt32(OidFunctionCall1Coll(user_typmod_in_function, InvalidOid,
+												PointerGetDatum(textArray)));
+
+	if (typmod < 0)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
+				 errmsg("type %s function %s returned invalid type modifier %d",
+						TLE_TYPMOD_IN_FUNC_STR, get_func_name(user_typmod_in_function),
+						typmod)));
+
+	PG_RETURN_INT32(typmod);
+}
+
+/*
+ * pg_tle_base_type_typmod_out
+ *
+ * This function is used by pg_tle base type C typmod_out function. It calls the corresponding
+ * user-defined typmod_out function and converts the result to cstring.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_base_type_typmod_out);
+Datum
+pg_tle_base_type_typmod_out(PG_FUNCTION_ARGS)
+{
+	int32		typmod = PG_GETARG_INT32(0);
+	Oid			user_typmod_out_function;
+	Datum		result;
+
+	user_typmod_out_function = find_user_typmod_func(fcinfo->flinfo->fn_oid);
+
+	/*
+	 * Call the user-defined typmod_out function.
+	 */
+	result = OidFunctionCall1Coll(user_typmod_out_function, InvalidOid,
+								  Int32GetDatum(typmod));
+
+	PG_RETURN_CSTRING(TextDatumGetCString(result));
+}
+
+/*
+ * pg_tle_create_aggregate
+ *
+ * Creates an aggregate from functions defined in trusted languages. The transition function
+ * must accept the state type followed by the aggregate argument types, the final function
+ * must accept the state type and the combine function must accept two arguments of the state
+ * type. A combine function allows the aggregate to be used in parallel aggregation when it is
+ * declared parallel safe.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_create_aggregate);
+Datum
+pg_tle_create_aggregate(PG_FUNCTION_ARGS)
+{
+	Oid			aggNamespace;
+	char	   *aggName;
+	ArrayType  *argTypesArray;
+	Oid			sfuncOid;
+	Oid			stateType;
+	Datum	   *aggArgTypes;
+	bool	   *aggArgNulls;
+	int			nAggArgs;
+	Oid		   *argTypes;
+	int			nargs;
+	AclResult	aclresult;
+	char	   *namespaceName;
+	List	   *aggArgs = NIL;
+	List	   *params = NIL;
+	bool		validSignature;
+	int			i;
+
+	if (PG_ARGISNULL(0) || PG_ARGISNULL(1) || PG_ARGISNULL(2) ||
+		PG_ARGISNULL(3) || PG_ARGISNULL(4))
+		ereport(ERROR,
+				(errcode(ERRCODE_NULL_VALUE_NOT_ALLOWED),
+				 errmsg("aggnamespace, aggname, argtypes, sfunc and stype must not be NULL")));
+
+	aggNamespace = PG_GETARG_OID(0);
+	aggName = NameStr(*PG_GETARG_NAME(1));
+	argTypesArray = PG_GETARG_ARRAYTYPE_P(2);
+	sfuncOid = PG_GETARG_OID(3);
+	stateType = PG_GETARG_OID(4);
+
+	/*
+	 * Even though the SQL function is locked down so only a member of
+	 * pgtle_admin can run this function, let's check and make sure there is
+	 * not a way to bypass that
+	 */
+	check_is_pgtle_admin();
+
+	/*
+	 * Check we have creation rights in target namespace
+	 */
+	aclresult = PG_NAMESPACE_ACLCHECK(aggNamespace, GetUserId(), ACL_CREATE);
+	namespaceName = get_namespace_name(aggNamespace);
+	if (aclresult != ACLCHECK_OK)
+		aclcheck_error(aclresult, OBJECT_SCHEMA, namespaceName);
+
+	deconstruct_array(argTypesArray, REGTYPEOID, sizeof(Oid), true, TYPALIGN_INT,
+					  &aggArgTypes, &aggArgNulls, &nAggArgs);
+
+	for (i = 0; i < nAggArgs; i++)
+	{
+		FunctionParameter *fp;
+
+		if (aggArgNulls[i])
+			ereport(ERROR,
+					(errcode(ERRCODE_NULL_VALUE_NOT_ALLOWED),
+					 errmsg("aggregate argument type cannot be NULL")));
+
+		fp = makeNode(FunctionParameter);
+		fp->name = NULL;
+		fp->argType = makeTypeNameFromOid(DatumGetObjectId(aggArgTypes[i]), -1);
+		fp->mode = FUNC_PARAM_IN;
+		fp->defexpr = NULL;
+		aggArgs = lappend(aggArgs, fp);
+	}
+
+	/*
+	 * DefineAggregate looks up the support functions by name and argument
+	 * types, so make sure the functions we were given are the ones it will
+	 * find.
+	 */
+	check_user_trusted_func(sfuncOid);
+	get_func_signature(sfuncOid, &argTypes, &nargs);
+	validSignature = (nargs == nAggArgs + 1 && argTypes[0] == stateType);
+	for (i = 0; validSignature && i < nAggArgs; i++)
+		validSignature = (argTypes[i + 1] == DatumGetObjectId(aggArgTypes[i]));
+	if (!validSignature)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("transition function must accept an argument of type %s followed by the aggregate argument types",
+						format_type_be(stateType))));
+
+	params = lappend(params, makeDefElem("sfunc",
+										 (Node *) get_qualified_funcname(sfuncOid),
+										 -1));
+	params = lappend(params, makeDefElem("stype",
+										 (Node *) makeTypeNameFromOid(stateType, -1),
+										 -1));
+
+	if (!PG_ARGISNULL(5))
+	{
+		Oid			finalfuncOid = PG_GETARG_OID(5);
+
+		check_user_trusted_func(finalfuncOid);
+		get_func_signature(finalfuncOid, &argTypes, &nargs);
+		if (nargs != 1 || argTypes[0] != stateType)
+			ereport(ERROR,
+					(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+					 errmsg("final function must accept one argument of type %s",
+							format_type_be(stateType))));
+
+		params = lappend(params, makeDefElem("finalfunc",
+											 (Node *) get_qualified_funcname(finalfuncOid),
+											 -1));
+	}
+
+	if (!PG_ARGISNULL(6))
+	{
+		Oid			combinefuncOid = PG_GETARG_OID(6);
+
+		check_user_trusted_func(combinefuncOid);
+		get_func_signature(combinefuncOid, &argTypes, &nargs);
+		if (nargs != 2 || argTypes[0] != stateType || argTypes[1] != stateType)
+			ereport(ERROR,
+					(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+					 errmsg("combine function must accept two arguments of type %s",
+							format_type_be(stateType))));
+
+		params = lappend(params, makeDefElem("combinefunc",
+											 (Node *) get_qualified_funcname(combinefuncOid),
+											 -1));
+	}
+
+	if (!PG_ARGISNULL(7))
+		params = lappend(params, makeDefElem("initcond",
+											 (Node *) makeString(text_to_cstring(PG_GETARG_TEXT_PP(7))),
+											 -1));
+
+	if (!PG_ARGISNULL(8))
+		params = lappend(params, makeDefElem("parallel",
+											 (Node *) makeString(text_to_cstring(PG_GETARG_TEXT_PP(8))),
+											 -1));
+
+	/*
+	 * DefineAggregate does the rest of the checks, such as the return type of
+	 * the support functions and the privileges on the state type.
+	 */
+	DEFINE_AGGREGATE(make_parsestate(NULL),
+					 list_make2(makeString(namespaceName), makeString(pstrdup(aggName))),
+					 list_make2(aggArgs, makeInteger(-1)),
+					 false,
+					 params);
+
+	PG_RETURN_VOID();
+}
@@ -0,0 +1,740 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * DDL hook features. Allows users to attach trusted language functions to
+ * the start (ddl_command_start) and the end (ddl_command_end) of DDL
+ * commands, in the same way as event triggers, but without having to create
+ * event triggers in every database.
+ *
+ * The functions are called from ProcessUtility_hook for the commands that
+ * event triggers support. An error raised by a function aborts the command.
+ *
+ * If pgtle.ddl_hook_db_name is empty, the functions registered in the
+ * current database are called directly by the backend running the command.
+ * Otherwise, the functions registered in pgtle.ddl_hook_db_name are called
+ * for DDL commands in every database, by a background worker connected to
+ * that database. This uses the same single-entry shared memory protocol as
+ * passcheck.
+ */
+#include "postgres.h"
+#include "access/xact.h"
+#include "catalog/pg_authid.h"
+#include "catalog/pg_type.h"
+#include "commands/dbcommands.h"
+#include "commands/event_trigger.h"
+#include "commands/extension.h"
+#include "executor/spi.h"
+#include "nodes/pg_list.h"
+#include "parser/scansup.h"
+#include "tcop/utility.h"
+#include "utils/builtins.h"
+#include "utils/elog.h"
+#include "utils/errcodes.h"
+#include "utils/guc.h"
+#include "utils/memutils.h"
+#include "utils/snapmgr.h"
+
+#include "compatibility.h"
+#include "constants.h"
+#include "ddlhook.h"
+#include "feature.h"
+#include "tleextension.h"
+
+/* These are necessary for background worker */
+#include "miscadmin.h"
+#include "postmaster/bgworker.h"
+#include "pgstat.h"
+#include "storage/condition_variable.h"
+#include "storage/ipc.h"
+#include "storage/lwlock.h"
+#include "storage/proc.h"
+#include "storage/procarray.h"
+#include "storage/shmem.h"
+
+/* Maximum length of strings (including \0) passed to the background worker */
+#define DDL_HOOK_DATA_MAX_STRLEN 256
+#define DDL_HOOK_COMMAND_MAX_STRLEN 8192
+#define DDL_HOOK_ERROR_MSG_MAX_STRLEN 4096
+
+static const char *ddl_command_start_feature = "ddl_command_start";
+static const char *ddl_command_end_feature = "ddl_command_end";
+static const char *ddlhook_shmem_name = "pgtle_ddlhook";
+static const char *ddlhook_worker_name = "pg_tle_ddlhook worker";
+
+void		ddlhook_init(void);
+
+static ProcessUtility_hook_type prev_process_utility_hook = NULL;
+
+_PROCESS_UTILITY_HOOK(ddlhook_process_utility);
+
+PGDLLEXPORT void ddlhook_worker_main(Datum arg);
+
+static shmem_startup_hook_type prev_shmem_startup_hook = NULL;
+static void ddlhook_shmem_startup(void);
+
+#if (PG_VERSION_NUM >= 150000)
+static shmem_request_hook_type prev_shmem_request_hook = NULL;
+static void ddlhook_shmem_request(void);
+#endif
+
+static Size ddlhook_shared_memsize(void);
+
+/* GUC that determines whether the DDL hooks are enabled */
+static int	enable_ddl_hook_feature = FEATURE_OFF;
+
+/* GUC that determines which database the functions are called in */
+static char *ddl_hook_database_name = "";
+
+/* Whether the functions are being called, to skip their own commands */
+static bool ddlhook_in_progress = false;
+
+/* Represents the DDL command passed to the user functions */
+typedef struct DDLHookData
+{
+	char		event[DDL_HOOK_DATA_MAX_STRLEN];
+	char		command_tag[DDL_HOOK_DATA_MAX_STRLEN];
+	char		command[DDL_HOOK_COMMAND_MAX_STRLEN];
+	char		database_name[DDL_HOOK_DATA_MAX_STRLEN];
+	char		user_name[DDL_HOOK_DATA_MAX_STRLEN];
+}			DDLHookData;
+
+/* Shared state used to communicate between client process and background worker. */
+typedef struct DDLHookBgwShmemSharedState
+{
+	LWLock	   *lock;
+
+	ConditionVariable available_cv;
+	ConditionVariable client_cv;
+	bool		available_entry;
+	bool		done_processing;
+
+	/* PID of backend that is currently running the DDL hook */
+	int			pid;
+
+	DDLHookData data;
+	bool		error;
+	char		error_msg[DDL_HOOK_ERROR_MSG_MAX_STRLEN];
+	char		error_hint[DDL_HOOK_ERROR_MSG_MAX_STRLEN];
+}			DDLHookBgwShmemSharedState;
+
+static DDLHookBgwShmemSharedState * ddlhook_ss = NULL;
+
+static bool is_ddl_command(Node *parsetree);
+static void ddlhook_run(const char *event, PlannedStmt *pstmt, const char *queryString);
+static void ddlhook_run_in_worker(DDLHookData * ddlhook_data);
+static void ddlhook_run_user_functions(DDLHookData * ddlhook_data);
+
+void
+ddlhook_init(void)
+{
+	DefineCustomEnumVariable("pgtle.enable_ddl_hook",
+							 gettext_noop("Sets the behavior for interacting with the pg_tle DDL hook features."),
+							 NULL,
+							 &enable_ddl_hook_feature,
+							 FEATURE_OFF,
+							 feature_mode_options,
+							 PGC_SIGHUP,
+							 GUC_SUPERUSER_ONLY,
+							 NULL, NULL, NULL);
+
+	DefineCustomStringVariable(
+							   "pgtle.ddl_hook_db_name",
+							   gettext_noop("Database containing pg_tle DDL hook functions that are called for all databases."),
+							   NULL,
+							   &ddl_hook_database_name,
+							   "",
+							   PGC_SIGHUP,
+							   GUC_SUPERUSER_ONLY,
+							   NULL, NULL, NULL);
+
+	/* Do not register hooks if we are in pg_upgrade */
+	if (IsBinaryUpgrade)
+		return;
+
+#if (PG_VERSION_NUM < 150000)
+	RequestNamedLWLockTranche(ddlhook_shmem_name, 1);
+	RequestAddinShmemSpace(ddlhook_shared_memsize());
+#endif
+
+	/* PG15 requires shared memory space to be requested in shmem_request_hook */
+#if (PG_VERSION_NUM >= 150000)
+	prev_shmem_request_hook = shmem_request_hook;
+	shmem_request_hook = ddlhook_shmem_request;
+#endif
+
+	/* Install our shmem hooks */
+	prev_shmem_startup_hook = shmem_startup_hook;
+	shmem_startup_hook = ddlhook_shmem_startup;
+
+	/*
+	 * The hook is always installed so that the features can be enabled
+	 * without a restart.
+	 */
+	prev_process_utility_hook = ProcessUtility_hook;
+	ProcessUtility_hook = ddlhook_process_utility;
+}
+
+/*
+ * ddlhook_process_utility
+ *
+ * Calls the ddl_command_start functions before the command is executed and
+ * the ddl_command_end functions after it is executed.
+ */
+_PROCESS_UTILITY_HOOK(ddlhook_process_utility)
+{
+	bool		run_hooks;
+
+	/*
+	 * Like event triggers, skip commands that are part of another command,
+	 * and commands run in single-user mode.
+	 */
+	run_hooks = enable_ddl_hook_feature != FEATURE_OFF &&
+		!ddlhook_in_progress &&
+		IsUnderPostmaster &&
+		context != PROCESS_UTILITY_SUBCOMMAND &&
+		is_ddl_command(pu_parsetree);
+
+	/*
+	 * pgtle.feature_info does not exist until the pg_tle install script has
+	 * created it, so skip the commands of pg_tle's own scripts.
+	 */
+	if (run_hooks && creating_extension &&
+		CurrentExtensionObject == get_extension_oid(PG_TLE_EXTNAME, true))
+		run_hooks = false;
+
+	if (run_hooks)
+		ddlhook_run(ddl_command_start_feature, pstmt, queryString);
+
+	if (prev_process_utility_hook)
+		_prev_process_utility_hook(prev_process_utility_hook);
+	else
+		_standard_ProcessUtility;
+
+	if (run_hooks)
+		ddlhook_run(ddl_command_end_feature, pstmt, queryString);
+}
+
+/*
+ * Returns whether the command is one that event triggers are called for,
+ * i.e. it is not one of the commands that standard_ProcessUtility handles
+ * itself. Commands on shared objects, such as databases and roles, are not
+ * included. Like standard_ProcessUtility, this checks the object type of the
+ * commands that can act on both shared and other objects.
+ */
+static bool
+is_ddl_command(Node *parsetree)
+{
+	switch (nodeTag(parsetree))
+	{
+		case T_TransactionStmt:
+		case T_DeclareCursorStmt:
+		case T_ClosePortalStmt:
+		case T_FetchStmt:
+		case T_DoStmt:
+		case T_CreateTableSpaceStmt:
+		case T_DropTableSpaceStmt:
+		case T_AlterTableSpaceOptionsStmt:
+		case T_TruncateStmt:
+		case T_CopyStmt:
+		case T_PrepareStmt:
+		case T_ExecuteStmt:
+		case T_DeallocateStmt:
+		case T_GrantRoleStmt:
+		case T_CreatedbStmt:
+		case T_AlterDatabaseStmt:
+		case T_AlterDatabaseSetStmt:
+		case T_DropdbStmt:
+		case T_NotifyStmt:
+		case T_ListenStmt:
+		case T_UnlistenStmt:
+		case T_LoadStmt:
+		case T_CallStmt:
+		case T_ClusterStmt:
+		case T_VacuumStmt:
+		case T_ExplainStmt:
+		case T_AlterSystemStmt:
+		case T_VariableSetStmt:
+		case T_VariableShowStmt:
+		case T_DiscardStmt:
+		case T_CreateEventTrigStmt:
+		case T_AlterEventTrigStmt:
+		case T_CreateRoleStmt:
+		case T_AlterRoleStmt:
+		case T_AlterRoleSetStmt:
+		case T_DropRoleStmt:
+		case T_ReassignOwnedStmt:
+		case T_LockStmt:
+		case T_ConstraintsSetStmt:
+		case T_CheckPointStmt:
+		case T_ReindexStmt:
+			return false;
+		case T_DropStmt:
+			return EventTriggerSupportsObjectType(((DropStmt *) parsetree)->removeType);
+		case T_RenameStmt:
+			return EventTriggerSupportsObjectType(((RenameStmt *) parsetree)->renameType);
+		case T_AlterObjectDependsStmt:
+			return EventTriggerSupportsObjectType(((AlterObjectDependsStmt *) parsetree)->objectType);
+		case T_AlterObjectSchemaStmt:
+			return EventTriggerSupportsObjectType(((AlterObjectSchemaStmt *) parsetree)->objectType);
+		case T_AlterOwnerStmt:
+			return EventTriggerSupportsObjectType(((AlterOwnerStmt *) parsetree)->objectType);
+		case T_CommentStmt:
+			return EventTriggerSupportsObjectType(((CommentStmt *) parsetree)->objtype);
+		case T_GrantStmt:
+			return EventTriggerSupportsObjectType(((GrantStmt *) parsetree)->objtype);
+		case T_SecLabelStmt:
+			return EventTriggerSupportsObjectType(((SecLabelStmt *) parsetree)->objtype);
+		default:
+			return true;
+	}
+}
+
+/*
+ * Calls the functions registered to the event, either directly or in a
+ * background worker. Raises an error if any of the functions fails.
+ */
+static void
+ddlhook_run(const char *event, PlannedStmt *pstmt, const char *queryString)
+{
+	DDLHookData data;
+	int			location = pstmt->stmt_location;
+	int			len = pstmt->stmt_len;
+	bool		pushed_snapshot = false;
+
+	/* Only pass the text of this command, as with pg_stat_statements */
+	if (location < 0)
+	{
+		location = 0;
+		len = strlen(queryString);
+	}
+	else if (len <= 0)
+		len = strlen(queryString) - location;
+
+	/* Discard leading and trailing whitespace */
+	while (len > 0 && scanner_isspace(queryString[location]))
+	{
+		location++;
+		len--;
+	}
+	while (len > 0 && scanner_isspace(queryString[location + len - 1]))
+		len--;
+
+	snprintf(data.event, DDL_HOOK_DATA_MAX_STRLEN, "%s", event);
+	snprintf(data.command_tag, DDL_HOOK_DATA_MAX_STRLEN, "%s", GET_COMMAND_TAG_NAME(pu_parsetree));
+	snprintf(data.command, DDL_HOOK_COMMAND_MAX_STRLEN, "%.*s", len, queryString + location);
+	snprintf(data.database_name, DDL_HOOK_DATA_MAX_STRLEN, "%s", get_database_name(MyDatabaseId));
+	snprintf(data.user_name, DDL_HOOK_DATA_MAX_STRLEN, "%s", GetUserNameFromId(GetUserId(), false));
+
+	if (strcmp("", ddl_hook_database_name) != 0)
+	{
+		/*
+		 * Call the functions directly for commands in the DDL hook database
+		 * itself, since the worker would wait for any lock held by the
+		 * command, e.g. ALTER TABLE of a table that the functions use.
+		 * They are called as the user running the command, as when no DDL
+		 * hook database is set: the session's search_path and settings are
+		 * under that user's control, so calling them as superuser here would
+		 * let the user hijack them.
+		 */
+		if (get_database_oid(ddl_hook_database_name, true) != MyDatabaseId)
+		{
+			ddlhook_run_in_worker(&data);
+			return;
+		}
+	}
+
+	/*
+	 * Some commands, e.g. CREATE INDEX CONCURRENTLY, return without an
+	 * active snapshot.
+	 */
+	if (!ActiveSnapshotSet())
+	{
+		PushActiveSnapshot(GetTransactionSnapshot());
+		pushed_snapshot = true;
+	}
+
+	ddlhook_in_progress = true;
+	PG_TRY();
+	{
+		ddlhook_run_user_functions(&data);
+	}
+	PG_FINALLY();
+	{
+		ddlhook_in_progress = false;
+	}
+	PG_END_TRY();
+
+	if (pushed_snapshot)
+		PopActiveSnapshot();
+}
+
+/*
+ * Control flow is the same as passcheck:
+ *
+ * 1. Client sleeps on available_cv until available_entry is true.
+ *
+ * 2. Client releases lock and spins up a background worker.
+ *
+ * 3. Client acquires lock and writes the command to shared memory. Sets
+ * available_entry to false. Client sleeps on client_cv until
+ * done_processing is true.
+ *
+ * 4. Background worker acquires lock. It executes the user's registered
+ * functions if needed.
+ *
+ * 5. Background worker writes the results to shared memory. It sets
+ * done_processing to true, releases lock, signals client_cv, and terminates.
+ *
+ * 6. Client acquires lock, copies results from shared memory, sets
+ * available_entry to true, signals available_cv, releases lock, and returns.
+ */
+static void
+ddlhook_run_in_worker(DDLHookData * ddlhook_data)
+{
+	BackgroundWorker worker;
+	BackgroundWorkerHandle *worker_handle = NULL;
+	bool		error;
+	char		error_msg[DDL_HOOK_ERROR_MSG_MAX_STRLEN];
+	char		error_hint[DDL_HOOK_ERROR_MSG_MAX_STRLEN];
+
+	/* 1. Sleep on available_cv until available_entry is true. */
+	ConditionVariablePrepareToSleep(&ddlhook_ss->available_cv);
+	while (true)
+	{
+		LWLockAcquire(ddlhook_ss->lock, LW_EXCLUSIVE);
+
+		/*
+		 * Check if the process that's holding this entry still exists. If it
+		 * doesn't then it must have terminated uncleanly and we can set
+		 * available_entry to true.
+		 */
+		if (!BackendPidGetProc(ddlhook_ss->pid))
+			ddlhook_ss->available_entry = true;
+
+		/*
+		 * In case the previous client terminated uncleanly, make sure the
+		 * background worker is finished with the previous client before we
+		 * continue.
+		 */
+		if (ddlhook_ss->available_entry && ddlhook_ss->done_processing)
+			break;
+
+		LWLockRelease(ddlhook_ss->lock);
+		ConditionVariableSleep(&ddlhook_ss->available_cv, WAIT_EVENT_MESSAGE_QUEUE_RECEIVE);
+	}
+	ConditionVariableCancelSleep();
+
+	/* Guarantee that the database exists before spinning up the worker */
+	if (get_database_oid(ddl_hook_database_name, true) == InvalidOid)
+	{
+		LWLockRelease(ddlhook_ss->lock);
+		ereport(ERROR,
+				errcode(ERRCODE_DATA_EXCEPTION),
+				errmsg("the DDL hook database \"%s\" does not exist", ddl_hook_database_name),
+				errhint("Check the value of pgtle.ddl_hook_db_name."));
+	}
+
+	/* 2. Spin up background worker. */
+	worker.bgw_flags = BGWORKER_SHMEM_ACCESS | BGWORKER_BACKEND_DATABASE_CONNECTION;
+	worker.bgw_start_time = BgWorkerStart_ConsistentState;
+	worker.bgw_restart_time = BGW_NEVER_RESTART;
+	worker.bgw_notify_pid = MyProcPid;
+	sprintf(worker.bgw_library_name, PG_TLE_EXTNAME);
+	sprintf(worker.bgw_function_name, "ddlhook_worker_main");
+	snprintf(worker.bgw_type, BGW_MAXLEN, "%s", ddlhook_worker_name);
+	snprintf(worker.bgw_name, BGW_MAXLEN, "%s", ddlhook_worker_name);
+
+	if (!RegisterDynamicBackgroundWorker(&worker, &worker_handle))
+	{
+		LWLockRelease(ddlhook_ss->lock);
+		ereport(ERROR,
+				errmsg("%s DDL hook feature failed to spawn background worker", PG_TLE_EXTNAME),
+				errhint("Consider increasing max_worker_processes or reducing other background workers."));
+	}
+
+	/* 3. Write the command to shared memory. */
+	ddlhook_ss->pid = MyProc->pid;
+	memcpy(&ddlhook_ss->data, ddlhook_data, sizeof(DDLHookData));
+
+	ddlhook_ss->available_entry = false;
+	ddlhook_ss->done_processing = false;
+	LWLockRelease(ddlhook_ss->lock);
+
+	ConditionVariablePrepareToSleep(&ddlhook_ss->client_cv);
+	while (true)
+	{
+		LWLockAcquire(ddlhook_ss->lock, LW_EXCLUSIVE);
+		if (ddlhook_ss->done_processing)
+			break;
+
+		LWLockRelease(ddlhook_ss->lock);
+		ConditionVariableSleep(&ddlhook_ss->client_cv, WAIT_EVENT_MESSAGE_QUEUE_RECEIVE);
+	}
+	ConditionVariableCancelSleep();
+
+	/*
+	 * Background worker has done steps 4 and 5. Make sure it is terminated
+	 * and unregistered.
+	 */
+	TerminateBackgroundWorker(worker_handle);
+
+	/* 6. Copy results from shared memory and finish processing. */
+	error = ddlhook_ss->error;
+	snprintf(error_msg, DDL_HOOK_ERROR_MSG_MAX_STRLEN, "%s", ddlhook_ss->error_msg);
+	snprintf(error_hint, DDL_HOOK_ERROR_MSG_MAX_STRLEN, "%s", ddlhook_ss->error_hint);
+
+	/* Erase data about this request from shared memory now that we're done */
+	memset(&ddlhook_ss->data, 0, sizeof(DDLHookData));
+	ddlhook_ss->error = false;
+	memset(ddlhook_ss->error_msg, 0, sizeof(char) * DDL_HOOK_ERROR_MSG_MAX_STRLEN);
+	memset(ddlhook_ss->error_hint, 0, sizeof(char) * DDL_HOOK_ERROR_MSG_MAX_STRLEN);
+
+	ddlhook_ss->available_entry = true;
+	LWLockRelease(ddlhook_ss->lock);
+	ConditionVariableSignal(&ddlhook_ss->available_cv);
+
+	if (error)
+	{
+		if (strcmp(error_hint, "") == 0)
+			ereport(ERROR, errcode(ERRCODE_DATA_EXCEPTION), errmsg("%s", error_msg));
+		else
+			ereport(ERROR, errcode(ERRCODE_DATA_EXCEPTION), errmsg("%s", error_msg), errhint("%s", error_hint));
+	}
+}
+
+void
+ddlhook_worker_main(Datum arg)
+{
+	MemoryContext old_context;
+	ResourceOwner old_owner;
+
+	DDLHookData ddlhook_data;
+	bool		error = false;
+	char		error_msg[DDL_HOOK_ERROR_MSG_MAX_STRLEN];
+	char		error_hint[DDL_HOOK_ERROR_MSG_MAX_STRLEN];
+
+	error_msg[0] = '\0';
+	error_hint[0] = '\0';
+
+	pqsignal(SIGTERM, die);
+	BackgroundWorkerUnblockSignals();
+
+	/* Do not call the DDL hooks for commands run by the user's functions */
+	ddlhook_in_progress = true;
+
+	/* Copy data from shared memory */
+	LWLockAcquire(ddlhook_ss->lock, LW_SHARED);
+	memcpy(&ddlhook_data, &ddlhook_ss->data, sizeof(DDLHookData));
+	LWLockRelease(ddlhook_ss->lock);
+
+	/*
+	 * Initialize connection to the database. ddlhook_run_in_worker has
+	 * already confirmed that the database exists.
+	 */
+	BackgroundWorkerInitializeConnection(ddl_hook_database_name, NULL, 0);
+
+	/* Start a transaction in which we can run queries */
+	SetCurrentStatementStartTimestamp();
+	StartTransactionCommand();
+	PushActiveSnapshot(GetTransactionSnapshot());
+
+	/* 4. Background worker calls the user's functions. */
+	old_context = CurrentMemoryContext;
+	old_owner = CurrentResourceOwner;
+
+	BeginInternalSubTransaction(NULL);
+	PG_TRY();
+	{
+		ddlhook_run_user_functions(&ddlhook_data);
+
+		ReleaseCurrentSubTransaction();
+		MemoryContextSwitchTo(old_context);
+		CurrentResourceOwner = old_owner;
+	}
+	PG_CATCH();
+	{
+		/*
+		 * There is a query error, copy the error message from SPI and
+		 * rollback the subtransaction.
+		 */
+		ErrorData  *edata;
+
+		SPI_finish();
+
+		MemoryContextSwitchTo(old_context);
+		edata = CopyErrorData();
+		FlushErrorState();
+
+		RollbackAndReleaseCurrentSubTransaction();
+		CurrentResourceOwner = old_owner;
+
+		/* Return the error and hint from SPI to the client */
+		snprintf(error_msg, DDL_HOOK_ERROR_MSG_MAX_STRLEN, "%s", edata->message ? edata->message : "");
+		snprintf(error_hint, DDL_HOOK_ERROR_MSG_MAX_STRLEN, "%s", edata->hint ? edata->hint : "");
+		error = true;
+		FreeErrorData(edata);
+	}
+	PG_END_TRY();
+
+	PopActiveSnapshot();
+	CommitTransactionCommand();
+
+	/* 5. Write result to shared memory */
+	LWLockAcquire(ddlhook_ss->lock, LW_EXCLUSIVE);
+	snprintf(ddlhook_ss->error_msg, DDL_HOOK_ERROR_MSG_MAX_STRLEN, "%s", error_msg);
+	snprintf(ddlhook_ss->error_hint, DDL_HOOK_ERROR_MSG_MAX_STRLEN, "%s", error_hint);
+	ddlhook_ss->error = error;
+	ddlhook_ss->done_processing = true;
+	LWLockRelease(ddlhook_ss->lock);
+
+	/*
+	 * Signal the client that we are done. Just in case the client backend has
+	 * terminated uncleanly, also signal the next waiting client to check
+	 * whether the current client still exists.
+	 */
+	ConditionVariableSignal(&ddlhook_ss->available_cv);
+	ConditionVariableSignal(&ddlhook_ss->client_cv);
+}
+
+/*
+ * Run the user's functions. This procedure should not do any transaction
+ * management (other than opening an SPI connection) or shared memory accesses.
+ */
+static void
+ddlhook_run_user_functions(DDLHookData * ddlhook_data)
+{
+	List	   *proc_names;
+	ListCell   *item;
+	char		database_error_msg[DDL_HOOK_ERROR_MSG_MAX_STRLEN];
+
+	if (strcmp("", ddl_hook_database_name) != 0)
+		snprintf(database_error_msg, DDL_HOOK_ERROR_MSG_MAX_STRLEN, " in the DDL hook database \"%s\"", ddl_hook_database_name);
+	else
+		database_error_msg[0] = '\0';
+
+	/* Check if pg_tle extension is installed */
+	if (get_extension_oid(PG_TLE_EXTNAME, true) == InvalidOid)
+	{
+		if (enable_ddl_hook_feature == FEATURE_REQUIRE)
+			ereport(ERROR,
+					errmsg("\"%s.enable_ddl_hook\" feature is set to require but extension \"%s\" is not installed%s",
+						   PG_TLE_NSPNAME, PG_TLE_EXTNAME, database_error_msg));
+		return;
+	}
+
+	/* Check if any functions are registered to the event */
+	proc_names = feature_proc(ddlhook_data->event);
+	if (list_length(proc_names) <= 0)
+	{
+		/*
+		 * A ddl_command_start or ddl_command_end function is enough to
+		 * satisfy require.
+		 */
+		if (enable_ddl_hook_feature == FEATURE_REQUIRE &&
+			list_length(feature_proc(ddl_command_start_feature)) <= 0 &&
+			list_length(feature_proc(ddl_command_end_feature)) <= 0)
+			ereport(ERROR,
+					errmsg("\"%s.enable_ddl_hook\" feature is set to require, however no entries exist in \"%s.feature_info\" with the feature \"%s\" or \"%s\"%s",
+						   PG_TLE_NSPNAME, PG_TLE_NSPNAME, ddl_command_start_feature,
+						   ddl_command_end_feature, database_error_msg));
+		return;
+	}
+
+	if (SPI_connect() != SPI_OK_CONNECT)
+		ereport(ERROR,
+				(errcode(ERRCODE_CONNECTION_EXCEPTION),
+				 errmsg("\"%s.%s\" feature was not able to connect to the database \"%s\"",
+						PG_TLE_NSPNAME, ddlhook_data->event, get_database_name(MyDatabaseId))));
+
+	foreach(item, proc_names)
+	{
+		char	   *query;
+		char	   *func_name = lfirst(item);
+		Oid			hookargtypes[SPI_NARGS_5] = {TEXTOID, TEXTOID, TEXTOID, TEXTOID, TEXTOID};
+		Datum		hookargs[SPI_NARGS_5];
+
+		/*
+		 * func_name is already using quote_identifier from when it was
+		 * assembled
+		 */
+		query = psprintf("SELECT %s($1::pg_catalog.text, $2::pg_catalog.text, $3::pg_catalog.text, $4::pg_catalog.text, $5::pg_catalog.text)",
+						 func_name);
+
+		hookargs[0] = CStringGetTextDatum(ddlhook_data->event);
+		hookargs[1] = CStringGetTextDatum(ddlhook_data->command_tag);
+		hookargs[2] = CStringGetTextDatum(ddlhook_data->command);
+		hookargs[3] = CStringGetTextDatum(ddlhook_data->database_name);
+		hookargs[4] = CStringGetTextDatum(ddlhook_data->user_name);
+
+		/*
+		 * Not read-only, so that the function can record the command, e.g.
+		 * in an audit table.
+		 */
+		if (SPI_execute_with_args(query, SPI_NARGS_5, hookargtypes, hookargs, NULL, false, 0) != SPI_OK_SELECT)
+			ereport(ERROR,
+					errmsg("unable to execute function \"%s\"", func_name));
+	}
+
+	SPI_finish();
+}
+
+static void
+ddlhook_shmem_startup(void)
+{
+	bool		found;
+
+	if (prev_shmem_startup_hook)
+		prev_shmem_startup_hook();
+
+	LWLockAcquire(AddinShmemInitLock, LW_EXCLUSIVE);
+	ddlhook_ss = ShmemInitStruct(ddlhook_shmem_name, ddlhook_shared_memsize(), &found);
+
+	if (!found)
+	{
+		ddlhook_ss->lock = &(GetNamedLWLockTranche(ddlhook_shmem_name))->lock;
+
+		ConditionVariableInit(&ddlhook_ss->available_cv);
+		ConditionVariableInit(&ddlhook_ss->client_cv);
+		ddlhook_ss->available_entry = true;
+		ddlhook_ss->done_processing = true;
+		ddlhook_ss->pid = 0;
+	}
+
+	LWLockRelease(AddinShmemInitLock);
+}
+
+#if (PG_VERSION_NUM >= 150000)
+static void
+ddlhook_shmem_request(void)
+{
+	if (prev_shmem_request_hook)
+		prev_shmem_request_hook();
+
+	RequestNamedLWLockTranche(ddlhook_shmem_name, 1);
+	RequestAddinShmemSpace(ddlhook_shared_memsize());
+}
+#endif
+
+static Size
+ddlhook_shared_memsize(void)
+{
+	Size		size;
+
+	size = MAXALIGN(sizeof(DDLHookBgwShmemSharedState));
+
+	return size;
+}
@@ -65,8 +65,13 @@ feature_proc(const char *featurename)
 		 * call out to SPI_exec if it doesn't anyway
 		 */
 
-		query = psprintf("SELECT schema_name, proname FROM %s.%s WHERE feature OPERATOR(pg_catalog.=) $1::%s.pg_tle_features ORDER BY proname",
-						 quote_identifier(PG_TLE_NSPNAME), quote_identifier(FEATURE_TABLE), quote_identifier(PG_TLE_NSPNAME));
+		/*
+		 * Compare the feature as text, since the pg_tle installed in this
+		 * database may be a version that does not have this feature in the
+		 * pg_tle_features enum yet.
+		 */
+		query = psprintf("SELECT schema_name, proname FROM %s.%s WHERE feature::pg_catalog.text OPERATOR(pg_catalog.=) $1 ORDER BY proname",
+						 quote_identifier(PG_TLE_NSPNAME), quote_identifier(FEATURE_TABLE));
 		featargs[0] = CStringGetTextDatum(featurename);
 
 		ret = SPI_execute_with_args(query, 1, featargtypes, featargs, NULL, true, 0);
@@ -0,0 +1,567 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * Scheduled jobs for trusted language extensions.
+ *
+ * pgtle.schedule_job registers a function in the pgtle.scheduled_job table
+ * together with the interval at which it runs. A single background worker,
+ * connected to pgtle.job_scheduler_db_name, wakes up every second and calls
+ * the jobs that are due. Each job is called in its own transaction, as the
+ * user who scheduled it, and the outcome of the latest run is recorded in
+ * pgtle.scheduled_job. The user is stored by OID, so a job keeps running as
+ * the same user after it is renamed, and the jobs of a user that was dropped
+ * are removed instead of being run.
+ */
+#include "postgres.h"
+#include "access/xact.h"
+#include "catalog/namespace.h"
+#include "catalog/pg_authid.h"
+#include "catalog/pg_proc.h"
+#include "catalog/pg_type.h"
+#include "commands/dbcommands.h"
+#include "commands/extension.h"
+#include "datatype/timestamp.h"
+#include "executor/spi.h"
+#include "fmgr.h"
+#include "miscadmin.h"
+#include "pgstat.h"
+#include "postmaster/bgworker.h"
+#include "storage/ipc.h"
+#include "storage/latch.h"
+#include "utils/acl.h"
+#include "utils/builtins.h"
+#include "utils/guc.h"
+#include "utils/lsyscache.h"
+#include "utils/memutils.h"
+#include "utils/regproc.h"
+#include "utils/snapmgr.h"
+#include "utils/syscache.h"
+#include "utils/timestamp.h"
+
+#include "compatibility.h"
+#include "constants.h"
+#include "jobscheduler.h"
+#include "tleextension.h"
+
+#define SCHEDULED_JOB_TABLE "scheduled_job"
+
+/* How often the scheduler checks for jobs that are due */
+#define JOB_SCHEDULER_NAPTIME_MS 1000
+
+static const char *job_scheduler_worker_name = "pg_tle_job_scheduler worker";
+
+void		jobscheduler_init(void);
+
+PGDLLEXPORT void jobscheduler_main(Datum arg);
+
+/* GUC that determines whether the job scheduler is started */
+static bool enable_job_scheduler = false;
+
+/* GUC that determines which database the job scheduler runs in */
+static char *job_scheduler_database_name = "postgres";
+
+static volatile sig_atomic_t job_scheduler_reload_config = false;
+
+/* A job that is due, copied out of pgtle.scheduled_job */
+typedef struct ScheduledJob
+{
+	char	   *name;
+	char	   *schema_name;
+	char	   *proname;
+	Oid			run_as;
+}			ScheduledJob;
+
+static void jobscheduler_sighup(SIGNAL_ARGS);
+static List *get_due_jobs(MemoryContext jobcontext);
+static void run_job(ScheduledJob * job);
+
+void
+jobscheduler_init(void)
+{
+	BackgroundWorker worker;
+
+	DefineCustomBoolVariable(
+							 "pgtle.enable_job_scheduler",
+							 gettext_noop("Starts the pg_tle background worker that runs scheduled jobs."),
+							 NULL,
+							 &enable_job_scheduler,
+							 false,
+							 PGC_POSTMASTER,
+							 GUC_SUPERUSER_ONLY,
+							 NULL, NULL, NULL);
+
+	DefineCustomStringVariable(
+							   "pgtle.job_scheduler_db_name",
+							   gettext_noop("Database in which pg_tle scheduled jobs run."),
+							   NULL,
+							   &job_scheduler_database_name,
+							   "postgres",
+							   PGC_POSTMASTER,
+							   GUC_SUPERUSER_ONLY,
+							   NULL, NULL, NULL);
+
+	/* Do not register background workers if we are in pg_upgrade */
+	if (IsBinaryUpgrade)
+		return;
+	if (!enable_job_scheduler)
+		return;
+
+	memset(&worker, 0, sizeof(worker));
+	worker.bgw_flags = BGWORKER_SHMEM_ACCESS | BGWORKER_BACKEND_DATABASE_CONNECTION;
+	worker.bgw_start_time = BgWorkerStart_RecoveryFinished;
+	worker.bgw_restart_time = 10;
+	worker.bgw_notify_pid = 0;
+	sprintf(worker.bgw_library_name, PG_TLE_EXTNAME);
+	sprintf(worker.bgw_function_name, "jobscheduler_main");
+	snprintf(worker.bgw_type, BGW_MAXLEN, "%s", job_scheduler_worker_name);
+	snprintf(worker.bgw_name, BGW_MAXLEN, "%s", job_scheduler_worker_name);
+	RegisterBackgroundWorker(&worker);
+}
+
+static void
+jobscheduler_sighup(SIGNAL_ARGS)
+{
+	int			save_errno = errno;
+
+	job_scheduler_reload_config = true;
+	SetLatch(MyLatch);
+
+	errno = save_errno;
+}
+
+void
+jobscheduler_main(Datum arg)
+{
+	MemoryContext jobcontext;
+
+	/* Establish signal handlers before unblocking signals */
+	pqsignal(SIGHUP, jobscheduler_sighup);
+	pqsignal(SIGTERM, die);
+	BackgroundWorkerUnblockSignals();
+
+	/* Initialize connection to the database */
+	BackgroundWorkerInitializeConnection(job_scheduler_database_name, NULL, 0);
+
+	jobcontext = AllocSetContextCreate(TopMemoryContext,
+									   "pg_tle job scheduler",
+									   ALLOCSET_DEFAULT_SIZES);
+
+	/* Main worker loop */
+	while (true)
+	{
+		List	   *jobs;
+		ListCell   *lc;
+		int			rc;
+
+		CHECK_FOR_INTERRUPTS();
+
+		if (job_scheduler_reload_config)
+		{
+			job_scheduler_reload_config = false;
+			ProcessConfigFile(PGC_SIGHUP);
+		}
+
+		MemoryContextReset(jobcontext);
+		jobs = get_due_jobs(jobcontext);
+
+		foreach(lc, jobs)
+		{
+			CHECK_FOR_INTERRUPTS();
+			run_job((ScheduledJob *) lfirst(lc));
+		}
+
+		pgstat_report_activity(STATE_IDLE, NULL);
+
+		rc = WaitLatch(MyLatch,
+					   WL_LATCH_SET | WL_TIMEOUT | WL_POSTMASTER_DEATH,
+					   JOB_SCHEDULER_NAPTIME_MS,
+					   PG_WAIT_EXTENSION);
+		ResetLatch(MyLatch);
+
+		if (rc & WL_POSTMASTER_DEATH)
+			proc_exit(1);
+	}
+}
+
+/*
+ * Returns the jobs whose next run is due, allocated in jobcontext. Returns no
+ * jobs if pg_tle is not installed in the database, or if it is installed at
+ * a version without pgtle.scheduled_job.
+ */
+static List *
+get_due_jobs(MemoryContext jobcontext)
+{
+	List	   *jobs = NIL;
+	char	   *query;
+	Oid			namespaceOid;
+	uint64		i;
+
+	SetCurrentStatementStartTimestamp();
+	StartTransactionCommand();
+	PushActiveSnapshot(GetTransactionSnapshot());
+
+	namespaceOid = get_namespace_oid(PG_TLE_NSPNAME, true);
+	if (get_extension_oid(PG_TLE_EXTNAME, true) == InvalidOid ||
+		!OidIsValid(namespaceOid) ||
+		!OidIsValid(get_relname_relid(SCHEDULED_JOB_TABLE, namespaceOid)))
+	{
+		PopActiveSnapshot();
+		CommitTransactionCommand();
+		return NIL;
+	}
+
+	if (SPI_connect() != SPI_OK_CONNECT)
+		elog(ERROR, "SPI_connect failed");
+
+	/*
+	 * Remove the jobs of users that were dropped, so that they cannot run as
+	 * a new user that is assigned the same OID.
+	 */
+	query = psprintf("DELETE FROM %s.%s WHERE NOT EXISTS "
+					 "(SELECT 1 FROM pg_catalog.pg_authid a WHERE a.oid OPERATOR(pg_catalog.=) run_as) "
+					 "RETURNING name, run_as::pg_catalog.oid",
+					 quote_identifier(PG_TLE_NSPNAME), quote_identifier(SCHEDULED_JOB_TABLE));
+	pgstat_report_activity(STATE_RUNNING, query);
+
+	if (SPI_execute(query, false, 0) != SPI_OK_DELETE_RETURNING)
+		elog(ERROR, "failed to delete from \"%s.%s\"", PG_TLE_NSPNAME, SCHEDULED_JOB_TABLE);
+
+	for (i = 0; i < SPI_processed; i++)
+		ereport(LOG,
+				errmsg("removed scheduled job \"%s\" because role with OID %s no longer exists",
+					   SPI_getvalue(SPI_tuptable->vals[i], SPI_tuptable->tupdesc, 1),
+					   SPI_getvalue(SPI_tuptable->vals[i], SPI_tuptable->tupdesc, 2)));
+
+	query = psprintf("SELECT name, schema_name, proname, run_as FROM %s.%s "
+					 "WHERE next_run OPERATOR(pg_catalog.<=) pg_catalog.now() ORDER BY next_run",
+					 quote_identifier(PG_TLE_NSPNAME), quote_identifier(SCHEDULED_JOB_TABLE));
+	pgstat_report_activity(STATE_RUNNING, query);
+
+	if (SPI_execute(query, true, 0) != SPI_OK_SELECT)
+		elog(ERROR, "failed to query \"%s.%s\"", PG_TLE_NSPNAME, SCHEDULED_JOB_TABLE);
+
+	for (i = 0; i < SPI_processed; i++)
+	{
+		HeapTuple	tuple = SPI_tuptable->vals[i];
+		TupleDesc	tupdesc = SPI_tuptable->tupdesc;
+		MemoryContext oldcontext;
+		ScheduledJob *job;
+		bool		isnull;
+
+		oldcontext = MemoryContextSwitchTo(jobcontext);
+		job = palloc(sizeof(ScheduledJob));
+		job->name = SPI_getvalue(tuple, tupdesc, 1);
+		job->schema_name = SPI_getvalue(tuple, tupdesc, 2);
+		job->proname = SPI_getvalue(tuple, tupdesc, 3);
+		job->run_as = DatumGetObjectId(SPI_getbinval(tuple, tupdesc, 4, &isnull));
+		jobs = lappend(jobs, job);
+		MemoryContextSwitchTo(oldcontext);
+	}
+
+	SPI_finish();
+	PopActiveSnapshot();
+	CommitTransactionCommand();
+
+	return jobs;
+}
+
+/*
+ * Calls the job's function as the user who scheduled it and records the
+ * outcome. Errors raised by the function are recorded, not rethrown. The job
+ * fails if the user no longer exists or is no longer a member of pgtle_admin.
+ */
+static void
+run_job(ScheduledJob * job)
+{
+	MemoryContext old_context;
+	ResourceOwner old_owner;
+	TimestampTz start_time = GetCurrentTimestamp();
+	char	   *query;
+	char	   *error_msg = NULL;
+	Oid			roleid = job->run_as;
+	char	   *rolename;
+	Oid			tleadminoid;
+	Oid			save_userid;
+	int			save_sec_context;
+	Oid			argtypes[3] = {TEXTOID, TIMESTAMPTZOID, TEXTOID};
+	Datum		args[3];
+	char		nulls[3] = {' ', ' ', ' '};
+
+	SetCurrentStatementStartTimestamp();
+	StartTransactionCommand();
+	PushActiveSnapshot(GetTransactionSnapshot());
+
+	query = psprintf("SELECT %s.%s()",
+					 quote_identifier(job->schema_name), quote_identifier(job->proname));
+	pgstat_report_activity(STATE_RUNNING, query);
+
+	old_context = CurrentMemoryContext;
+	old_owner = CurrentResourceOwner;
+	GetUserIdAndSecContext(&save_userid, &save_sec_context);
+
+	/*
+	 * Wrap the job in a subtransaction so that its errors can be recorded in
+	 * the same transaction.
+	 */
+	BeginInternalSubTransaction(NULL);
+	PG_TRY();
+	{
+		rolename = GetUserNameFromId(roleid, true);
+		if (rolename == NULL)
+			ereport(ERROR,
+					(errcode(ERRCODE_UNDEFINED_OBJECT),
+					 errmsg("role with OID %u does not exist", roleid)));
+
+		tleadminoid = get_role_oid(PG_TLE_ADMIN, false);
+		if (!is_member_of_role(roleid, tleadminoid))
+			ereport(ERROR,
+					(errcode(ERRCODE_INSUFFICIENT_PRIVILEGE),
+					 errmsg("role \"%s\" is not a member of \"%s\"",
+							rolename, PG_TLE_ADMIN)));
+
+		/*
+		 * Like a security definer function, the job cannot change the role
+		 * back to the superuser the worker runs as.
+		 */
+		SetUserIdAndSecContext(roleid, save_sec_context | SECURITY_LOCAL_USERID_CHANGE);
+
+		if (SPI_connect() != SPI_OK_CONNECT)
+			elog(ERROR, "SPI_connect failed");
+		if (SPI_execute(query, false, 0) != SPI_OK_SELECT)
+			ereport(ERROR,
+					errmsg("unable to execute function \"%s\"", query));
+		SPI_finish();
+
+		SetUserIdAndSecContext(save_userid, save_sec_context);
+
+		ReleaseCurrentSubTransaction();
+		MemoryContextSwitchTo(old_context);
+		CurrentResourceOwner = old_owner;
+	}
+	PG_CATCH();
+	{
+		ErrorData  *edata;
+
+		MemoryContextSwitchTo(old_context);
+		edata = CopyErrorData();
+		FlushErrorState();
+
+		RollbackAndReleaseCurrentSubTransaction();
+		MemoryContextSwitchTo(old_context);
+		CurrentResourceOwner = old_owner;
+		SetUserIdAndSecContext(save_userid, save_sec_context);
+
+		error_msg = pstrdup(edata->message);
+		FreeErrorData(edata);
+
+		ereport(LOG,
+				errmsg("scheduled job \"%s\" failed: %s", job->name, error_msg));
+	}
+	PG_END_TRY();
+
+	/* Record the outcome and schedule the next run */
+	if (SPI_connect() != SPI_OK_CONNECT)
+		elog(ERROR, "SPI_connect failed");
+
+	args[0] = CStringGetTextDatum(job->name);
+	args[1] = TimestampTzGetDatum(start_time);
+	if (error_msg)
+		args[2] = CStringGetTextDatum(error_msg);
+	else
+		nulls[2] = 'n';
+
+	query = psprintf("UPDATE %s.%s SET last_run = $2, "
+					 "next_run = $2 OPERATOR(pg_catalog.+) schedule, "
+					 "last_status = CASE WHEN $3 IS NULL THEN 'succeeded' ELSE 'failed' END, "
+					 "last_error = $3 "
+					 "WHERE name OPERATOR(pg_catalog.=) $1",
+					 quote_identifier(PG_TLE_NSPNAME), quote_identifier(SCHEDULED_JOB_TABLE));
+	if (SPI_execute_with_args(query, 3, argtypes, args, nulls, false, 0) != SPI_OK_UPDATE)
+		elog(ERROR, "failed to update \"%s.%s\"", PG_TLE_NSPNAME, SCHEDULED_JOB_TABLE);
+
+	SPI_finish();
+	PopActiveSnapshot();
+	CommitTransactionCommand();
+}
+
+/*
+ * Registers a job in pgtle.scheduled_job. The job runs as the calling user,
+ * once every schedule, starting schedule from now.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_schedule_job);
+Datum
+pg_tle_schedule_job(PG_FUNCTION_ARGS)
+{
+	char	   *name = text_to_cstring(PG_GETARG_TEXT_PP(0));
+	Interval   *schedule = PG_GETARG_INTERVAL_P(1);
+	Oid			funcid = PG_GETARG_OID(2);
+	HeapTuple	tup;
+	Form_pg_proc procForm;
+	char	   *schemaName;
+	char	   *procName;
+	AclResult	aclresult;
+	Oid			tleadminoid;
+	Oid			save_userid;
+	int			save_sec_context;
+	char	   *query;
+	int			ret;
+	Oid			argtypes[5] = {TEXTOID, INTERVALOID, TEXTOID, TEXTOID, REGROLEOID};
+	Datum		args[5];
+
+	/*
+	 * Even though the SQL function is locked down so only a member of
+	 * pgtle_admin can run this function, let's check and make sure there is
+	 * not a way to bypass that
+	 */
+	tleadminoid = get_role_oid(PG_TLE_ADMIN, false);
+	CHECK_CAN_SET_ROLE(GetUserId(), tleadminoid);
+
+	if (schedule->time + schedule->day * USECS_PER_DAY +
+		schedule->month * (int64) DAYS_PER_MONTH * USECS_PER_DAY < USECS_PER_SEC)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
+				 errmsg("schedule must be at least 1 second")));
+
+	tup = SearchSysCache1(PROCOID, ObjectIdGetDatum(funcid));
+	if (!HeapTupleIsValid(tup))
+		elog(ERROR, "cache lookup failed for function %u", funcid);
+	procForm = (Form_pg_proc) GETSTRUCT(tup);
+
+	if (procForm->pronargs != 0)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_FUNCTION_DEFINITION),
+				 errmsg("job function %s must not take any arguments",
+						format_procedure(funcid))));
+
+	schemaName = get_namespace_name(procForm->pronamespace);
+	procName = pstrdup(NameStr(procForm->proname));
+	ReleaseSysCache(tup);
+
+	/* The job runs as the calling user, so it must be able to call the function */
+	aclresult = PG_PROC_ACLCHECK(funcid, GetUserId(), ACL_EXECUTE);
+	if (aclresult != ACLCHECK_OK)
+		aclcheck_error(aclresult, OBJECT_FUNCTION, procName);
+
+	/*
+	 * pgtle_admin cannot modify pgtle.scheduled_job directly, so that a job
+	 * cannot be set to run as another user.
+	 */
+	GetUserIdAndSecContext(&save_userid, &save_sec_context);
+	SetUserIdAndSecContext(BOOTSTRAP_SUPERUSERID,
+						   save_sec_context | SECURITY_LOCAL_USERID_CHANGE);
+
+	if (SPI_connect() != SPI_OK_CONNECT)
+		elog(ERROR, "SPI_connect failed");
+
+	args[0] = CStringGetTextDatum(name);
+	query = psprintf("SELECT 1 FROM %s.%s WHERE name OPERATOR(pg_catalog.=) $1",
+					 quote_identifier(PG_TLE_NSPNAME), quote_identifier(SCHEDULED_JOB_TABLE));
+	ret = SPI_execute_with_args(query, 1, argtypes, args, NULL, true, 0);
+	if (ret != SPI_OK_SELECT)
+		elog(ERROR, "failed to query \"%s.%s\"", PG_TLE_NSPNAME, SCHEDULED_JOB_TABLE);
+
+	if (SPI_processed > 0)
+		ereport(ERROR,
+				(errcode(ERRCODE_DUPLICATE_OBJECT),
+				 errmsg("job \"%s\" already exists", name)));
+
+	args[1] = IntervalPGetDatum(schedule);
+	args[2] = CStringGetTextDatum(schemaName);
+	args[3] = CStringGetTextDatum(procName);
+	args[4] = ObjectIdGetDatum(save_userid);
+	query = psprintf("INSERT INTO %s.%s (name, schedule, schema_name, proname, run_as, next_run) "
+					 "VALUES ($1, $2, $3, $4, $5, pg_catalog.now() OPERATOR(pg_catalog.+) $2)",
+					 quote_identifier(PG_TLE_NSPNAME), quote_identifier(SCHEDULED_JOB_TABLE));
+	ret = SPI_execute_with_args(query, 5, argtypes, args, NULL, false, 0);
+	if (ret != SPI_OK_INSERT)
+		elog(ERROR, "failed to insert into \"%s.%s\"", PG_TLE_NSPNAME, SCHEDULED_JOB_TABLE);
+
+	SPI_finish();
+
+	SetUserIdAndSecContext(save_userid, save_sec_context);
+
+	if (!enable_job_scheduler)
+		ereport(NOTICE,
+				errmsg("pgtle.enable_job_scheduler is set to off. To run scheduled jobs, set pgtle.enable_job_scheduler = on and restart the database."));
+	else if (strcmp(get_database_name(MyDatabaseId), job_scheduler_database_name) != 0)
+		ereport(NOTICE,
+				errmsg("pgtle.job_scheduler_db_name is currently \"%s\". To run this job, schedule it in that database.",
+					   job_scheduler_database_name));
+
+	PG_RETURN_VOID();
+}
+
+/*
+ * Removes a job from pgtle.scheduled_job. Only the user the job runs as, or a
+ * superuser, can remove it.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_unschedule_job);
+Datum
+pg_tle_unschedule_job(PG_FUNCTION_ARGS)
+{
+	char	   *name = text_to_cstring(PG_GETARG_TEXT_PP(0));
+	Oid			tleadminoid;
+	Oid			save_userid;
+	int			save_sec_context;
+	char	   *query;
+	Oid			runAs;
+	bool		isnull;
+	int			ret;
+	Oid			argtypes[1] = {TEXTOID};
+	Datum		args[1];
+
+	tleadminoid = get_role_oid(PG_TLE_ADMIN, false);
+	CHECK_CAN_SET_ROLE(GetUserId(), tleadminoid);
+
+	GetUserIdAndSecContext(&save_userid, &save_sec_context);
+	SetUserIdAndSecContext(BOOTSTRAP_SUPERUSERID,
+						   save_sec_context | SECURITY_LOCAL_USERID_CHANGE);
+
+	if (SPI_connect() != SPI_OK_CONNECT)
+		elog(ERROR, "SPI_connect failed");
+
+	args[0] = CStringGetTextDatum(name);
+	query = psprintf("SELECT run_as FROM %s.%s WHERE name OPERATOR(pg_catalog.=) $1 FOR UPDATE",
+					 quote_identifier(PG_TLE_NSPNAME), quote_identifier(SCHEDULED_JOB_TABLE));
+	ret = SPI_execute_with_args(query, 1, argtypes, args, NULL, false, 0);
+	if (ret != SPI_OK_SELECT)
+		elog(ERROR, "failed to query \"%s.%s\"", PG_TLE_NSPNAME, SCHEDULED_JOB_TABLE);
+
+	if (SPI_processed == 0)
+		ereport(ERROR,
+				(errcode(ERRCODE_UNDEFINED_OBJECT),
+				 errmsg("job \"%s\" does not exist", name)));
+
+	runAs = DatumGetObjectId(SPI_getbinval(SPI_tuptable->vals[0],
+										   SPI_tuptable->tupdesc, 1, &isnull));
+	if (!superuser_arg(save_userid) && runAs != save_userid)
+		ereport(ERROR,
+				(errcode(ERRCODE_INSUFFICIENT_PRIVILEGE),
+				 errmsg("must be owner of job \"%s\"", name)));
+
+	query = psprintf("DELETE FROM %s.%s WHERE name OPERATOR(pg_catalog.=) $1",
+					 quote_identifier(PG_TLE_NSPNAME), quote_identifier(SCHEDULED_JOB_TABLE));
+	ret = SPI_execute_with_args(query, 1, argtypes, args, NULL, false, 0);
+	if (ret != SPI_OK_DELETE)
+		elog(ERROR, "failed to delete from \"%s.%s\"", PG_TLE_NSPNAME, SCHEDULED_JOB_TABLE);
+
+	SPI_finish();
+
+	SetUserIdAndSecContext(save_userid, save_sec_context);
+
+	PG_RETURN_VOID();
+}
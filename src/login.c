@@ -0,0 +1,374 @@
+/*
+ * Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License").
+ * You may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/*
+ * login feature. Allows users to attach trusted language functions to the
+ * start of a session.
+ *
+ * PostgreSQL does not provide a hook that runs once a session is connected
+ * to its database, so the registered functions are called in the session's
+ * own backend right before its first statement is executed. Because the
+ * functions run in the session, they can end it by raising an error, which
+ * terminates the session, or change session settings with set_config.
+ *
+ * This is not a way to reject sessions: a session that never sends a
+ * statement, or that only calls functions through the fast-path interface,
+ * does not go through parse analysis and is never checked. clientauth is
+ * the feature to use for rejecting connections.
+ *
+ * The functions run in the transaction of the first statement. If that
+ * transaction (or the subtransaction the functions ran in) is rolled back,
+ * so are the settings they changed, and the functions are called again
+ * before the next statement.
+ *
+ * Background workers, WAL senders and single-user mode are never checked.
+ * Sessions of the users and databases in pgtle.login_users_to_skip and
+ * pgtle.login_databases_to_skip are not checked either.
+ */
+#include "postgres.h"
+#include "access/xact.h"
+#include "catalog/pg_type.h"
+#include "commands/dbcommands.h"
+#include "commands/extension.h"
+#include "executor/spi.h"
+#include "miscadmin.h"
+#include "nodes/pg_list.h"
+#include "parser/analyze.h"
+#include "replication/walsender.h"
+#include "utils/builtins.h"
+#include "utils/elog.h"
+#include "utils/guc.h"
+#include "utils/memutils.h"
+#include "utils/snapmgr.h"
+
+#include "compatibility.h"
+#include "constants.h"
+#include "feature.h"
+#include "login.h"
+#include "tleextension.h"
+
+static const char *login_feature = "login";
+
+void		login_init(void);
+
+static post_parse_analyze_hook_type prev_post_parse_analyze_hook = NULL;
+
+_POST_PARSE_ANALYZE_HOOK(login_post_parse_analyze);
+
+static void login_xact_callback(XactEvent event, void *arg);
+static void login_subxact_callback(SubXactEvent event, SubTransactionId mySubid,
+								   SubTransactionId parentSubid, void *arg);
+static void login_run_user_functions(void);
+
+/* GUC that determines whether login is enabled */
+static int	enable_login_feature = FEATURE_OFF;
+
+/* GUC that determines users that login feature skips */
+static char *login_users_to_skip = "";
+
+/* GUC that determines databases that login feature skips */
+static char *login_databases_to_skip = "";
+
+/* Whether the login functions have been called in this session */
+static bool login_done = false;
+
+/* Whether the login functions are being called, to skip their own statements */
+static bool login_in_progress = false;
+
+/*
+ * Subtransaction the login functions were called in, until the top-level
+ * transaction commits
+ */
+static SubTransactionId login_subxid = InvalidSubTransactionId;
+
+void
+login_init(void)
+{
+	DefineCustomEnumVariable(
+							 "pgtle.enable_login",
+							 gettext_noop("Sets the behavior for interacting with the pg_tle login feature."),
+							 NULL,
+							 &enable_login_feature,
+							 FEATURE_OFF,
+							 feature_mode_options,
+							 PGC_SIGHUP,
+							 GUC_SUPERUSER_ONLY,
+							 NULL, NULL, NULL);
+
+	DefineCustomStringVariable(
+							   "pgtle.login_users_to_skip",
+							   gettext_noop("Comma-delimited list of users that pg_tle login hook skips."),
+							   NULL,
+							   &login_users_to_skip,
+							   "",
+							   PGC_SIGHUP,
+							   GUC_LIST_INPUT,
+							   NULL, NULL, NULL);
+
+	DefineCustomStringVariable(
+							   "pgtle.login_databases_to_skip",
+							   gettext_noop("Comma-delimited list of databases that pg_tle login hook skips."),
+							   NULL,
+							   &login_databases_to_skip,
+							   "",
+							   PGC_SIGHUP,
+							   GUC_LIST_INPUT,
+							   NULL, NULL, NULL);
+
+	/* Do not register hooks if we are in pg_upgrade */
+	if (IsBinaryUpgrade)
+		return;
+
+	/*
+	 * The hook is always installed so that the feature can be enabled
+	 * without a restart.
+	 */
+	prev_post_parse_analyze_hook = post_parse_analyze_hook;
+	post_parse_analyze_hook = login_post_parse_analyze;
+
+	RegisterXactCallback(login_xact_callback, NULL);
+	RegisterSubXactCallback(login_subxact_callback, NULL);
+}
+
+/*
+ * login_post_parse_analyze
+ *
+ * Calls the login functions before the first statement of the session is
+ * executed. Any error raised by the functions terminates the session.
+ */
+_POST_PARSE_ANALYZE_HOOK(login_post_parse_analyze)
+{
+	MemoryContext old_context = CurrentMemoryContext;
+	char	   *user_name;
+	char	   *database_name;
+	bool		pushed_snapshot = false;
+
+	if (prev_post_parse_analyze_hook)
+		_prev_post_parse_analyze_hook;
+
+	if (login_done || login_in_progress)
+		return;
+
+	/*
+	 * Catalog lookups must be done in a transaction state, e.g. not while
+	 * parsing ROLLBACK of an aborted transaction.
+	 */
+	if (!IsTransactionState())
+		return;
+
+	/*
+	 * Calling the functions takes a snapshot, after which the isolation
+	 * level of the transaction can no longer be set. Wait for the next
+	 * statement.
+	 */
+	if (query->commandType == CMD_UTILITY &&
+		(IsA(query->utilityStmt, TransactionStmt) ||
+		 (IsA(query->utilityStmt, VariableSetStmt) &&
+		  ((VariableSetStmt *) query->utilityStmt)->kind == VAR_SET_MULTI)))
+		return;
+
+	/* Only check client sessions */
+	if (!IsUnderPostmaster || IsBackgroundWorker || am_walsender)
+	{
+		login_done = true;
+		return;
+	}
+
+	/*
+	 * The feature can be enabled later on with a SIGHUP, but sessions that
+	 * started while it was disabled are not checked.
+	 */
+	if (enable_login_feature == FEATURE_OFF)
+	{
+		login_done = true;
+		return;
+	}
+
+	user_name = GetUserNameFromId(GetSessionUserId(), false);
+	database_name = get_database_name(MyDatabaseId);
+
+	/* Skip if this user is on the skip list */
+	if (check_string_in_guc_list(user_name, login_users_to_skip, "pgtle.login_users_to_skip"))
+	{
+		login_done = true;
+		return;
+	}
+	/* Skip if this database is on the skip list */
+	if (check_string_in_guc_list(database_name, login_databases_to_skip, "pgtle.login_databases_to_skip"))
+	{
+		login_done = true;
+		return;
+	}
+
+	/* Utility statements are parsed without a snapshot */
+	if (!ActiveSnapshotSet())
+	{
+		PushActiveSnapshot(GetTransactionSnapshot());
+		pushed_snapshot = true;
+	}
+
+	login_in_progress = true;
+	PG_TRY();
+	{
+		login_run_user_functions();
+	}
+	PG_CATCH();
+	{
+		ErrorData  *edata;
+
+		/*
+		 * Reject the session, keeping the error reported by the function or
+		 * by the feature itself.
+		 */
+		MemoryContextSwitchTo(old_context);
+		edata = CopyErrorData();
+		FlushErrorState();
+
+		edata->elevel = FATAL;
+		ThrowErrorData(edata);
+	}
+	PG_END_TRY();
+	login_in_progress = false;
+
+	if (pushed_snapshot)
+		PopActiveSnapshot();
+
+	login_done = true;
+	login_subxid = GetCurrentSubTransactionId();
+}
+
+/*
+ * Run the user's functions. Raises an error if the session should be
+ * rejected.
+ */
+static void
+login_run_user_functions(void)
+{
+	List	   *proc_names;
+	ListCell   *item;
+	char	   *user_name = GetUserNameFromId(GetSessionUserId(), false);
+	char	   *database_name = get_database_name(MyDatabaseId);
+
+	/* Check if pg_tle extension is installed */
+	if (get_extension_oid(PG_TLE_EXTNAME, true) == InvalidOid)
+	{
+		if (enable_login_feature == FEATURE_REQUIRE)
+			ereport(ERROR,
+					errmsg("\"%s.enable_login\" feature is set to require but extension \"%s\" is not installed in the database \"%s\"",
+						   PG_TLE_NSPNAME, PG_TLE_EXTNAME, database_name));
+		return;
+	}
+
+	/* Check if any functions are registered to login */
+	proc_names = feature_proc(login_feature);
+	if (list_length(proc_names) <= 0)
+	{
+		if (enable_login_feature == FEATURE_REQUIRE)
+			ereport(ERROR,
+					errmsg("\"%s.enable_login\" feature is set to require, however no entries exist in \"%s.feature_info\" with the feature \"%s\" in the database \"%s\"",
+						   PG_TLE_NSPNAME, PG_TLE_NSPNAME, login_feature, database_name));
+		return;
+	}
+
+	if (SPI_connect() != SPI_OK_CONNECT)
+		ereport(ERROR,
+				(errcode(ERRCODE_CONNECTION_EXCEPTION),
+				 errmsg("\"%s.%s\" feature was not able to connect to the database \"%s\"",
+						PG_TLE_NSPNAME, login_feature, database_name)));
+
+	foreach(item, proc_names)
+	{
+		char	   *query;
+		char	   *func_name = lfirst(item);
+		Oid			hookargtypes[SPI_NARGS_3] = {TEXTOID, TEXTOID, TEXTOID};
+		Datum		hookargs[SPI_NARGS_3];
+		char		hooknulls[SPI_NARGS_3];
+
+		memset(hooknulls, ' ', sizeof(hooknulls));
+
+		/*
+		 * func_name is already using quote_identifier from when it was
+		 * assembled
+		 */
+		query = psprintf("SELECT %s($1::pg_catalog.text, $2::pg_catalog.text, $3::pg_catalog.text)",
+						 func_name);
+
+		hookargs[0] = CStringGetTextDatum(user_name);
+		hookargs[1] = CStringGetTextDatum(database_name);
+		if (application_name)
+			hookargs[2] = CStringGetTextDatum(application_name);
+		else
+			hooknulls[2] = 'n';
+
+		/*
+		 * Not read-only, so that the function can record the session, e.g.
+		 * in an audit table.
+		 */
+		if (SPI_execute_with_args(query, SPI_NARGS_3, hookargtypes, hookargs, hooknulls, false, 0) != SPI_OK_SELECT)
+			ereport(ERROR,
+					errmsg("unable to execute function \"%s\"", func_name));
+	}
+
+	SPI_finish();
+}
+
+/*
+ * If the transaction that the login functions ran in is rolled back, so are
+ * their effects. Call them again before the next statement.
+ */
+static void
+login_xact_callback(XactEvent event, void *arg)
+{
+	if (login_subxid == InvalidSubTransactionId)
+		return;
+
+	switch (event)
+	{
+		case XACT_EVENT_COMMIT:
+		case XACT_EVENT_PARALLEL_COMMIT:
+		case XACT_EVENT_PREPARE:
+			login_subxid = InvalidSubTransactionId;
+			break;
+		case XACT_EVENT_ABORT:
+		case XACT_EVENT_PARALLEL_ABORT:
+			login_subxid = InvalidSubTransactionId;
+			login_done = false;
+			break;
+		default:
+			break;
+	}
+}
+
+static void
+login_subxact_callback(SubXactEvent event, SubTransactionId mySubid,
+					   SubTransactionId parentSubid, void *arg)
+{
+	if (login_subxid == InvalidSubTransactionId || mySubid != login_subxid)
+		return;
+
+	switch (event)
+	{
+		case SUBXACT_EVENT_COMMIT_SUB:
+			login_subxid = parentSubid;
+			break;
+		case SUBXACT_EVENT_ABORT_SUB:
+			login_subxid = InvalidSubTransactionId;
+			login_done = false;
+			break;
+		default:
+			break;
+	}
+}
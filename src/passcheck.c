@@ -14,19 +14,27 @@
  * limitations under the License.
  */
 #include "postgres.h"
+#include "access/htup_details.h"
 #include "access/xact.h"
+#include "catalog/pg_auth_members.h"
+#include "catalog/pg_authid.h"
 #include "catalog/pg_type.h"
 #include "commands/dbcommands.h"
+#include "commands/defrem.h"
 #include "commands/extension.h"
 #include "commands/user.h"
 #include "executor/spi.h"
 #include "nodes/pg_list.h"
+#include "tcop/utility.h"
+#include "utils/acl.h"
+#include "utils/array.h"
 #include "utils/builtins.h"
 #include "utils/elog.h"
 #include "utils/errcodes.h"
 #include "utils/guc.h"
 #include "utils/palloc.h"
 #include "utils/snapmgr.h"
+#include "utils/syscache.h"
 #include "utils/timestamp.h"
 #include "utils/fmgrprotos.h"
 
@@ -55,6 +63,7 @@
 
 #define PASSCHECK_DATA_MAX_STRLEN 256
 #define PASSCHECK_ERROR_MSG_MAX_STRLEN 4096
+#define PASSCHECK_MAX_MEMBER_OF 128
 
 void		passcheck_init(void);
 
@@ -62,6 +71,10 @@ static check_password_hook_type next_check_password_hook = NULL;
 static void passcheck_check_password_hook(const char *username, const char *shadow_pass, PasswordType password_type, Datum validuntil_time, bool validuntil_null);
 PGDLLEXPORT void passcheck_worker_main(Datum arg);
 
+static ProcessUtility_hook_type prev_process_utility_hook = NULL;
+
+_PROCESS_UTILITY_HOOK(passcheck_process_utility);
+
 static shmem_startup_hook_type prev_shmem_startup_hook = NULL;
 static void passcheck_shmem_startup(void);
 
@@ -79,6 +92,13 @@ static const char *extension_name = PG_TLE_EXTNAME;
 static const char *password_check_feature = "passcheck";
 static const char *passcheck_shmem_name = "pgtle_passcheck";
 
+/*
+ * The CREATE ROLE or ALTER ROLE statement being executed, if any. The
+ * password check hook is not passed the role attributes that the statement
+ * sets, so they are read from the statement.
+ */
+static Node *passcheck_role_stmt = NULL;
+
 
 /*  This should match crypt.h */
 char	   *pass_types[3] = {"PASSWORD_TYPE_PLAINTEXT", "PASSWORD_TYPE_MD5", "PASSWORD_TYPE_SCRAM_SHA_256"};
@@ -91,6 +111,10 @@ typedef struct PasswordCheckHookData
 	PasswordType password_type;
 	TimestampTz validuntil_time;
 	bool		validuntil_null;
+	bool		superuser;
+	bool		login;
+	int			num_member_of;
+	NameData	member_of[PASSCHECK_MAX_MEMBER_OF];
 }			PasswordCheckHookData;
 
 /* Shared state used to communicate between client process and background worker. */
@@ -115,6 +139,8 @@ typedef struct PasscheckBgwShmemSharedState
 static PasscheckBgwShmemSharedState * passcheck_ss = NULL;
 
 static void passcheck_run_user_functions(PasswordCheckHookData * passcheck_data);
+static void passcheck_get_role_attributes(const char *username, PasswordCheckHookData * passcheck_data);
+static bool passcheck_function_takes_role_attributes(const char *func_name);
 
 void
 passcheck_init(void)
@@ -124,6 +150,9 @@ passcheck_init(void)
 	next_check_password_hook = check_password_hook;
 	check_password_hook = passcheck_check_password_hook;
 
+	prev_process_utility_hook = ProcessUtility_hook;
+	ProcessUtility_hook = passcheck_process_utility;
+
 #if (PG_VERSION_NUM < 150000)
 	RequestNamedLWLockTranche(passcheck_shmem_name, 1);
 	RequestAddinShmemSpace(passcheck_shared_memsize());
@@ -170,6 +199,7 @@ passcheck_check_password_hook(const char *username, const char *shadow_pass, Pas
 	char		error_hint[PASSCHECK_ERROR_MSG_MAX_STRLEN];
 
 	Oid			database_oid;
+	PasswordCheckHookData role_data;
 
 	/* Call the next hook if it exists */
 	if (next_check_password_hook)
@@ -192,6 +222,7 @@ passcheck_check_password_hook(const char *username, const char *shadow_pass, Pas
 		data.password_type = password_type;
 		data.validuntil_time = DatumGetTimestampTz(validuntil_time);
 		data.validuntil_null = validuntil_null;
+		passcheck_get_role_attributes(username, &data);
 
 		PG_TRY();
 		{
@@ -245,6 +276,12 @@ passcheck_check_password_hook(const char *username, const char *shadow_pass, Pas
 	 * once
 	 */
 
+	/*
+	 * Look up the role attributes before taking the lock, since this can
+	 * raise an error.
+	 */
+	passcheck_get_role_attributes(username, &role_data);
+
 	/* 1. Sleep on available_cv until available_entry is true. */
 	ConditionVariablePrepareToSleep(&passcheck_ss->available_cv);
 	while (true)
@@ -315,6 +352,11 @@ passcheck_check_password_hook(const char *username, const char *shadow_pass, Pas
 	passcheck_ss->data.password_type = password_type;
 	passcheck_ss->data.validuntil_time = DatumGetTimestampTz(validuntil_time);
 	passcheck_ss->data.validuntil_null = validuntil_null;
+	passcheck_ss->data.superuser = role_data.superuser;
+	passcheck_ss->data.login = role_data.login;
+	passcheck_ss->data.num_member_of = role_data.num_member_of;
+	memcpy(passcheck_ss->data.member_of, role_data.member_of,
+		   sizeof(NameData) * role_data.num_member_of);
 
 	passcheck_ss->available_entry = false;
 	passcheck_ss->done_processing = false;
@@ -537,19 +579,15 @@ passcheck_run_user_functions(PasswordCheckHookData * passcheck_hook_data)
 	{
 		char	   *query;
 		char	   *func_name = lfirst(item);
-		Oid			hookargtypes[SPI_NARGS_5] = {TEXTOID, TEXTOID, TEXTOID, TIMESTAMPTZOID, BOOLOID};
-		Datum		hookargs[SPI_NARGS_5];
-		char		hooknulls[SPI_NARGS_5];
+		Oid			hookargtypes[SPI_NARGS_8] = {TEXTOID, TEXTOID, TEXTOID, TIMESTAMPTZOID, BOOLOID, BOOLOID, BOOLOID, TEXTARRAYOID};
+		Datum		hookargs[SPI_NARGS_8];
+		char		hooknulls[SPI_NARGS_8];
+		int			nargs;
+		Datum	   *member_of;
+		int			i;
 
 		memset(hooknulls, ' ', sizeof(hooknulls));
 
-		/*
-		 * func_name is already using quote_identifier from when it was
-		 * assembled
-		 */
-		query = psprintf("SELECT %s($1::pg_catalog.text, $2::pg_catalog.text, $3::%s.password_types, $4::pg_catalog.timestamptz, $5::pg_catalog.bool)",
-						 func_name, quote_identifier(PG_TLE_NSPNAME));
-
 		hookargs[0] = CStringGetTextDatum(passcheck_hook_data->username);
 		hookargs[1] = CStringGetTextDatum(passcheck_hook_data->shadow_pass);
 		hookargs[2] = CStringGetTextDatum(pass_types[passcheck_hook_data->password_type]);
@@ -565,7 +603,43 @@ passcheck_run_user_functions(PasswordCheckHookData * passcheck_hook_data)
 			hookargs[4] = BoolGetDatum(false);
 		}
 
-		if (SPI_execute_with_args(query, 5, hookargtypes, hookargs, hooknulls, true, 0) != SPI_OK_SELECT)
+		/*
+		 * Functions written before the role attributes were passed take only
+		 * the first five arguments, so only pass the role attributes to
+		 * functions that accept them.
+		 */
+		if (passcheck_function_takes_role_attributes(func_name))
+		{
+			hookargs[5] = BoolGetDatum(passcheck_hook_data->superuser);
+			hookargs[6] = BoolGetDatum(passcheck_hook_data->login);
+
+			member_of = palloc(sizeof(Datum) * Max(passcheck_hook_data->num_member_of, 1));
+			for (i = 0; i < passcheck_hook_data->num_member_of; i++)
+				member_of[i] = CStringGetTextDatum(NameStr(passcheck_hook_data->member_of[i]));
+			hookargs[7] = PointerGetDatum(construct_array(member_of, passcheck_hook_data->num_member_of,
+														  TEXTOID, -1, false, 'i'));
+			nargs = SPI_NARGS_8;
+
+			/*
+			 * func_name is already using quote_identifier from when it was
+			 * assembled
+			 */
+			query = psprintf("SELECT %s($1::pg_catalog.text, $2::pg_catalog.text, $3::%s.password_types, $4::pg_catalog.timestamptz, $5::pg_catalog.bool, $6::pg_catalog.bool, $7::pg_catalog.bool, $8::pg_catalog.text[])",
+							 func_name, quote_identifier(PG_TLE_NSPNAME));
+		}
+		else
+		{
+			nargs = SPI_NARGS_5;
+
+			/*
+			 * func_name is already using quote_identifier from when it was
+			 * assembled
+			 */
+			query = psprintf("SELECT %s($1::pg_catalog.text, $2::pg_catalog.text, $3::%s.password_types, $4::pg_catalog.timestamptz, $5::pg_catalog.bool)",
+							 func_name, quote_identifier(PG_TLE_NSPNAME));
+		}
+
+		if (SPI_execute_with_args(query, nargs, hookargtypes, hookargs, hooknulls, true, 0) != SPI_OK_SELECT)
 			ereport(ERROR,
 					errmsg("unable to execute function \"%s\"", func_name));
 	}
@@ -574,6 +648,172 @@ passcheck_run_user_functions(PasswordCheckHookData * passcheck_hook_data)
 	SPI_finish();
 }
 
+/*
+ * Returns whether the function accepts the role attributes in addition to the
+ * original five passcheck arguments. Must be called while connected to SPI.
+ */
+static bool
+passcheck_function_takes_role_attributes(const char *func_name)
+{
+	char	   *signature;
+	Oid			argtypes[SPI_NARGS_1] = {TEXTOID};
+	Datum		args[SPI_NARGS_1];
+	bool		isnull;
+
+	signature = psprintf("%s(pg_catalog.text, pg_catalog.text, %s.password_types, pg_catalog.timestamptz, pg_catalog.bool, pg_catalog.bool, pg_catalog.bool, pg_catalog.text[])",
+						 func_name, quote_identifier(PG_TLE_NSPNAME));
+	args[0] = CStringGetTextDatum(signature);
+
+	if (SPI_execute_with_args("SELECT pg_catalog.to_regprocedure($1)", 1, argtypes, args, NULL, true, 1) != SPI_OK_SELECT)
+		ereport(ERROR,
+				errmsg("unable to look up function \"%s\"", func_name));
+
+	SPI_getbinval(SPI_tuptable->vals[0], SPI_tuptable->tupdesc, 1, &isnull);
+
+	return !isnull;
+}
+
+/*
+ * Fills in the attributes that the role will have once the statement setting
+ * its password completes: whether it is a superuser, whether it can log in and
+ * the roles it is a member of, directly or indirectly.
+ */
+static void
+passcheck_get_role_attributes(const char *username, PasswordCheckHookData * passcheck_data)
+{
+	Oid			roleid = get_role_oid(username, true);
+	List	   *options = NIL;
+	List	   *roles = NIL;
+	ListCell   *lc;
+
+	passcheck_data->superuser = false;
+	passcheck_data->login = false;
+	passcheck_data->num_member_of = 0;
+
+	/* Start from the current attributes of an existing role */
+	if (OidIsValid(roleid))
+	{
+		HeapTuple	tuple = SearchSysCache1(AUTHOID, ObjectIdGetDatum(roleid));
+
+		if (!HeapTupleIsValid(tuple))
+			elog(ERROR, "cache lookup failed for role %u", roleid);
+
+		passcheck_data->superuser = ((Form_pg_authid) GETSTRUCT(tuple))->rolsuper;
+		passcheck_data->login = ((Form_pg_authid) GETSTRUCT(tuple))->rolcanlogin;
+		ReleaseSysCache(tuple);
+	}
+
+	/* Apply the attributes set by the statement */
+	if (passcheck_role_stmt && IsA(passcheck_role_stmt, CreateRoleStmt))
+	{
+		CreateRoleStmt *stmt = (CreateRoleStmt *) passcheck_role_stmt;
+
+		if (strcmp(stmt->role, username) == 0)
+		{
+			/* CREATE USER implies LOGIN */
+			passcheck_data->login = stmt->stmt_type == ROLESTMT_USER;
+			options = stmt->options;
+		}
+	}
+	else if (passcheck_role_stmt && IsA(passcheck_role_stmt, AlterRoleStmt))
+	{
+		AlterRoleStmt *stmt = (AlterRoleStmt *) passcheck_role_stmt;
+
+		if (OidIsValid(roleid) && get_rolespec_oid(stmt->role, true) == roleid)
+			options = stmt->options;
+	}
+
+	foreach(lc, options)
+	{
+		DefElem    *defel = (DefElem *) lfirst(lc);
+
+		if (strcmp(defel->defname, "superuser") == 0)
+			passcheck_data->superuser = defGetBoolean(defel);
+		else if (strcmp(defel->defname, "canlogin") == 0)
+			passcheck_data->login = defGetBoolean(defel);
+		else if (strcmp(defel->defname, "addroleto") == 0)
+		{
+			/* CREATE ROLE ... IN ROLE */
+			ListCell   *rlc;
+
+			foreach(rlc, (List *) defel->arg)
+			{
+				Oid			memberof = get_rolespec_oid((RoleSpec *) lfirst(rlc), true);
+
+				if (OidIsValid(memberof))
+					roles = list_append_unique_oid(roles, memberof);
+			}
+		}
+	}
+
+	/*
+	 * Collect the roles that the role is a member of. roles grows while it is
+	 * scanned, so that indirect memberships are found too.
+	 */
+	if (OidIsValid(roleid))
+		roles = lcons_oid(roleid, roles);
+
+	foreach(lc, roles)
+	{
+		Oid			memberid = lfirst_oid(lc);
+		CatCList   *memlist;
+		int			i;
+
+		memlist = SearchSysCacheList1(AUTHMEMMEMROLE, ObjectIdGetDatum(memberid));
+		for (i = 0; i < memlist->n_members; i++)
+		{
+			HeapTuple	tup = &memlist->members[i]->tuple;
+
+			roles = list_append_unique_oid(roles, ((Form_pg_auth_members) GETSTRUCT(tup))->roleid);
+		}
+		ReleaseSysCacheList(memlist);
+	}
+
+	foreach(lc, roles)
+	{
+		Oid			memberof = lfirst_oid(lc);
+
+		if (memberof == roleid)
+			continue;
+
+		if (passcheck_data->num_member_of >= PASSCHECK_MAX_MEMBER_OF)
+			ereport(ERROR,
+					errhidestmt(true),
+					errmsg("role \"%s\" is a member of more than %d roles, which is not supported by the \"%s\" feature",
+						   username, PASSCHECK_MAX_MEMBER_OF, password_check_feature));
+
+		namestrcpy(&passcheck_data->member_of[passcheck_data->num_member_of++],
+				   GetUserNameFromId(memberof, false));
+	}
+}
+
+/*
+ * passcheck_process_utility
+ *
+ * Remembers the CREATE ROLE or ALTER ROLE statement being executed, so that
+ * the password check hook can pass the role attributes it sets.
+ */
+_PROCESS_UTILITY_HOOK(passcheck_process_utility)
+{
+	Node	   *prev_role_stmt = passcheck_role_stmt;
+
+	if (IsA(pu_parsetree, CreateRoleStmt) || IsA(pu_parsetree, AlterRoleStmt))
+		passcheck_role_stmt = pu_parsetree;
+
+	PG_TRY();
+	{
+		if (prev_process_utility_hook)
+			_prev_process_utility_hook(prev_process_utility_hook);
+		else
+			_standard_ProcessUtility;
+	}
+	PG_FINALLY();
+	{
+		passcheck_role_stmt = prev_role_stmt;
+	}
+	PG_END_TRY();
+}
+
 static void
 passcheck_shmem_startup(void)
 {
@@ -64,6 +64,8 @@
 #include "commands/defrem.h"
 #include "commands/extension.h"
 #include "commands/schemacmds.h"
+#include "commands/trigger.h"
+#include "common/pg_lzcompress.h"
 #include "executor/spi.h"
 #include "funcapi.h"
 #include "mb/pg_wchar.h"
@@ -85,6 +87,7 @@
 #include "utils/rel.h"
 #include "utils/snapmgr.h"
 #include "utils/syscache.h"
+#include "utils/timestamp.h"
 #include "utils/varlena.h"
 
 #include "constants.h"
@@ -171,6 +174,26 @@ bool		tleext = false;
 
 static ProcessUtility_hook_type prev_hook = NULL;
 
+/* limits on the TLEs installed in a database, -1 means no limit */
+static int	max_extensions = -1;
+static int	max_extension_versions = -1;
+static int	max_script_size = -1;
+static int	max_extension_size = -1;
+
+#define EXTENSION_INSTALL_TIME_TABLE "extension_install_time"
+#define EXTENSION_HISTORY_TABLE "extension_history"
+
+/*
+ * Number of columns returned by pgtle.available_extensions() and
+ * pgtle.available_extension_versions() before install times and control
+ * file parameters were added to them in 1.5.0. The library may be loaded
+ * into a database where the pg_tle extension has not been updated yet.
+ */
+#define AVAILABLE_EXTENSIONS_OLD_NATTS		3
+#define AVAILABLE_EXTENSIONS_NATTS			9
+#define AVAILABLE_EXT_VERSIONS_OLD_NATTS	8
+#define AVAILABLE_EXT_VERSIONS_NATTS		9
+
 /* Local functions */
 static void tleerrorConflictingDefElem(DefElem *defel, ParseState *pstate);
 static char *exec_scalar_text_sql_func(const char *funcname);
@@ -190,6 +213,7 @@ static Oid	get_required_extension(char *reqExtensionName,
 								   List *parents,
 								   bool is_create);
 static Oid	get_tlefunc_oid_if_exists(const char *funcname);
+static bool is_tle_artifact_name(const char *funcname);
 static void get_available_versions_for_extension(ExtensionControlFile *pcontrol,
 												 Tuplestorestate *tupstore,
 												 TupleDesc tupdesc);
@@ -205,10 +229,37 @@ static char *read_whole_file(const char *filename, int *length);
 static void pg_tle_xact_callback(XactEvent event, void *arg);
 static List *textarray_to_stringlist(ArrayType *textarray);
 static bool validate_tle_sql(char *sql);
+static char *compress_tle_script(const char *sql_str);
+static char *decompress_tle_script(char *script, const char *name);
+static char *build_script_function_body(const char *sql_str);
+static char *read_tle_script(const char *funcname);
 static void check_requires_list(List *requires);
+static List *add_pg_tle_to_requires(List *requires);
+static void check_can_add_trusted_extension_script(const char *extname);
+static void check_trusted_extension_scripts(const char *extname);
+static void check_trusted_extension_function_owner(const char *extname,
+												   const char *funcname);
+static void check_script_size(const char *sql_str);
+static void check_extension_quotas(const char *extname);
+static ExtensionControlFile *read_tle_control_for_update(const char *extname);
+static void write_tle_control(const char *extname, ExtensionControlFile *control);
 static bool is_pgtle_defined_c_func(Oid funcid, bool *is_operator_func);
 static bool is_pgtle_used_user_func(Oid funcid, bool *is_operator_func);
+static bool is_pgtle_used_typmod_func(Oid funcid, bool *is_operator_func);
 static void check_pgtle_used_func(Oid funcid);
+static void record_extension_install_time(const char *extname,
+										  const char *extvers,
+										  bool new_extension);
+static bool get_extension_install_time(const char *extname,
+									   const char *extvers,
+									   TimestampTz *installed_at);
+static void record_extension_history(const char *action,
+									 const char *extname,
+									 const char *extvers,
+									 const char *feature,
+									 const char *sql_str);
+static List *get_dropped_tle_artifacts(DropStmt *stmt);
+static void record_dropped_tle_artifacts(List *funcnames);
 
 #if PG_VERSION_NUM < 150001
 /* flag bits for InitMaterializedSRF() */
@@ -915,8 +966,14 @@ parse_extension_control_file(ExtensionControlFile *control,
 		control->module_pathname = NULL;
 		control->relocatable = false;
 		control->schema = NULL;
-		control->superuser = false;
-		control->trusted = false;
+
+		/*
+		 * A trusted TLE behaves like a community extension marked both
+		 * superuser and trusted: its scripts are run as the bootstrap
+		 * superuser on behalf of users with CREATE privilege on the current
+		 * database. Untrusted TLEs never need superuser.
+		 */
+		control->superuser = control->trusted;
 		control->encoding = -1; /* encoding is that of the server_side
 								 * encoding */
 
@@ -972,11 +1029,16 @@ build_extension_control_file_string(ExtensionControlFile *control)
 	appendStringInfo(ctlstr, "comment = %s\n",
 					 quote_literal_cstr(control->comment));
 
-	/* relocatable, superuser, and trusted values are forced to be false */
+	/*
+	 * relocatable is forced to be false, and superuser follows trusted (see
+	 * parse_extension_control_file)
+	 */
 	appendStringInfo(ctlstr,
 					 "relocatable = false\n"
-					 "superuser = false\n"
-					 "trusted = false\n");
+					 "superuser = %s\n"
+					 "trusted = %s\n",
+					 control->trusted ? "true" : "false",
+					 control->trusted ? "true" : "false");
 
 	/*
 	 * we do not need to set "encoding" because it is set to the server_side
@@ -1060,7 +1122,7 @@ read_extension_script_file(const ExtensionControlFile *control,
 		src_str = read_whole_file(filename, &len);
 	else						/* pg_tle extension */
 	{
-		src_str = exec_scalar_text_sql_func(filename);
+		src_str = read_tle_script(filename);
 		if (src_str)
 			len = strnlen(src_str, MaxAllocSize);
 		else
@@ -1265,10 +1327,11 @@ execute_extension_script(Oid extensionOid, ExtensionControlFile *control,
 	 * here so that the control flags are correctly associated with the right
 	 * script(s) if they happen to be set in secondary control files.
 	 *
-	 * NOTE: TLE extensions **do not** require superuser. We can consider just
-	 * if (false) -ing this block, just to keep it to compare to upstream.
+	 * NOTE: TLE extensions **do not** require superuser unless they were
+	 * installed as trusted by a superuser, in which case they follow the
+	 * same rules as trusted file-based extensions.
 	 */
-	if (!tleext && control->superuser && !superuser())
+	if (control->superuser && !superuser())
 	{
 		if (extension_is_trusted(control))
 			switch_to_superuser = true;
@@ -1292,6 +1355,20 @@ execute_extension_script(Oid extensionOid, ExtensionControlFile *control,
 
 	filename = get_extension_script_filename(control, from_version, version);
 
+	/*
+	 * The functions that make up a TLE can also be created directly in the
+	 * pgtle schema, so make sure that the ones that mark the extension as
+	 * trusted and provide the script were not created by a non-superuser.
+	 */
+	if (switch_to_superuser && tleext)
+	{
+		check_trusted_extension_function_owner(control->name,
+											   get_extension_control_filename(control->name));
+		check_trusted_extension_function_owner(control->name,
+											   get_extension_aux_control_filename(control, version));
+		check_trusted_extension_function_owner(control->name, filename);
+	}
+
 	/*
 	 * If installing a trusted extension on behalf of a non-superuser, become
 	 * the bootstrap superuser.  (This switch will be cleaned up automatically
@@ -2299,6 +2376,21 @@ get_tlefunc_oid_if_exists(const char *funcname)
 	return LookupFuncName(namelist, 0, argtypes, true /* missing_ok */ );
 }
 
+/*
+ * Returns true if funcname is the name of a TLE extension .control or .sql
+ * function.
+ */
+static bool
+is_tle_artifact_name(const char *funcname)
+{
+	size_t		len = strlen(funcname);
+
+	return (len > strlen(".control") &&
+			strcmp(funcname + len - strlen(".control"), ".control") == 0) ||
+		(len > strlen(".sql") &&
+		 strcmp(funcname + len - strlen(".sql"), ".sql") == 0);
+}
+
 /*
  * CREATE EXTENSION
  */
@@ -2519,8 +2611,9 @@ pg_tle_available_extensions(PG_FUNCTION_ARGS)
 		{
 			ExtensionControlFile *control;
 			char	   *extname;
-			Datum		values[3];
-			bool		nulls[3];
+			TimestampTz installed_at;
+			Datum		values[AVAILABLE_EXTENSIONS_NATTS];
+			bool		nulls[AVAILABLE_EXTENSIONS_NATTS];
 			char	   *fname = SPI_getvalue(SPI_tuptable->vals[i],
 											 SPI_tuptable->tupdesc, 1);
 
@@ -2554,6 +2647,40 @@ pg_tle_available_extensions(PG_FUNCTION_ARGS)
 			else
 				values[2] = CStringGetTextDatum(control->comment);
 
+			if (rsinfo->setDesc->natts > AVAILABLE_EXTENSIONS_OLD_NATTS)
+			{
+				/*
+				 * Report the parameters of the default version, which is the
+				 * version CREATE EXTENSION installs.
+				 */
+				if (control->default_version != NULL)
+					control = read_extension_aux_control_file(control,
+															  control->default_version);
+
+				/* superuser */
+				values[3] = BoolGetDatum(control->superuser);
+				/* trusted */
+				values[4] = BoolGetDatum(control->trusted);
+				/* relocatable */
+				values[5] = BoolGetDatum(control->relocatable);
+				/* schema */
+				if (control->schema == NULL)
+					nulls[6] = true;
+				else
+					values[6] = DirectFunctionCall1(namein,
+													CStringGetDatum(control->schema));
+				/* requires */
+				if (control->requires == NIL)
+					nulls[7] = true;
+				else
+					values[7] = convert_requires_to_datum(control->requires);
+				/* installed_at */
+				if (get_extension_install_time(extname, NULL, &installed_at))
+					values[8] = TimestampTzGetDatum(installed_at);
+				else
+					nulls[8] = true;
+			}
+
 			tuplestore_putvalues(rsinfo->setResult, rsinfo->setDesc,
 								 values, nulls);
 		}
@@ -2675,8 +2802,9 @@ get_available_versions_for_extension(ExtensionControlFile *pcontrol,
 	{
 		ExtensionVersionInfo *evi = (ExtensionVersionInfo *) lfirst(lc);
 		ExtensionControlFile *control;
-		Datum		values[8];
-		bool		nulls[8];
+		TimestampTz installed_at;
+		Datum		values[AVAILABLE_EXT_VERSIONS_NATTS];
+		bool		nulls[AVAILABLE_EXT_VERSIONS_NATTS];
 		ListCell   *lc2;
 
 		if (!evi->installable)
@@ -2717,6 +2845,14 @@ get_available_versions_for_extension(ExtensionControlFile *pcontrol,
 			nulls[7] = true;
 		else
 			values[7] = CStringGetTextDatum(control->comment);
+		/* installed_at */
+		if (tupdesc->natts > AVAILABLE_EXT_VERSIONS_OLD_NATTS)
+		{
+			if (get_extension_install_time(control->name, evi->name, &installed_at))
+				values[8] = TimestampTzGetDatum(installed_at);
+			else
+				nulls[8] = true;
+		}
 
 		tuplestore_putvalues(tupstore, tupdesc, values, nulls);
 
@@ -2758,6 +2894,9 @@ get_available_versions_for_extension(ExtensionControlFile *pcontrol,
 					nulls[6] = false;
 				}
 				/* comment stays the same */
+				/* installed_at, only versions with an install script have one */
+				if (tupdesc->natts > AVAILABLE_EXT_VERSIONS_OLD_NATTS)
+					nulls[8] = true;
 
 				tuplestore_putvalues(tupstore, tupdesc, values, nulls);
 			}
@@ -3979,6 +4118,54 @@ pg_tle_init(void)
 		ereport(ERROR, (errcode(ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE),
 						errmsg("pg_tle must be loaded via shared_preload_libraries")));
 
+	DefineCustomIntVariable(
+							"pgtle.max_extensions",
+							gettext_noop("Maximum number of TLEs that can be installed in a database."),
+							gettext_noop("-1 means no limit."),
+							&max_extensions,
+							-1,
+							-1,
+							INT_MAX,
+							PGC_SUSET,
+							0,
+							NULL, NULL, NULL);
+
+	DefineCustomIntVariable(
+							"pgtle.max_extension_versions",
+							gettext_noop("Maximum number of versions that can be installed for a TLE."),
+							gettext_noop("-1 means no limit."),
+							&max_extension_versions,
+							-1,
+							-1,
+							INT_MAX,
+							PGC_SUSET,
+							0,
+							NULL, NULL, NULL);
+
+	DefineCustomIntVariable(
+							"pgtle.max_script_size",
+							gettext_noop("Maximum size of a TLE version or update path script."),
+							gettext_noop("-1 means no limit."),
+							&max_script_size,
+							-1,
+							-1,
+							INT_MAX,
+							PGC_SUSET,
+							GUC_UNIT_BYTE,
+							NULL, NULL, NULL);
+
+	DefineCustomIntVariable(
+							"pgtle.max_extension_size",
+							gettext_noop("Maximum total size of the version and update path scripts of a TLE."),
+							gettext_noop("-1 means no limit."),
+							&max_extension_size,
+							-1,
+							-1,
+							INT_MAX,
+							PGC_SUSET,
+							GUC_UNIT_BYTE,
+							NULL, NULL, NULL);
+
 	/* Install hook */
 	prev_hook = ProcessUtility_hook;
 	ProcessUtility_hook = PU_hook;
@@ -4002,6 +4189,7 @@ _PU_HOOK
 {
 	bool		cmd_done = false;
 	Oid			tleExtensionOid;
+	List	   *dropped_artifacts = NIL;
 
 	/*
 	 * Explicitly skip TransactionStmts before calling get_extension_oid().
@@ -4146,6 +4334,9 @@ _PU_HOOK
 				 * being replaced. We only care about functions that are being
 				 * created in the private pg_tle schema which are not under
 				 * the control of the pg_tle artifact manipulation functions.
+				 * Only superusers can create control and script functions
+				 * directly, e.g. when restoring a dump, so that the limits
+				 * the artifact functions enforce cannot be bypassed.
 				 */
 				if ((strncmp(nspname, PG_TLE_NSPNAME, sizeof(PG_TLE_NSPNAME)) == 0) &&
 					!tleart)
@@ -4166,7 +4357,9 @@ _PU_HOOK
 											errmsg("control file not found for the %s extension",
 												   PG_TLE_EXTNAME)));
 					}
-					else if (!IsBinaryUpgrade && OidIsValid(get_tlefunc_oid_if_exists(funcname)))
+					else if (!IsBinaryUpgrade &&
+							 (OidIsValid(get_tlefunc_oid_if_exists(funcname)) ||
+							  (!superuser() && is_tle_artifact_name(funcname))))
 					{
 						/*
 						 * This is not a pg_tle extension artifact, so it does
@@ -4339,10 +4532,31 @@ _PU_HOOK
 												 &relation,
 												 AccessExclusiveLock, false);
 					check_pgtle_used_func(address.objectId);
+
+					/*
+					 * As with CREATE FUNCTION, only superusers can make
+					 * control and script functions directly.
+					 */
+					if (!IsBinaryUpgrade && !tleart && !superuser() &&
+						is_tle_artifact_name(stmt->newname) &&
+						get_func_namespace(address.objectId) ==
+						get_namespace_oid(PG_TLE_NSPNAME, false))
+						ereport(ERROR, (errcode(ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE),
+										errmsg("%s schema reserved for pg_tle functions",
+											   PG_TLE_NSPNAME)));
 				}
 				break;
 			}
 
+		case T_DropStmt:		/* DROP FUNCTION */
+			{
+				DropStmt   *stmt = (DropStmt *) pu_parsetree;
+
+				if (stmt->removeType == OBJECT_FUNCTION)
+					dropped_artifacts = get_dropped_tle_artifacts(stmt);
+				break;
+			}
+
 		case T_AlterOwnerStmt:	/* ALTER FUNCTION xxx OWNER TO */
 			{
 				AlterOwnerStmt *stmt = (AlterOwnerStmt *) pu_parsetree;
@@ -4384,6 +4598,369 @@ _PU_HOOK
 			_standard_ProcessUtility;
 		}
 	}
+
+	/* record the TLEs uninstalled by dropping their functions */
+	if (dropped_artifacts != NIL)
+		record_dropped_tle_artifacts(dropped_artifacts);
+}
+
+/*
+ * Record when a version of a TLE was installed in pgtle.extension_install_time.
+ * If a new extension is being installed, forget about the versions of any
+ * extension of the same name that was uninstalled before.
+ */
+static void
+record_extension_install_time(const char *extname, const char *extvers,
+							  bool new_extension)
+{
+	Oid			save_userid;
+	int			save_sec_context;
+	int			spi_rc;
+	char	   *sql;
+	Oid			sqlargtypes[SPI_NARGS_2] = {TEXTOID, TEXTOID};
+	Datum		sqlargs[SPI_NARGS_2];
+	Oid			schemaOid = get_namespace_oid(PG_TLE_NSPNAME, false);
+
+	/*
+	 * Install times are not recorded until the pg_tle extension has been
+	 * updated to a version that has pgtle.extension_install_time.
+	 */
+	if (!OidIsValid(get_relname_relid(EXTENSION_INSTALL_TIME_TABLE, schemaOid)))
+		return;
+
+	/*
+	 * pgtle_admin cannot modify pgtle.extension_install_time directly, so
+	 * that it only contains the times recorded here.
+	 */
+	GetUserIdAndSecContext(&save_userid, &save_sec_context);
+	SetUserIdAndSecContext(BOOTSTRAP_SUPERUSERID,
+						   save_sec_context | SECURITY_LOCAL_USERID_CHANGE);
+
+	if (SPI_connect() != SPI_OK_CONNECT)
+		elog(ERROR, "SPI_connect failed");
+
+	sqlargs[0] = CStringGetTextDatum(extname);
+	sqlargs[1] = CStringGetTextDatum(extvers);
+
+	if (new_extension)
+	{
+		sql = psprintf("DELETE FROM %s.%s WHERE name OPERATOR(pg_catalog.=) $1",
+					   quote_identifier(PG_TLE_NSPNAME),
+					   quote_identifier(EXTENSION_INSTALL_TIME_TABLE));
+		spi_rc = SPI_execute_with_args(sql, 1, sqlargtypes, sqlargs, NULL, false, 0);
+		if (spi_rc != SPI_OK_DELETE)
+			elog(ERROR, "failed to delete from \"%s.%s\"",
+				 PG_TLE_NSPNAME, EXTENSION_INSTALL_TIME_TABLE);
+	}
+
+	sql = psprintf("INSERT INTO %s.%s VALUES ($1, $2, pg_catalog.now()) "
+				   "ON CONFLICT (name, version) DO UPDATE SET installed_at = EXCLUDED.installed_at",
+				   quote_identifier(PG_TLE_NSPNAME),
+				   quote_identifier(EXTENSION_INSTALL_TIME_TABLE));
+	spi_rc = SPI_execute_with_args(sql, 2, sqlargtypes, sqlargs, NULL, false, 0);
+	if (spi_rc != SPI_OK_INSERT)
+		elog(ERROR, "failed to insert into \"%s.%s\"",
+			 PG_TLE_NSPNAME, EXTENSION_INSTALL_TIME_TABLE);
+
+	if (SPI_finish() != SPI_OK_FINISH)
+		elog(ERROR, "SPI_finish failed");
+
+	SetUserIdAndSecContext(save_userid, save_sec_context);
+}
+
+/*
+ * Record an action on a TLE in pgtle.extension_history, along with the
+ * script it installed, if any. The script is stored compressed with
+ * compress_tle_script if possible. pgtle_admin cannot modify
+ * pgtle.extension_history directly, so the row is inserted as the bootstrap
+ * superuser and the current user is recorded as the user that performed the
+ * action.
+ */
+static void
+record_extension_history(const char *action, const char *extname,
+						 const char *extvers, const char *feature,
+						 const char *sql_str)
+{
+	Oid			save_userid;
+	int			save_sec_context;
+	int			spi_rc;
+	char	   *sql;
+	char	   *compressed;
+	Oid			sqlargtypes[SPI_NARGS_7] = {TEXTOID, TEXTOID, TEXTOID, TEXTOID, NAMEOID, TEXTOID, TEXTOID};
+	Datum		sqlargs[SPI_NARGS_7];
+	char		sqlnulls[SPI_NARGS_7] = {' ', ' ', ' ', ' ', ' ', ' ', ' '};
+	Oid			schemaOid = get_namespace_oid(PG_TLE_NSPNAME, false);
+
+	/*
+	 * History is not recorded until the pg_tle extension has been updated to
+	 * a version that has pgtle.extension_history.
+	 */
+	if (!OidIsValid(get_relname_relid(EXTENSION_HISTORY_TABLE, schemaOid)))
+		return;
+
+	GetUserIdAndSecContext(&save_userid, &save_sec_context);
+
+	sqlargs[0] = CStringGetTextDatum(action);
+	sqlargs[1] = CStringGetTextDatum(extname);
+	if (extvers != NULL)
+		sqlargs[2] = CStringGetTextDatum(extvers);
+	else
+		sqlnulls[2] = 'n';
+	if (feature != NULL)
+		sqlargs[3] = CStringGetTextDatum(feature);
+	else
+		sqlnulls[3] = 'n';
+	sqlargs[4] = DirectFunctionCall1(namein,
+									 CStringGetDatum(GetUserNameFromId(save_userid, false)));
+	if (sql_str != NULL)
+	{
+		compressed = compress_tle_script(sql_str);
+		sqlargs[5] = CStringGetTextDatum(sql_str);
+		sqlargs[6] = CStringGetTextDatum(compressed != NULL ? compressed : sql_str);
+	}
+	else
+	{
+		sqlnulls[5] = 'n';
+		sqlnulls[6] = 'n';
+	}
+
+	SetUserIdAndSecContext(BOOTSTRAP_SUPERUSERID,
+						   save_sec_context | SECURITY_LOCAL_USERID_CHANGE);
+
+	if (SPI_connect() != SPI_OK_CONNECT)
+		elog(ERROR, "SPI_connect failed");
+
+	sql = psprintf("INSERT INTO %s.%s (action, name, version, feature, performed_by, checksum, script) "
+				   "VALUES ($1, $2, $3, $4, $5, pg_catalog.encode(pg_catalog.sha256("
+				   "pg_catalog.convert_to($6, pg_catalog.getdatabaseencoding())), 'hex'), $7)",
+				   quote_identifier(PG_TLE_NSPNAME),
+				   quote_identifier(EXTENSION_HISTORY_TABLE));
+	spi_rc = SPI_execute_with_args(sql, 7, sqlargtypes, sqlargs, sqlnulls, false, 0);
+	if (spi_rc != SPI_OK_INSERT)
+		elog(ERROR, "failed to insert into \"%s.%s\"",
+			 PG_TLE_NSPNAME, EXTENSION_HISTORY_TABLE);
+
+	if (SPI_finish() != SPI_OK_FINISH)
+		elog(ERROR, "SPI_finish failed");
+
+	SetUserIdAndSecContext(save_userid, save_sec_context);
+}
+
+/*
+ * Return the names of the TLE control and script functions that a DROP
+ * FUNCTION statement drops, so that record_dropped_tle_artifacts can record
+ * them once they are dropped.
+ */
+static List *
+get_dropped_tle_artifacts(DropStmt *stmt)
+{
+	List	   *funcnames = NIL;
+	Oid			schemaOid = get_namespace_oid(PG_TLE_NSPNAME, false);
+	ListCell   *lc;
+
+	foreach(lc, stmt->objects)
+	{
+		ObjectWithArgs *owa = (ObjectWithArgs *) lfirst(lc);
+		Oid			funcid = LookupFuncWithArgs(OBJECT_FUNCTION, owa, true);
+		char	   *funcname;
+
+		if (!OidIsValid(funcid) || get_func_namespace(funcid) != schemaOid)
+			continue;
+
+		funcname = get_func_name(funcid);
+		if (is_tle_artifact_name(funcname))
+			funcnames = lappend(funcnames, funcname);
+	}
+
+	return funcnames;
+}
+
+/*
+ * Record the uninstall of the TLEs, versions and update paths whose functions
+ * a DROP FUNCTION statement dropped. Dropping the control function of a TLE
+ * uninstalls it, and dropping a script function of a TLE that is still
+ * installed uninstalls that version or update path.
+ */
+static void
+record_dropped_tle_artifacts(List *funcnames)
+{
+	ListCell   *lc;
+
+	foreach(lc, funcnames)
+	{
+		char	   *funcname = lfirst(lc);
+		size_t		len = strlen(funcname);
+		char	   *sep = strstr(funcname, "--");
+		char	   *extname;
+
+		if (sep == NULL)
+		{
+			/* primary control function, "extname.control" */
+			if (len > strlen(".control") &&
+				strcmp(funcname + len - strlen(".control"), ".control") == 0)
+				record_extension_history("uninstall",
+										 pnstrdup(funcname, len - strlen(".control")),
+										 NULL, NULL, NULL);
+			continue;
+		}
+
+		/* auxiliary control functions are dropped with their version */
+		if (strcmp(funcname + len - strlen(".sql"), ".sql") != 0)
+			continue;
+
+		extname = pnstrdup(funcname, sep - funcname);
+		if (OidIsValid(get_tlefunc_oid_if_exists(psprintf("%s.control", extname))))
+			record_extension_history("uninstall", extname,
+									 pnstrdup(sep + 2, len - (sep + 2 - funcname) - strlen(".sql")),
+									 NULL, NULL);
+	}
+}
+
+/*
+ * Record in pgtle.extension_history the functions registered or unregistered
+ * for a feature, i.e. added to or removed from pgtle.feature_info.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_feature_info_history);
+Datum
+pg_tle_feature_info_history(PG_FUNCTION_ARGS)
+{
+	TriggerData *trigdata = (TriggerData *) fcinfo->context;
+	TupleDesc	tupdesc;
+	HeapTuple	tuple;
+	const char *action;
+
+	if (!CALLED_AS_TRIGGER(fcinfo) ||
+		!TRIGGER_FIRED_AFTER(trigdata->tg_event) ||
+		!TRIGGER_FIRED_FOR_ROW(trigdata->tg_event))
+		elog(ERROR, "pg_tle_feature_info_history: must be fired after row");
+
+	if (TRIGGER_FIRED_BY_INSERT(trigdata->tg_event))
+		action = "register_feature";
+	else if (TRIGGER_FIRED_BY_DELETE(trigdata->tg_event))
+		action = "unregister_feature";
+	else
+		elog(ERROR, "pg_tle_feature_info_history: must be fired by INSERT or DELETE");
+
+	tupdesc = trigdata->tg_relation->rd_att;
+	tuple = trigdata->tg_trigtuple;
+
+	record_extension_history(action,
+							 SPI_getvalue(tuple, tupdesc, SPI_fnumber(tupdesc, "obj_identity")),
+							 NULL,
+							 SPI_getvalue(tuple, tupdesc, SPI_fnumber(tupdesc, "feature")),
+							 NULL);
+
+	return PointerGetDatum(NULL);
+}
+
+/*
+ * Return the script that was most recently installed for a version or update
+ * path of a TLE at or before a given time, decompressing it if
+ * record_extension_history stored it compressed.
+ */
+PG_FUNCTION_INFO_V1(pg_tle_extension_history_script);
+Datum
+pg_tle_extension_history_script(PG_FUNCTION_ARGS)
+{
+	int			spi_rc;
+	char	   *sql;
+	char	   *extname = text_to_cstring(PG_GETARG_TEXT_PP(0));
+	char	   *script = NULL;
+	Oid			sqlargtypes[SPI_NARGS_3] = {TEXTOID, TEXTOID, TIMESTAMPTZOID};
+	Datum		sqlargs[SPI_NARGS_3];
+	MemoryContext ctx = CurrentMemoryContext;
+
+	if (SPI_connect() != SPI_OK_CONNECT)
+		elog(ERROR, "SPI_connect failed");
+
+	sqlargs[0] = PG_GETARG_DATUM(0);
+	sqlargs[1] = PG_GETARG_DATUM(1);
+	sqlargs[2] = PG_GETARG_DATUM(2);
+
+	sql = psprintf("SELECT h.script FROM %s.%s h "
+				   "WHERE h.action OPERATOR(pg_catalog.=) 'install' "
+				   "AND h.name OPERATOR(pg_catalog.=) $1 "
+				   "AND h.version OPERATOR(pg_catalog.=) $2 "
+				   "AND h.performed_at OPERATOR(pg_catalog.<=) $3 "
+				   "ORDER BY h.performed_at DESC, h.id DESC LIMIT 1",
+				   quote_identifier(PG_TLE_NSPNAME),
+				   quote_identifier(EXTENSION_HISTORY_TABLE));
+	spi_rc = SPI_execute_with_args(sql, 3, sqlargtypes, sqlargs, NULL, true, 1);
+	if (spi_rc != SPI_OK_SELECT)
+		elog(ERROR, "failed to query \"%s.%s\"",
+			 PG_TLE_NSPNAME, EXTENSION_HISTORY_TABLE);
+
+	if (SPI_processed == 1)
+	{
+		MemoryContext oldcontext = MemoryContextSwitchTo(ctx);
+
+		script = SPI_getvalue(SPI_tuptable->vals[0],
+							  SPI_tuptable->tupdesc, 1);
+		MemoryContextSwitchTo(oldcontext);
+	}
+
+	if (SPI_finish() != SPI_OK_FINISH)
+		elog(ERROR, "SPI_finish failed");
+
+	script = decompress_tle_script(script, extname);
+	if (script == NULL)
+		PG_RETURN_NULL();
+
+	PG_RETURN_TEXT_P(cstring_to_text(script));
+}
+
+/*
+ * Look up when a version of a TLE was installed, or when any version of it
+ * was first installed if extvers is NULL. Returns false if the install time
+ * is not known, e.g. because the extension was installed before pg_tle
+ * started recording install times.
+ */
+static bool
+get_extension_install_time(const char *extname, const char *extvers,
+						   TimestampTz *installed_at)
+{
+	int			spi_rc;
+	StringInfo	sql = makeStringInfo();
+	Oid			sqlargtypes[SPI_NARGS_2] = {TEXTOID, TEXTOID};
+	Datum		sqlargs[SPI_NARGS_2];
+	Datum		value;
+	bool		isnull = true;
+
+	if (SPI_connect() != SPI_OK_CONNECT)
+		elog(ERROR, "SPI_connect failed");
+
+	appendStringInfo(sql, "SELECT pg_catalog.min(installed_at) FROM %s.%s "
+					 "WHERE name OPERATOR(pg_catalog.=) $1",
+					 quote_identifier(PG_TLE_NSPNAME),
+					 quote_identifier(EXTENSION_INSTALL_TIME_TABLE));
+	sqlargs[0] = CStringGetTextDatum(extname);
+
+	if (extvers != NULL)
+	{
+		appendStringInfoString(sql, " AND version OPERATOR(pg_catalog.=) $2");
+		sqlargs[1] = CStringGetTextDatum(extvers);
+	}
+
+	spi_rc = SPI_execute_with_args(sql->data, extvers != NULL ? 2 : 1,
+								   sqlargtypes, sqlargs, NULL, true, 0);
+	if (spi_rc != SPI_OK_SELECT)
+		elog(ERROR, "failed to query \"%s.%s\"",
+			 PG_TLE_NSPNAME, EXTENSION_INSTALL_TIME_TABLE);
+
+	if (SPI_processed == 1)
+	{
+		value = SPI_getbinval(SPI_tuptable->vals[0],
+							  SPI_tuptable->tupdesc, 1, &isnull);
+		if (!isnull)
+			*installed_at = DatumGetTimestampTz(value);
+	}
+
+	SPI_freetuptable(SPI_tuptable);
+
+	if (SPI_finish() != SPI_OK_FINISH)
+		elog(ERROR, "SPI_finish failed");
+
+	return !isnull;
 }
 
 Datum		pg_tle_install_extension(PG_FUNCTION_ARGS);
@@ -4405,8 +4982,7 @@ pg_tle_install_extension(PG_FUNCTION_ARGS)
 	char	   *sqlsql;
 	char	   *filename;
 	List	   *reqlist;
-	ListCell   *req;
-	bool		has_ext = false;
+	bool		trusted = false;
 	ExtensionControlFile *control;
 	ObjectAddress pgtleobj;
 	ObjectAddress ctlfunc;
@@ -4455,6 +5031,7 @@ pg_tle_install_extension(PG_FUNCTION_ARGS)
 				 errmsg("\"ext\" is a required argument")));
 
 	sql_str = text_to_cstring(PG_GETARG_TEXT_PP(3));
+	check_script_size(sql_str);
 
 	if (PG_ARGISNULL(4))
 		reqlist = NIL;
@@ -4465,29 +5042,31 @@ pg_tle_install_extension(PG_FUNCTION_ARGS)
 		check_requires_list(reqlist);
 	}
 
-	/*
-	 * Build appropriate function names based on extension name and version.
-	 */
-	sqlname = psprintf("%s--%s.sql", extname, extvers);
-	ctlname = psprintf("%s.control", extname);
+	/* "trusted" was added in 1.5.0 */
+	if (PG_NARGS() > 5 && !PG_ARGISNULL(5))
+		trusted = PG_GETARG_BOOL(5);
 
 	/*
-	 * Check if PG_TLE_EXTNAME is in the list of requirements. Meanwhile, also
-	 * build up the "requires" string for the extension control file.
+	 * The scripts of a trusted extension are run as the bootstrap superuser,
+	 * so only a superuser can vouch for them.
 	 */
-	foreach(req, reqlist)
-	{
-		char	   *reqname = lfirst(req);
+	if (trusted && !superuser())
+		ereport(ERROR,
+				(errcode(ERRCODE_INSUFFICIENT_PRIVILEGE),
+				 errmsg("permission denied to install trusted extension \"%s\"", extname),
+				 errhint("Must be superuser to install a trusted extension.")));
 
-		has_ext = has_ext ||
-			strncmp(reqname, PG_TLE_EXTNAME, sizeof(PG_TLE_EXTNAME)) == 0;
+	if (trusted)
+		check_trusted_extension_scripts(extname);
 
-		if (has_ext)
-			break;
-	}
+	/*
+	 * Build appropriate function names based on extension name and version.
+	 */
+	sqlname = psprintf("%s--%s.sql", extname, extvers);
+	ctlname = psprintf("%s.control", extname);
 
-	if (!has_ext)
-		reqlist = lappend(reqlist, PG_TLE_EXTNAME);
+	/* every TLE requires PG_TLE_EXTNAME */
+	reqlist = add_pg_tle_to_requires(reqlist);
 
 	/*
 	 * Build up the control file that will be injected into the DB for the
@@ -4497,10 +5076,8 @@ pg_tle_install_extension(PG_FUNCTION_ARGS)
 	control = build_default_extension_control_file(extname);
 	control->relocatable = false;
 	/* explicitly set to false */
-	control->superuser = false;
-	/* explicitly set to false */
-	control->trusted = false;
-	/* explicitly set to false; */
+	control->superuser = trusted;
+	control->trusted = trusted;
 	control->default_version = pstrdup(extvers);
 	control->comment = pstrdup(extdesc);
 	control->requires = reqlist;
@@ -4532,11 +5109,11 @@ pg_tle_install_extension(PG_FUNCTION_ARGS)
 
 	sqlsql = psprintf(
 					  "CREATE FUNCTION %s.%s() RETURNS TEXT AS %s"
-					  "SELECT %s%s%s%s LANGUAGE SQL",
+					  "%s%s LANGUAGE SQL",
 					  PG_TLE_NSPNAME, quote_identifier(sqlname),
-					  PG_TLE_OUTER_STR, PG_TLE_INNER_STR,
-					  sql_str,
-					  PG_TLE_INNER_STR, PG_TLE_OUTER_STR);
+					  PG_TLE_OUTER_STR,
+					  build_script_function_body(sql_str),
+					  PG_TLE_OUTER_STR);
 	ctlsql = psprintf(
 					  "CREATE OR REPLACE FUNCTION %s.%s() RETURNS TEXT AS %s"
 					  "SELECT %s%s%s%s LANGUAGE SQL",
@@ -4586,6 +5163,8 @@ pg_tle_install_extension(PG_FUNCTION_ARGS)
 	if (SPI_finish() != SPI_OK_FINISH)
 		elog(ERROR, "SPI_finish failed");
 
+	check_extension_quotas(extname);
+
 	/* .sql and .control functions must depend on pg_tle extension */
 	pgtleExtId = get_extension_oid(PG_TLE_EXTNAME, true /* missing_ok */ );
 	if (pgtleExtId == InvalidOid)
@@ -4615,6 +5194,9 @@ pg_tle_install_extension(PG_FUNCTION_ARGS)
 	recordDependencyOn(&ctlfunc, &pgtleobj, DEPENDENCY_NORMAL);
 	recordDependencyOn(&sqlfunc, &pgtleobj, DEPENDENCY_NORMAL);
 
+	record_extension_install_time(extname, extvers, true);
+	record_extension_history("install", extname, extvers, NULL, sql_str);
+
 	/* done manipulating pg_tle artifacts */
 	UNSET_TLEART;
 
@@ -4668,6 +5250,8 @@ pg_tle_install_extension_version_sql(PG_FUNCTION_ARGS)
 				(errcode(ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE),
 				 errmsg("could not find control function %s for extension %s in schema %s", quote_identifier(ctlname), quote_identifier(extname), PG_TLE_NSPNAME)));
 
+	check_can_add_trusted_extension_script(extname);
+
 	if (PG_ARGISNULL(1))
 		ereport(ERROR,
 				(errcode(ERRCODE_NULL_VALUE_NOT_ALLOWED),
@@ -4682,6 +5266,7 @@ pg_tle_install_extension_version_sql(PG_FUNCTION_ARGS)
 				 errmsg("\"ext\" is a required argument")));
 
 	sql_str = text_to_cstring(PG_GETARG_TEXT_PP(2));
+	check_script_size(sql_str);
 
 	/*
 	 * Build appropriate function names based on extension name and version
@@ -4706,11 +5291,11 @@ pg_tle_install_extension_version_sql(PG_FUNCTION_ARGS)
 
 	sqlsql = psprintf(
 					  "CREATE FUNCTION %s.%s() RETURNS TEXT AS %s"
-					  "SELECT %s%s%s%s LANGUAGE SQL",
+					  "%s%s LANGUAGE SQL",
 					  PG_TLE_NSPNAME, quote_identifier(sqlname),
-					  PG_TLE_OUTER_STR, PG_TLE_INNER_STR,
-					  sql_str,
-					  PG_TLE_INNER_STR, PG_TLE_OUTER_STR);
+					  PG_TLE_OUTER_STR,
+					  build_script_function_body(sql_str),
+					  PG_TLE_OUTER_STR);
 
 	/* flag that we are manipulating pg_tle artifacts */
 	SET_TLEART;
@@ -4748,6 +5333,8 @@ pg_tle_install_extension_version_sql(PG_FUNCTION_ARGS)
 	if (SPI_finish() != SPI_OK_FINISH)
 		elog(ERROR, "SPI_finish failed");
 
+	check_extension_quotas(extname);
+
 	/* .sql and .control functions must depend on pg_tle extension */
 	pgtleExtId = get_extension_oid(PG_TLE_EXTNAME, true /* missing_ok */ );
 	if (pgtleExtId == InvalidOid)
@@ -4767,6 +5354,9 @@ pg_tle_install_extension_version_sql(PG_FUNCTION_ARGS)
 
 	recordDependencyOn(&sqlfunc, &pgtleobj, DEPENDENCY_NORMAL);
 
+	record_extension_install_time(extname, extvers, false);
+	record_extension_history("install", extname, extvers, NULL, sql_str);
+
 	/* done manipulating pg_tle artifacts */
 	UNSET_TLEART;
 
@@ -4806,6 +5396,8 @@ pg_tle_install_update_path(PG_FUNCTION_ARGS)
 				(errcode(ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE),
 				 errmsg("control file already exists for the \"%s\" extension", extname)));
 
+	check_can_add_trusted_extension_script(extname);
+
 	if (PG_ARGISNULL(1))
 		ereport(ERROR,
 				(errcode(ERRCODE_NULL_VALUE_NOT_ALLOWED),
@@ -4827,6 +5419,7 @@ pg_tle_install_update_path(PG_FUNCTION_ARGS)
 				 errmsg("\"ext\" is a required argument")));
 
 	sql_str = text_to_cstring(PG_GETARG_TEXT_PP(3));
+	check_script_size(sql_str);
 
 	/*
 	 * Validate that there are no injections using the dollar-quoted strings
@@ -4842,11 +5435,11 @@ pg_tle_install_update_path(PG_FUNCTION_ARGS)
 	sqlname = psprintf("%s--%s--%s.sql", extname, fromvers, tovers);
 	sqlsql = psprintf(
 					  "CREATE FUNCTION %s.%s() RETURNS TEXT AS %s"
-					  "SELECT %s%s%s%s LANGUAGE SQL",
+					  "%s%s LANGUAGE SQL",
 					  quote_identifier(PG_TLE_NSPNAME), quote_identifier(sqlname),
-					  PG_TLE_OUTER_STR, PG_TLE_INNER_STR,
-					  sql_str,
-					  PG_TLE_INNER_STR, PG_TLE_OUTER_STR);
+					  PG_TLE_OUTER_STR,
+					  build_script_function_body(sql_str),
+					  PG_TLE_OUTER_STR);
 
 	/* flag that we are manipulating pg_tle artifacts */
 	SET_TLEART;
@@ -4885,6 +5478,11 @@ pg_tle_install_update_path(PG_FUNCTION_ARGS)
 	if (SPI_finish() != SPI_OK_FINISH)
 		elog(ERROR, "SPI_finish failed");
 
+	check_extension_quotas(extname);
+
+	record_extension_history("install", extname,
+							 psprintf("%s--%s", fromvers, tovers), NULL, sql_str);
+
 	/* done manipulating pg_tle artifacts */
 	UNSET_TLEART;
 
@@ -4900,12 +5498,9 @@ pg_tle_set_default_version(PG_FUNCTION_ARGS)
 	int			spi_rc;
 	char	   *extname;
 	char	   *extvers;
-	char	   *ctlname;
 	char	   *versql;
 	Oid			verargtypes[SPI_NARGS_2] = {TEXTOID, TEXTOID};
 	Datum		verargs[SPI_NARGS_2];
-	StringInfo	ctlstr;
-	char	   *ctlsql;
 	ExtensionControlFile *control;
 	char	   *filename;
 	List	   *updateVersions;
@@ -4964,6 +5559,9 @@ pg_tle_set_default_version(PG_FUNCTION_ARGS)
 				 errmsg("extension and version do not exist"),
 				 errhint("Try installing the extension with \"%s.install_extension\".", PG_TLE_NSPNAME)));
 
+	if (SPI_finish() != SPI_OK_FINISH)
+		elog(ERROR, "SPI_finish failed");
+
 	/*
 	 * Modify the control file with a new version
 	 */
@@ -4975,17 +5573,95 @@ pg_tle_set_default_version(PG_FUNCTION_ARGS)
 
 	control->default_version = pstrdup(extvers);
 
-	ctlname = psprintf("%s.control", extname);
-	ctlstr = build_extension_control_file_string(control);
+	write_tle_control(extname, control);
 
 	/*
-	 * Validate that there are no injections using the dollar-quoted strings
+	 * When default version is updated we update the dependencies so that
+	 * pg_dump can maintain the correct order.
 	 */
-	if (!(validate_tle_sql(ctlstr->data)))
-		ereport(ERROR,
-				(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
-				 errmsg("invalid character in extension definition"),
-				 errdetail("Use of string delimiters %s and %s are forbidden in extension definitions.",
+	extensionOid = get_extension_oid(extname, true);
+	if (extensionOid != InvalidOid)
+	{
+		const char *defaultVersion = control->default_version;
+
+		extAddress.classId = ExtensionRelationId;
+		extAddress.objectId = extensionOid;
+		extAddress.objectSubId = 0;
+
+		SET_TLEEXT;
+		updateVersions = find_versions_to_apply(control, &defaultVersion);
+		UNSET_TLEEXT;
+		record_sql_function_dependencies(extname, defaultVersion, updateVersions, extAddress);
+	}
+
+	record_extension_history("set_default_version", extname, extvers, NULL, NULL);
+
+	PG_RETURN_BOOL(true);
+}
+
+/*
+ * Read the primary control function of an installed TLE so that its
+ * parameters can be modified in place. Raises an error if the TLE is not
+ * installed.
+ */
+static ExtensionControlFile *
+read_tle_control_for_update(const char *extname)
+{
+	ExtensionControlFile *control;
+	char	   *filename;
+	char	   *ctlname;
+
+	check_valid_extension_name(extname);
+
+	/*
+	 * Verify that extname does not already exist as a standard file-based
+	 * extension.
+	 */
+	filename = get_extension_control_filename(extname);
+	if (filestat(filename))
+		ereport(ERROR,
+				(errcode(ERRCODE_OBJECT_NOT_IN_PREREQUISITE_STATE),
+				 errmsg("control file already exists for the %s extension", extname)));
+
+	ctlname = psprintf("%s.control", extname);
+	if (!OidIsValid(get_tlefunc_oid_if_exists(ctlname)))
+		ereport(ERROR,
+				(errcode(ERRCODE_UNDEFINED_OBJECT),
+				 errmsg("extension \"%s\" does not exist", extname),
+				 errhint("Try installing the extension with \"%s.install_extension\".", PG_TLE_NSPNAME)));
+
+	control = build_default_extension_control_file(extname);
+
+	SET_TLEEXT;
+	parse_extension_control_file(control, NULL);
+	UNSET_TLEEXT;
+
+	return control;
+}
+
+/*
+ * Replace the primary control function of an installed TLE with one built
+ * from the given control parameters.
+ */
+static void
+write_tle_control(const char *extname, ExtensionControlFile *control)
+{
+	int			spi_rc;
+	char	   *ctlname;
+	StringInfo	ctlstr;
+	char	   *ctlsql;
+
+	ctlname = psprintf("%s.control", extname);
+	ctlstr = build_extension_control_file_string(control);
+
+	/*
+	 * Validate that there are no injections using the dollar-quoted strings
+	 */
+	if (!(validate_tle_sql(ctlstr->data)))
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
+				 errmsg("invalid character in extension definition"),
+				 errdetail("Use of string delimiters \"%s\" and \"%s\" are forbidden in extension definitions.",
 						   PG_TLE_OUTER_STR, PG_TLE_INNER_STR),
 				 errhint("This may be an attempt at a SQL injection attack. Please verify your installation file.")));
 
@@ -5000,36 +5676,81 @@ pg_tle_set_default_version(PG_FUNCTION_ARGS)
 	/* flag that we are manipulating pg_tle artifacts */
 	SET_TLEART;
 
+	if (SPI_connect() != SPI_OK_CONNECT)
+		elog(ERROR, "SPI_connect failed");
+
 	spi_rc = SPI_exec(ctlsql, 0);
 	if (spi_rc != SPI_OK_UTILITY)
 		ereport(ERROR,
 				(errcode(ERRCODE_INTERNAL_ERROR),
-				 errmsg("failed to updated default version for \"%s\"", extname)));
+				 errmsg("failed to update control function for \"%s\"", extname)));
 
 	if (SPI_finish() != SPI_OK_FINISH)
 		elog(ERROR, "SPI_finish failed");
 
-	/*
-	 * When default version is updated we update the dependencies so that
-	 * pg_dump can maintain the correct order.
-	 */
-	extensionOid = get_extension_oid(extname, true);
-	if (extensionOid != InvalidOid)
-	{
-		const char *defaultVersion = control->default_version;
+	/* flag that we are done manipulating pg_tle artifacts */
+	UNSET_TLEART;
+}
 
-		extAddress.classId = ExtensionRelationId;
-		extAddress.objectId = extensionOid;
-		extAddress.objectSubId = 0;
+Datum		pg_tle_set_requires(PG_FUNCTION_ARGS);
 
-		SET_TLEEXT;
-		updateVersions = find_versions_to_apply(control, &defaultVersion);
-		UNSET_TLEEXT;
-		record_sql_function_dependencies(extname, defaultVersion, updateVersions, extAddress);
+PG_FUNCTION_INFO_V1(pg_tle_set_requires);
+Datum
+pg_tle_set_requires(PG_FUNCTION_ARGS)
+{
+	char	   *extname;
+	List	   *reqlist;
+	ExtensionControlFile *control;
+
+	if (PG_ARGISNULL(0))
+		ereport(ERROR,
+				(errcode(ERRCODE_NULL_VALUE_NOT_ALLOWED),
+				 errmsg("\"name\" is a required argument")));
+
+	extname = text_to_cstring(PG_GETARG_TEXT_PP(0));
+	control = read_tle_control_for_update(extname);
+
+	if (PG_ARGISNULL(1))
+		reqlist = NIL;
+	else
+	{
+		reqlist = textarray_to_stringlist(PG_GETARG_ARRAYTYPE_P(1));
+		check_requires_list(reqlist);
 	}
 
-	/* flag that we are done manipulating pg_tle artifacts */
-	UNSET_TLEART;
+	/* every TLE requires PG_TLE_EXTNAME */
+	control->requires = add_pg_tle_to_requires(reqlist);
+
+	write_tle_control(extname, control);
+
+	PG_RETURN_BOOL(true);
+}
+
+Datum		pg_tle_set_comment(PG_FUNCTION_ARGS);
+
+PG_FUNCTION_INFO_V1(pg_tle_set_comment);
+Datum
+pg_tle_set_comment(PG_FUNCTION_ARGS)
+{
+	char	   *extname;
+	ExtensionControlFile *control;
+
+	if (PG_ARGISNULL(0))
+		ereport(ERROR,
+				(errcode(ERRCODE_NULL_VALUE_NOT_ALLOWED),
+				 errmsg("\"name\" is a required argument")));
+
+	extname = text_to_cstring(PG_GETARG_TEXT_PP(0));
+	control = read_tle_control_for_update(extname);
+
+	if (PG_ARGISNULL(1))
+		ereport(ERROR,
+				(errcode(ERRCODE_NULL_VALUE_NOT_ALLOWED),
+				 errmsg("\"comment\" is a required argument")));
+
+	control->comment = text_to_cstring(PG_GETARG_TEXT_PP(1));
+
+	write_tle_control(extname, control);
 
 	PG_RETURN_BOOL(true);
 }
@@ -5072,6 +5793,124 @@ validate_tle_sql(char *sql)
 				   strstr(sql, PG_TLE_OUTER_STR) == NULL && strstr(sql, PG_TLE_INNER_STR) == NULL);
 }
 
+/*
+ * Compress a TLE script with pglz and encode it in base64, after a header
+ * with the size of the script. Only scripts of at least
+ * TLE_SCRIPT_COMPRESS_MIN_SIZE bytes are compressed, and only when that makes
+ * them smaller, even once encoded. Returns NULL if the script is not
+ * compressed.
+ */
+static char *
+compress_tle_script(const char *sql_str)
+{
+	int32		rawsize = strlen(sql_str);
+	bytea	   *compressed;
+	int32		len;
+	char	   *encoded;
+
+	if (rawsize < TLE_SCRIPT_COMPRESS_MIN_SIZE)
+		return NULL;
+
+	compressed = (bytea *) palloc(VARHDRSZ + PGLZ_MAX_OUTPUT(rawsize));
+	len = pglz_compress(sql_str, rawsize, VARDATA(compressed),
+						PGLZ_strategy_default);
+	if (len < 0)
+		return NULL;
+
+	SET_VARSIZE(compressed, VARHDRSZ + len);
+	encoded = TextDatumGetCString(DirectFunctionCall2(binary_encode,
+													  PointerGetDatum(compressed),
+													  CStringGetTextDatum("base64")));
+	encoded = psprintf("%s%d%s%s", TLE_COMPRESSED_SCRIPT_PREFIX, rawsize,
+					   TLE_COMPRESSED_SCRIPT_SUFFIX, encoded);
+
+	if (strlen(encoded) >= (size_t) rawsize)
+		return NULL;
+
+	return encoded;
+}
+
+/*
+ * Return a TLE script as it was before compress_tle_script, if it was
+ * compressed. name is used in the error raised if it cannot be decompressed.
+ */
+static char *
+decompress_tle_script(char *script, const char *name)
+{
+	char	   *sizestr;
+	char	   *endptr;
+	long		rawsize;
+	bytea	   *compressed;
+	char	   *result;
+
+	if (script == NULL ||
+		strncmp(script, TLE_COMPRESSED_SCRIPT_PREFIX,
+				strlen(TLE_COMPRESSED_SCRIPT_PREFIX)) != 0)
+		return script;
+
+	sizestr = script + strlen(TLE_COMPRESSED_SCRIPT_PREFIX);
+	errno = 0;
+	rawsize = strtol(sizestr, &endptr, 10);
+	if (errno != 0 || endptr == sizestr || rawsize < 0 ||
+		rawsize >= MaxAllocSize ||
+		strncmp(endptr, TLE_COMPRESSED_SCRIPT_SUFFIX,
+				strlen(TLE_COMPRESSED_SCRIPT_SUFFIX)) != 0)
+		ereport(ERROR,
+				(errcode(ERRCODE_DATA_CORRUPTED),
+				 errmsg("compressed extension script \"%s\" is corrupted", name)));
+
+	compressed = DatumGetByteaPP(DirectFunctionCall2(binary_decode,
+													 CStringGetTextDatum(endptr + strlen(TLE_COMPRESSED_SCRIPT_SUFFIX)),
+													 CStringGetTextDatum("base64")));
+
+	result = palloc(rawsize + 1);
+	if (pglz_decompress(VARDATA_ANY(compressed), VARSIZE_ANY_EXHDR(compressed),
+						result, rawsize, true) != rawsize)
+		ereport(ERROR,
+				(errcode(ERRCODE_DATA_CORRUPTED),
+				 errmsg("compressed extension script \"%s\" is corrupted", name)));
+	result[rawsize] = '\0';
+
+	return result;
+}
+
+/*
+ * Build the body of the function that returns a TLE script, compressed with
+ * compress_tle_script if possible. Scripts that start with the header of
+ * compressed scripts themselves are rejected, as read_tle_script could not
+ * tell them apart from compressed ones.
+ */
+static char *
+build_script_function_body(const char *sql_str)
+{
+	char	   *compressed;
+
+	if (strncmp(sql_str, TLE_COMPRESSED_SCRIPT_PREFIX,
+				strlen(TLE_COMPRESSED_SCRIPT_PREFIX)) == 0)
+		ereport(ERROR,
+				(errcode(ERRCODE_INVALID_PARAMETER_VALUE),
+				 errmsg("extension script must not start with \"%s\"",
+						TLE_COMPRESSED_SCRIPT_PREFIX),
+				 errhint("Add a line before the comment.")));
+
+	compressed = compress_tle_script(sql_str);
+	if (compressed != NULL)
+		return psprintf("SELECT %s%s%s", PG_TLE_INNER_STR, compressed, PG_TLE_INNER_STR);
+
+	return psprintf("SELECT %s%s%s", PG_TLE_INNER_STR, sql_str, PG_TLE_INNER_STR);
+}
+
+/*
+ * Execute the function that returns a TLE script and return the script,
+ * decompressing it if build_script_function_body stored it compressed.
+ * Returns NULL if the function does not return a row.
+ */
+static char *
+read_tle_script(const char *funcname)
+{
+	return decompress_tle_script(exec_scalar_text_sql_func(funcname), funcname);
+}
+
 /*
  * Check that a TLE requires list is valid. This includes check its length.
  * Raises errors if its invalid.
@@ -5086,6 +5925,276 @@ check_requires_list(List *requires)
 						TLE_REQUIRES_LIMIT, PG_TLE_EXTNAME)));
 }
 
+/*
+ * Add PG_TLE_EXTNAME to a TLE requires list if it is not in it already.
+ */
+static List *
+add_pg_tle_to_requires(List *requires)
+{
+	ListCell   *req;
+
+	foreach(req, requires)
+	{
+		char	   *reqname = lfirst(req);
+
+		if (strncmp(reqname, PG_TLE_EXTNAME, sizeof(PG_TLE_EXTNAME)) == 0)
+			return requires;
+	}
+
+	return lappend(requires, PG_TLE_EXTNAME);
+}
+
+/*
+ * Check that the current user may add a script to the TLE. The scripts of a
+ * trusted TLE are run as the bootstrap superuser, so only a superuser may add
+ * to them. Raises an error if the user may not.
+ */
+static void
+check_can_add_trusted_extension_script(const char *extname)
+{
+	ExtensionControlFile *control;
+	char	   *ctlname;
+
+	if (superuser())
+		return;
+
+	/* nothing to check if the extension is not installed yet */
+	ctlname = psprintf("%s.control", extname);
+	if (!OidIsValid(get_tlefunc_oid_if_exists(ctlname)))
+		return;
+
+	control = build_default_extension_control_file(extname);
+
+	SET_TLEEXT;
+	parse_extension_control_file(control, NULL);
+	UNSET_TLEEXT;
+
+	if (control->trusted)
+		ereport(ERROR,
+				(errcode(ERRCODE_INSUFFICIENT_PRIVILEGE),
+				 errmsg("permission denied to add scripts to trusted extension \"%s\"", extname),
+				 errhint("Must be superuser to add scripts to a trusted extension.")));
+}
+
+/*
+ * Check that the scripts already installed for a TLE that is about to be
+ * installed as trusted, such as update paths, are owned by superusers.
+ * Otherwise a script provided by a non-superuser would be run as the
+ * bootstrap superuser. Raises an error if one is not.
+ */
+static void
+check_trusted_extension_scripts(const char *extname)
+{
+	int			spi_rc;
+	char	   *sql;
+	Oid			sqlargtypes[SPI_NARGS_2] = {TEXTOID, TEXTOID};
+	Datum		sqlargs[SPI_NARGS_2];
+	char	   *funcname = NULL;
+	MemoryContext ctx = CurrentMemoryContext;
+
+	if (SPI_connect() != SPI_OK_CONNECT)
+		elog(ERROR, "SPI_connect failed");
+
+	sql = "SELECT p.proname FROM pg_catalog.pg_proc p "
+		"JOIN pg_catalog.pg_namespace n ON n.oid OPERATOR(pg_catalog.=) p.pronamespace "
+		"JOIN pg_catalog.pg_roles r ON r.oid OPERATOR(pg_catalog.=) p.proowner "
+		"WHERE n.nspname OPERATOR(pg_catalog.=) $1::pg_catalog.name AND "
+		"pg_catalog.starts_with(p.proname::pg_catalog.text, $2) AND "
+		"NOT r.rolsuper LIMIT 1";
+	sqlargs[0] = CStringGetTextDatum(PG_TLE_NSPNAME);
+	sqlargs[1] = CStringGetTextDatum(psprintf("%s--", extname));
+
+	spi_rc = SPI_execute_with_args(sql, 2, sqlargtypes, sqlargs, NULL, true, 0);
+	if (spi_rc != SPI_OK_SELECT)
+		elog(ERROR, "failed to look up the scripts of extension \"%s\"", extname);
+
+	/* copy the function name out of the SPI memory context */
+	if (SPI_processed > 0)
+		funcname = MemoryContextStrdup(ctx,
+									   SPI_getvalue(SPI_tuptable->vals[0],
+													SPI_tuptable->tupdesc, 1));
+
+	SPI_freetuptable(SPI_tuptable);
+
+	if (SPI_finish() != SPI_OK_FINISH)
+		elog(ERROR, "SPI_finish failed");
+
+	if (funcname != NULL)
+		ereport(ERROR,
+				(errcode(ERRCODE_INSUFFICIENT_PRIVILEGE),
+				 errmsg("cannot install trusted extension \"%s\"", extname),
+				 errdetail("Function \"%s\".\"%s\" is not owned by a superuser.",
+						   PG_TLE_NSPNAME, funcname),
+				 errhint("Uninstall the update paths of the extension with \"%s.uninstall_update_path\" and retry.",
+						 PG_TLE_NSPNAME)));
+}
+
+/*
+ * Check that a function that makes up a trusted TLE, if it exists, is owned
+ * by a superuser, since the script is about to be run as the bootstrap
+ * superuser. Raises an error if it is not.
+ */
+static void
+check_trusted_extension_function_owner(const char *extname, const char *funcname)
+{
+	Oid			funcid;
+	HeapTuple	tuple;
+	Oid			ownerid;
+
+	funcid = get_tlefunc_oid_if_exists(funcname);
+	if (!OidIsValid(funcid))
+		return;
+
+	tuple = SearchSysCache1(PROCOID, ObjectIdGetDatum(funcid));
+	if (!HeapTupleIsValid(tuple))
+		elog(ERROR, "cache lookup failed for function %u", funcid);
+	ownerid = ((Form_pg_proc) GETSTRUCT(tuple))->proowner;
+	ReleaseSysCache(tuple);
+
+	if (!superuser_arg(ownerid))
+		ereport(ERROR,
+				(errcode(ERRCODE_INSUFFICIENT_PRIVILEGE),
+				 errmsg("permission denied to run the scripts of trusted extension \"%s\"", extname),
+				 errdetail("Function \"%s\".\"%s\" is not owned by a superuser.",
+						   PG_TLE_NSPNAME, funcname)));
+}
+
+/*
+ * Check that a TLE version or update path script is no larger than
+ * pgtle.max_script_size. Raises an error if it is.
+ */
+static void
+check_script_size(const char *sql_str)
+{
+	size_t		len;
+
+	if (max_script_size < 0)
+		return;
+
+	len = strlen(sql_str);
+	if (len > (size_t) max_script_size)
+		ereport(ERROR,
+				(errcode(ERRCODE_CONFIGURATION_LIMIT_EXCEEDED),
+				 errmsg("extension script is too large"),
+				 errdetail("The script is %zu bytes and \"pgtle.max_script_size\" is set to %d bytes.",
+						   len, max_script_size)));
+}
+
+/*
+ * Check that the TLEs installed in the database, including the script that
+ * was just installed for the given TLE, are within the limits set by
+ * pgtle.max_extensions, pgtle.max_extension_versions and
+ * pgtle.max_extension_size. Raises an error if they are not, which rolls
+ * back the installation of the script.
+ */
+static void
+check_extension_quotas(const char *extname)
+{
+	int			spi_rc;
+	char	   *sql;
+	Oid			sqlargtypes[SPI_NARGS_2] = {TEXTOID, TEXTOID};
+	Datum		sqlargs[SPI_NARGS_2];
+	char	   *prefix;
+	List	   *scripts = NIL;
+	ListCell   *lc;
+	int64		nextensions = 0;
+	int			nversions = 0;
+	int64		total_size = 0;
+	uint64		i;
+	MemoryContext ctx = CurrentMemoryContext;
+
+	if (max_extensions < 0 && max_extension_versions < 0 && max_extension_size < 0)
+		return;
+
+	prefix = psprintf("%s--", extname);
+
+	if (SPI_connect() != SPI_OK_CONNECT)
+		elog(ERROR, "SPI_connect failed");
+
+	/* update paths have the same prefix but are not counted as versions */
+	sql = "SELECT p.proname FROM pg_catalog.pg_proc p "
+		"JOIN pg_catalog.pg_namespace n ON n.oid OPERATOR(pg_catalog.=) p.pronamespace "
+		"WHERE n.nspname OPERATOR(pg_catalog.=) $1::pg_catalog.name AND "
+		"pg_catalog.starts_with(p.proname::pg_catalog.text, $2) AND "
+		"p.proname OPERATOR(pg_catalog.~~) '%.sql'";
+	sqlargs[0] = CStringGetTextDatum(PG_TLE_NSPNAME);
+	sqlargs[1] = CStringGetTextDatum(prefix);
+
+	spi_rc = SPI_execute_with_args(sql, 2, sqlargtypes, sqlargs, NULL, true, 0);
+	if (spi_rc != SPI_OK_SELECT)
+		elog(ERROR, "failed to look up the scripts of extension \"%s\"", extname);
+
+	for (i = 0; i < SPI_processed; i++)
+	{
+		char	   *funcname = MemoryContextStrdup(ctx,
+												   SPI_getvalue(SPI_tuptable->vals[i],
+																SPI_tuptable->tupdesc, 1));
+
+		if (strstr(funcname + strlen(prefix), "--") == NULL)
+			nversions++;
+		scripts = lappend(scripts, funcname);
+	}
+
+	SPI_freetuptable(SPI_tuptable);
+
+	if (max_extensions >= 0)
+	{
+		bool		isnull;
+
+		sql = "SELECT pg_catalog.count(*) FROM pg_catalog.pg_proc p "
+			"JOIN pg_catalog.pg_namespace n ON n.oid OPERATOR(pg_catalog.=) p.pronamespace "
+			"WHERE n.nspname OPERATOR(pg_catalog.=) $1::pg_catalog.name AND "
+			"p.proname OPERATOR(pg_catalog.~~) '%.control' AND "
+			"pg_catalog.strpos(p.proname::pg_catalog.text, '--') OPERATOR(pg_catalog.=) 0";
+
+		spi_rc = SPI_execute_with_args(sql, 1, sqlargtypes, sqlargs, NULL, true, 0);
+		if (spi_rc != SPI_OK_SELECT || SPI_processed != 1)
+			elog(ERROR, "failed to count the installed extensions");
+
+		nextensions = DatumGetInt64(SPI_getbinval(SPI_tuptable->vals[0],
+												  SPI_tuptable->tupdesc, 1, &isnull));
+
+		SPI_freetuptable(SPI_tuptable);
+	}
+
+	if (SPI_finish() != SPI_OK_FINISH)
+		elog(ERROR, "SPI_finish failed");
+
+	if (max_extensions >= 0 && nextensions > max_extensions)
+		ereport(ERROR,
+				(errcode(ERRCODE_CONFIGURATION_LIMIT_EXCEEDED),
+				 errmsg("too many extensions installed"),
+				 errdetail("\"pgtle.max_extensions\" is set to %d.", max_extensions),
+				 errhint("Uninstall extensions that are no longer needed with \"%s.uninstall_extension\".",
+						 PG_TLE_NSPNAME)));
+
+	if (max_extension_versions >= 0 && nversions > max_extension_versions)
+		ereport(ERROR,
+				(errcode(ERRCODE_CONFIGURATION_LIMIT_EXCEEDED),
+				 errmsg("too many versions of extension \"%s\" installed", extname),
+				 errdetail("\"pgtle.max_extension_versions\" is set to %d.", max_extension_versions),
+				 errhint("Uninstall versions that are no longer needed with \"%s.uninstall_extension\".",
+						 PG_TLE_NSPNAME)));
+
+	if (max_extension_size < 0)
+		return;
+
+	foreach(lc, scripts)
+	{
+		char	   *script = read_tle_script(lfirst(lc));
+
+		if (script != NULL)
+			total_size += strlen(script);
+	}
+
+	if (total_size > max_extension_size)
+		ereport(ERROR,
+				(errcode(ERRCODE_CONFIGURATION_LIMIT_EXCEEDED),
+				 errmsg("scripts of extension \"%s\" are too large", extname),
+				 errdetail("The scripts are " INT64_FORMAT " bytes and \"pgtle.max_extension_size\" is set to %d bytes.",
+						   total_size, max_extension_size)));
+}
+
 /*
  * is_pgtle_defined_c_func
  *
@@ -5135,7 +6244,12 @@ is_pgtle_defined_c_func(Oid funcid, bool *is_operator_func)
 
 	result = *is_operator_func ||
 		strncmp(prosrcstring, TLE_BASE_TYPE_IN, sizeof(TLE_BASE_TYPE_IN)) == 0 ||
-		strncmp(prosrcstring, TLE_BASE_TYPE_OUT, sizeof(TLE_BASE_TYPE_OUT)) == 0;
+		strncmp(prosrcstring, TLE_BASE_TYPE_OUT, sizeof(TLE_BASE_TYPE_OUT)) == 0 ||
+		strncmp(prosrcstring, TLE_BASE_TYPE_RECV, sizeof(TLE_BASE_TYPE_RECV)) == 0 ||
+		strncmp(prosrcstring, TLE_BASE_TYPE_SEND, sizeof(TLE_BASE_TYPE_SEND)) == 0 ||
+		strncmp(prosrcstring, TLE_BASE_TYPE_TYPMOD_IN, sizeof(TLE_BASE_TYPE_TYPMOD_IN)) == 0 ||
+		strncmp(prosrcstring, TLE_BASE_TYPE_TYPMOD_OUT, sizeof(TLE_BASE_TYPE_TYPMOD_OUT)) == 0 ||
+		strncmp(prosrcstring, TLE_BASE_TYPE_ANALYZE, sizeof(TLE_BASE_TYPE_ANALYZE)) == 0;
 	pfree(prosrcstring);
 	return result;
 }
@@ -5147,10 +6261,11 @@ is_pgtle_defined_c_func(Oid funcid, bool *is_operator_func)
  * `is_operator_func` is set to true when the input function is an
  * operator function used by create_operator_func API.
  *
- * If a function is used by pgtle datatype APIs (i.e. create_base_type
- * or create_operator_func), a C version function will be defined with
- * the same name. We can know if the given function is used by looking
- * for existence of the C version funcion
+ * If a function is used by pgtle datatype APIs (i.e. create_base_type,
+ * set_base_type_binary_io or create_operator_func), a C version function
+ * will be defined with the same name. We can know if the given function is
+ * used by looking for existence of the C version funcion. Functions used by
+ * set_base_type_typmod_io are checked through pg_depend instead.
  */
 static bool
 is_pgtle_used_user_func(Oid funcid, bool *is_operator_func)
@@ -5187,8 +6302,23 @@ is_pgtle_used_user_func(Oid funcid, bool *is_operator_func)
 	proname = pstrdup(NameStr(proc->proname));
 	ReleaseSysCache(tuple);
 
-	/* nargs == 1, it could be an operator or I/O function */
-	if (nargs == 1)
+	/*
+	 * typmod_in and typmod_out functions are not shadowed by a C version
+	 * function with the same name, look them up through pg_depend instead.
+	 */
+	if (nargs == 1 &&
+		((argTypes[0] == TEXTARRAYOID && retType == INT4OID) ||
+		 (argTypes[0] == INT4OID && retType == TEXTOID)))
+		return is_pgtle_used_typmod_func(funcid, is_operator_func);
+
+	/* nargs == 1, it could be an operator, I/O or analyze function */
+	if (nargs == 1 && argTypes[0] == get_array_type(BYTEAOID))
+	{
+		/* argType is bytea[], it must be an analyze function and return real */
+		if (retType != FLOAT4OID)
+			return false;
+	}
+	else if (nargs == 1)
 	{
 		if (argTypes[0] != TEXTOID && argTypes[0] != BYTEAOID)
 			return false;
@@ -5216,6 +6346,53 @@ is_pgtle_used_user_func(Oid funcid, bool *is_operator_func)
 	return false;
 }
 
+/*
+ * is_pgtle_used_typmod_func
+ *
+ * Returns whether a given function is used by set_base_type_typmod_io API,
+ * i.e. whether a pg_tle defined C function depends on it.
+ */
+static bool
+is_pgtle_used_typmod_func(Oid funcid, bool *is_operator_func)
+{
+	Relation	depRel;
+	ScanKeyData key[2];
+	SysScanDesc depScan;
+	HeapTuple	depTup;
+	bool		result = false;
+
+	depRel = table_open(DependRelationId, AccessShareLock);
+
+	ScanKeyInit(&key[0],
+				Anum_pg_depend_refclassid,
+				BTEqualStrategyNumber, F_OIDEQ,
+				ObjectIdGetDatum(ProcedureRelationId));
+	ScanKeyInit(&key[1],
+				Anum_pg_depend_refobjid,
+				BTEqualStrategyNumber, F_OIDEQ,
+				ObjectIdGetDatum(funcid));
+
+	depScan = systable_beginscan(depRel, DependReferenceIndexId, true,
+								 NULL, 2, key);
+
+	while (HeapTupleIsValid(depTup = systable_getnext(depScan)))
+	{
+		Form_pg_depend pg_depend = (Form_pg_depend) GETSTRUCT(depTup);
+
+		if (pg_depend->classid == ProcedureRelationId &&
+			is_pgtle_defined_c_func(pg_depend->objid, is_operator_func))
+		{
+			result = true;
+			break;
+		}
+	}
+
+	systable_endscan(depScan);
+	table_close(depRel, AccessShareLock);
+
+	return result;
+}
+
 /*
  * check_pgtle_used_func
  *
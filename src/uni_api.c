@@ -18,6 +18,11 @@
 #include "tleextension.h"
 #include "passcheck.h"
 #include "clientauth.h"
+#include "customguc.h"
+#include "login.h"
+#include "ddlhook.h"
+#include "jobscheduler.h"
+#include "clusterextensions.h"
 #include "fmgr.h"
 
 PG_MODULE_MAGIC;
@@ -31,6 +36,11 @@ _PG_init(void)
 	pg_tle_init();
 	passcheck_init();
 	clientauth_init();
+	customguc_init();
+	login_init();
+	ddlhook_init();
+	jobscheduler_init();
+	clusterextensions_init();
 }
 
 void
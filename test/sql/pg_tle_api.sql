@@ -89,6 +89,8 @@ LANGUAGE PLPGSQL;
 
 SELECT pgtle.register_feature('password_check_length_greater_than_8', 'passcheck');
 SELECT pgtle.unregister_feature('test_validuntil', 'passcheck');
+-- registering and unregistering functions is recorded in the history
+SELECT action, name, feature FROM pgtle.extension_history WHERE action LIKE '%feature' ORDER BY id;
 -- Expect failure since pass is shorter than 8
 ALTER ROLE testuser with password 'pass';
 ALTER ROLE testuser with password 'passwords';
@@ -0,0 +1,81 @@
+/*
+*
+* Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+*/
+
+\pset pager off
+CREATE EXTENSION pg_tle;
+
+-- create semi-privileged role to manipulate pg_tle artifacts
+CREATE ROLE dbadmin;
+GRANT pgtle_admin TO dbadmin;
+
+-- create unprivileged role
+CREATE ROLE dbstaff;
+
+-- unprivileged role cannot define parameters
+SET SESSION AUTHORIZATION dbstaff;
+SELECT pgtle.define_custom_guc('test_guc.enabled', 'bool', 'on', 'Enables test_guc.');
+
+SET SESSION AUTHORIZATION dbadmin;
+-- pgtle.custom_guc cannot be modified directly
+INSERT INTO pgtle.custom_guc VALUES ('test_guc.enabled', 'bool', 'on', 'Enables test_guc.', 'user');
+-- Invalid: parameter names
+SELECT pgtle.define_custom_guc('enabled', 'bool', 'on', 'Enables test_guc.');
+SELECT pgtle.define_custom_guc('test_guc.1enabled', 'bool', 'on', 'Enables test_guc.');
+SELECT pgtle.define_custom_guc('pgtle.enabled', 'bool', 'on', 'Enables test_guc.');
+SELECT pgtle.define_custom_guc('PGTLE.enabled', 'bool', 'on', 'Enables test_guc.');
+SELECT pgtle.define_custom_guc('plpgsql.enabled', 'bool', 'on', 'Enables test_guc.');
+-- Invalid: type and context
+SELECT pgtle.define_custom_guc('test_guc.enabled', 'boolean', 'on', 'Enables test_guc.');
+SELECT pgtle.define_custom_guc('test_guc.enabled', 'bool', 'on', 'Enables test_guc.', 'postmaster');
+-- Invalid: default values
+SELECT pgtle.define_custom_guc('test_guc.enabled', 'bool', 'maybe', 'Enables test_guc.');
+SELECT pgtle.define_custom_guc('test_guc.max_items', 'integer', 'ten', 'Maximum number of items.');
+SELECT pgtle.define_custom_guc('test_guc.ratio', 'real', 'half', 'Ratio of items.');
+
+SELECT pgtle.define_custom_guc('test_guc.enabled', 'bool', 'on', 'Enables test_guc.');
+SELECT pgtle.define_custom_guc('test_guc.max_items', 'integer', '10', 'Maximum number of items.');
+SELECT pgtle.define_custom_guc('test_guc.ratio', 'real', '0.5', 'Ratio of items.', 'superuser');
+SELECT pgtle.define_custom_guc('test_guc.label', 'string', 'default', 'Label of items.');
+-- define_custom_guc fails if the parameter is already defined
+SELECT pgtle.define_custom_guc('test_guc.enabled', 'bool', 'off', 'Enables test_guc.');
+SELECT * FROM pgtle.custom_guc ORDER BY name;
+SELECT name, setting, vartype, context, short_desc FROM pg_catalog.pg_settings WHERE name LIKE 'test_guc.%' ORDER BY name;
+
+-- parameters registered by a rolled back transaction are not defined
+BEGIN;
+SELECT pgtle.define_custom_guc('test_guc.rolled_back', 'integer', '1', 'Rolled back.');
+ROLLBACK;
+SELECT count(*) FROM pg_catalog.pg_settings WHERE name = 'test_guc.rolled_back';
+SELECT count(*) FROM pgtle.custom_guc WHERE name = 'test_guc.rolled_back';
+
+-- parameters are validated by type
+SET test_guc.max_items = 20;
+SHOW test_guc.max_items;
+SET test_guc.max_items = 'twenty';
+SET test_guc.enabled = 'maybe';
+-- superuser parameters cannot be set by other roles
+SET test_guc.ratio = 0.75;
+
+-- parameters can be set per-role and per-database
+RESET SESSION AUTHORIZATION;
+ALTER ROLE dbstaff SET test_guc.max_items = 'twenty';
+ALTER ROLE dbstaff SET test_guc.max_items = 30;
+SELECT setconfig FROM pg_catalog.pg_db_role_setting WHERE setrole = 'dbstaff'::regrole;
+ALTER ROLE dbstaff RESET ALL;
+
+-- parameters already defined by a loaded module are skipped
+INSERT INTO pgtle.custom_guc VALUES ('plpgsql.variable_conflict', 'string', 'use_column', 'Conflicts with plpgsql.', 'user');
+DO $$ BEGIN END $$;
+SELECT pgtle.define_custom_guc('test_guc.other', 'integer', '1', 'Other items.');
+SHOW plpgsql.variable_conflict;
+DELETE FROM pgtle.custom_guc WHERE name = 'plpgsql.variable_conflict';
+
+-- clean up
+DROP ROLE dbstaff;
+DROP ROLE dbadmin;
+DROP EXTENSION pg_tle;
+DROP SCHEMA pgtle;
+DROP ROLE pgtle_admin;
@@ -818,9 +818,561 @@ SELECT c1, md5(CAST(c2 AS TEXT)), md5(repeat('0123456789', 500)) from test_dt;
 DROP TABLE test_dt;
 DROP TYPE test_toast CASCADE;
 
+-- Test default value and analyze function
+CREATE FUNCTION public.test_toast_analyze(sample bytea[]) RETURNS real AS
+$$
+  SELECT count(DISTINCT s)::real FROM pg_catalog.unnest(sample) s
+$$ IMMUTABLE STRICT LANGUAGE sql;
+
+SELECT pgtle.create_shell_type('public', 'test_toast');
+-- Analyze functions must accept a bytea[] and arguments other than default_value and analyzefunc cannot be NULL.
+SELECT pgtle.create_base_type('public', 'test_toast', 'test_toast_in(text)'::regprocedure, 'test_toast_out(bytea)'::regprocedure, -1, analyzefunc => 'test_toast_out(bytea)'::regprocedure);
+SELECT pgtle.create_base_type('public', 'test_toast', 'test_toast_in(text)'::regprocedure, 'test_toast_out(bytea)'::regprocedure, -1, storage => NULL);
+SELECT pgtle.create_base_type('public', 'test_toast', 'test_toast_in(text)'::regprocedure, 'test_toast_out(bytea)'::regprocedure, -1, default_value => '1', analyzefunc => 'test_toast_analyze(bytea[])'::regprocedure);
+CREATE TABLE test_dt(c1 int, c2 test_toast);
+INSERT INTO test_dt (c1) SELECT i FROM generate_series(1, 10) i;
+INSERT INTO test_dt SELECT i, CAST((i % 4)::text AS test_toast) FROM generate_series(11, 100) i;
+-- values wider than 1024 bytes are not passed to the analyze function
+INSERT INTO test_dt SELECT i, CAST(repeat('0123456789', 100) AS test_toast) FROM generate_series(101, 102) i;
+SELECT c1, c2 FROM test_dt WHERE c1 IN (1, 11) ORDER BY c1;
+ANALYZE test_dt;
+SELECT attname, n_distinct FROM pg_stats WHERE tablename = 'test_dt' ORDER BY attname;
+
+-- Invalid: REPLACE pgtle used type analyze function
+CREATE OR REPLACE FUNCTION public.test_toast_analyze(sample bytea[]) RETURNS real AS
+$$
+  SELECT -2::real
+$$ IMMUTABLE STRICT LANGUAGE sql;
+DROP TABLE test_dt;
+DROP TYPE test_toast CASCADE;
+
+-- Analyze functions must return a valid number of distinct values
+CREATE OR REPLACE FUNCTION public.test_toast_analyze(sample bytea[]) RETURNS real AS
+$$
+  SELECT -2::real
+$$ IMMUTABLE STRICT LANGUAGE sql;
+SELECT pgtle.create_shell_type('public', 'test_toast');
+SELECT pgtle.create_base_type('public', 'test_toast', 'test_toast_in(text)'::regprocedure, 'test_toast_out(bytea)'::regprocedure, -1, analyzefunc => 'test_toast_analyze(bytea[])'::regprocedure);
+CREATE TABLE test_dt(c1 int, c2 test_toast);
+INSERT INTO test_dt SELECT i, CAST((i % 4)::text AS test_toast) FROM generate_series(1, 100) i;
+ANALYZE test_dt;
+DROP TABLE test_dt;
+DROP TYPE test_toast CASCADE;
+DROP FUNCTION test_toast_analyze(bytea[]);
+
 DROP FUNCTION test_toast_in(text) CASCADE;
 DROP FUNCTION test_toast_out(bytea) CASCADE;
 
+-- Test enum types
+-- unprivileged role cannot execute pgtle.create_enum_type and add_enum_label
+SET SESSION AUTHORIZATION dbstaff;
+SELECT pgtle.create_enum_type('public', 'test_enum', ARRAY['small', 'large']);
+SELECT pgtle.add_enum_label('public', 'test_enum', 'medium');
+
+SET SESSION AUTHORIZATION dbadmin;
+SELECT pgtle.create_enum_type('public', 'test_enum', ARRAY['small', 'large']);
+-- create_enum_type fails if the type already exists
+SELECT pgtle.create_enum_type('public', 'test_enum', ARRAY['small', 'large']);
+-- create_enum_type_if_not_exists returns false if the type already exists
+SELECT pgtle.create_enum_type_if_not_exists('public', 'test_enum', ARRAY['small', 'large']);
+-- Invalid: NULL label
+SELECT pgtle.create_enum_type('public', 'test_enum2', ARRAY['small', NULL]);
+
+SELECT pgtle.add_enum_label('public', 'test_enum', 'medium', before_label => 'large');
+SELECT pgtle.add_enum_label('public', 'test_enum', 'huge', after_label => 'large');
+SELECT pgtle.add_enum_label('public', 'test_enum', 'tiny', before_label => 'small');
+-- add_enum_label fails if the label already exists
+SELECT pgtle.add_enum_label('public', 'test_enum', 'huge');
+-- add_enum_label_if_not_exists returns false if the label already exists
+SELECT pgtle.add_enum_label_if_not_exists('public', 'test_enum', 'huge');
+SELECT pgtle.add_enum_label_if_not_exists('public', 'test_enum', 'enormous');
+-- Invalid: both before_label and after_label
+SELECT pgtle.add_enum_label('public', 'test_enum', 'average', before_label => 'large', after_label => 'medium');
+-- Invalid: neighbor label does not exist
+SELECT pgtle.add_enum_label('public', 'test_enum', 'average', after_label => 'average_plus');
+-- Invalid: not an enum type
+SELECT pgtle.add_enum_label('pg_catalog', 'int4', 'average');
+-- Invalid: type does not exist
+SELECT pgtle.add_enum_label('public', 'test_enum2', 'average');
+-- not owner of the enum type
+SET SESSION AUTHORIZATION dbuser2;
+SELECT pgtle.add_enum_label('public', 'test_enum', 'average');
+
+SET SESSION AUTHORIZATION dbadmin;
+SELECT pg_catalog.enum_range(NULL::public.test_enum);
+CREATE TABLE test_dt(c1 public.test_enum);
+INSERT INTO test_dt VALUES ('large'), ('tiny'), ('medium');
+SELECT * FROM test_dt ORDER BY c1;
+DROP TABLE test_dt;
+DROP TYPE public.test_enum;
+
+-- Test range types
+CREATE FUNCTION public.test_int4_diff(a int4, b int4) RETURNS float8 AS
+$$
+  SELECT (a - b)::float8;
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_int4_diff_stable(a int4, b int4) RETURNS float8 AS
+$$
+  SELECT (a - b)::float8;
+$$ STABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_text_diff(a text, b text) RETURNS float8 AS
+$$
+  SELECT 0::float8;
+$$ IMMUTABLE STRICT LANGUAGE sql;
+
+-- unprivileged role cannot execute pgtle.create_range_type
+SET SESSION AUTHORIZATION dbstaff;
+SELECT pgtle.create_range_type('public', 'test_int4range', 'int4');
+
+SET SESSION AUTHORIZATION dbadmin;
+-- Invalid: subtype_diff argument type mismatch
+SELECT pgtle.create_range_type('public', 'test_int4range', 'int4', 'public.test_text_diff(text, text)'::regprocedure);
+-- Invalid: subtype_diff not immutable
+SELECT pgtle.create_range_type('public', 'test_int4range', 'int4', 'public.test_int4_diff_stable(int4, int4)'::regprocedure);
+-- Invalid: subtype_opclass does not exist
+SELECT pgtle.create_range_type('public', 'test_int4range', 'int4', subtype_opclass => 'public.no_such_ops');
+-- Invalid: pseudo-type subtype
+SELECT pgtle.create_range_type('public', 'test_anyrange', 'anyelement');
+SELECT pgtle.create_range_type('public', 'test_int4range', 'int4', 'public.test_int4_diff(int4, int4)'::regprocedure, 'int4_ops');
+-- create_range_type fails if the type already exists
+SELECT pgtle.create_range_type('public', 'test_int4range', 'int4');
+-- create_range_type_if_not_exists returns false if the type already exists
+SELECT pgtle.create_range_type_if_not_exists('public', 'test_int4range', 'int4');
+SELECT public.test_int4range(1, 10) @> 5;
+SELECT '[1,10]'::public.test_int4range;
+SELECT rngsubdiff::regprocedure FROM pg_catalog.pg_range WHERE rngtypid = 'public.test_int4range'::regtype;
+DROP TYPE public.test_int4range;
+DROP FUNCTION public.test_int4_diff;
+DROP FUNCTION public.test_int4_diff_stable;
+DROP FUNCTION public.test_text_diff;
+
+-- Test operators and operator classes
+SELECT pgtle.create_shell_type('public', 'test_optext');
+CREATE FUNCTION public.test_optext_in(input text) RETURNS bytea AS
+$$
+  SELECT pg_catalog.convert_to(input, 'UTF8');
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_optext_out(input bytea) RETURNS text AS
+$$
+  SELECT pg_catalog.convert_from(input, 'UTF8');
+$$ IMMUTABLE STRICT LANGUAGE sql;
+SELECT pgtle.create_base_type('public', 'test_optext', 'test_optext_in(text)'::regprocedure, 'test_optext_out(bytea)'::regprocedure, -1);
+
+CREATE FUNCTION public.test_optext_cmp(l bytea, r bytea) RETURNS int AS
+$$
+  SELECT pg_catalog.byteacmp(l, r);
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_optext_lt(l bytea, r bytea) RETURNS boolean AS
+$$
+  SELECT l < r;
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_optext_le(l bytea, r bytea) RETURNS boolean AS
+$$
+  SELECT l <= r;
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_optext_eq(l bytea, r bytea) RETURNS boolean AS
+$$
+  SELECT l = r;
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_optext_ge(l bytea, r bytea) RETURNS boolean AS
+$$
+  SELECT l >= r;
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_optext_gt(l bytea, r bytea) RETURNS boolean AS
+$$
+  SELECT l > r;
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_optext_hash(v bytea) RETURNS int AS
+$$
+  SELECT pg_catalog.hashtext(pg_catalog.encode(v, 'hex'));
+$$ IMMUTABLE STRICT LANGUAGE sql;
+SELECT pgtle.create_operator_func('public', 'test_optext', 'public.test_optext_cmp(bytea, bytea)'::regprocedure);
+SELECT pgtle.create_operator_func('public', 'test_optext', 'public.test_optext_lt(bytea, bytea)'::regprocedure);
+SELECT pgtle.create_operator_func('public', 'test_optext', 'public.test_optext_le(bytea, bytea)'::regprocedure);
+SELECT pgtle.create_operator_func('public', 'test_optext', 'public.test_optext_eq(bytea, bytea)'::regprocedure);
+SELECT pgtle.create_operator_func('public', 'test_optext', 'public.test_optext_ge(bytea, bytea)'::regprocedure);
+SELECT pgtle.create_operator_func('public', 'test_optext', 'public.test_optext_gt(bytea, bytea)'::regprocedure);
+SELECT pgtle.create_operator_func('public', 'test_optext', 'public.test_optext_hash(bytea)'::regprocedure);
+
+-- unprivileged role cannot execute pgtle.create_operator and create_operator_class
+SET SESSION AUTHORIZATION dbstaff;
+SELECT pgtle.create_operator('public', '<', 'test_optext', 'test_optext', 'public.test_optext_lt(test_optext, test_optext)'::regprocedure);
+SELECT pgtle.create_operator_class('public', 'test_optext_ops', 'test_optext', 'btree', ARRAY[]::regoperator[], ARRAY[]::regprocedure[]);
+
+SET SESSION AUTHORIZATION dbadmin;
+-- Invalid: operator function argument type mismatch
+SELECT pgtle.create_operator('public', '<', 'test_optext', 'test_optext', 'public.test_optext_lt(bytea, bytea)'::regprocedure);
+SELECT pgtle.create_operator('public', '<', 'test_optext', 'test_optext', 'public.test_optext_lt(test_optext, test_optext)'::regprocedure,
+  commutator => '>', negator => '>=', restrict_func => 'scalarltsel', join_func => 'scalarltjoinsel');
+SELECT pgtle.create_operator('public', '<=', 'test_optext', 'test_optext', 'public.test_optext_le(test_optext, test_optext)'::regprocedure,
+  commutator => '>=', negator => '>', restrict_func => 'scalarlesel', join_func => 'scalarlejoinsel');
+SELECT pgtle.create_operator('public', '=', 'test_optext', 'test_optext', 'public.test_optext_eq(test_optext, test_optext)'::regprocedure,
+  commutator => '=', restrict_func => 'eqsel', join_func => 'eqjoinsel', hashes => true, merges => true);
+SELECT pgtle.create_operator('public', '>=', 'test_optext', 'test_optext', 'public.test_optext_ge(test_optext, test_optext)'::regprocedure,
+  commutator => '<=', negator => '<', restrict_func => 'scalargesel', join_func => 'scalargejoinsel');
+SELECT pgtle.create_operator('public', '>', 'test_optext', 'test_optext', 'public.test_optext_gt(test_optext, test_optext)'::regprocedure,
+  commutator => '<', negator => '<=', restrict_func => 'scalargtsel', join_func => 'scalargtjoinsel');
+-- create_operator fails if the operator already exists
+SELECT pgtle.create_operator('public', '<', 'test_optext', 'test_optext', 'public.test_optext_lt(test_optext, test_optext)'::regprocedure);
+
+-- Invalid: unsupported access method
+SELECT pgtle.create_operator_class('public', 'test_optext_ops', 'test_optext', 'gist', ARRAY[]::regoperator[], ARRAY[]::regprocedure[]);
+-- Invalid: wrong number of operators
+SELECT pgtle.create_operator_class('public', 'test_optext_ops', 'test_optext', 'btree',
+  ARRAY['public.=(test_optext, test_optext)']::regoperator[],
+  ARRAY['public.test_optext_cmp(test_optext, test_optext)']::regprocedure[]);
+-- Invalid: support function argument type mismatch
+SELECT pgtle.create_operator_class('public', 'test_optext_ops', 'test_optext', 'btree',
+  ARRAY['public.<(test_optext, test_optext)', 'public.<=(test_optext, test_optext)', 'public.=(test_optext, test_optext)',
+        'public.>=(test_optext, test_optext)', 'public.>(test_optext, test_optext)']::regoperator[],
+  ARRAY['public.test_optext_cmp(bytea, bytea)']::regprocedure[]);
+-- not owner of the type
+SET SESSION AUTHORIZATION dbuser2;
+SELECT pgtle.create_operator_class('public', 'test_optext_ops', 'test_optext', 'btree',
+  ARRAY['public.<(test_optext, test_optext)', 'public.<=(test_optext, test_optext)', 'public.=(test_optext, test_optext)',
+        'public.>=(test_optext, test_optext)', 'public.>(test_optext, test_optext)']::regoperator[],
+  ARRAY['public.test_optext_cmp(test_optext, test_optext)']::regprocedure[]);
+-- not owner of the operators
+CREATE FUNCTION public.test_optext_ne(l test_optext, r test_optext) RETURNS boolean AS
+$$
+  SELECT l::bytea <> r::bytea;
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE OPERATOR public.<> (LEFTARG = test_optext, RIGHTARG = test_optext, FUNCTION = public.test_optext_ne);
+SET SESSION AUTHORIZATION dbadmin;
+SELECT pgtle.create_operator_class('public', 'test_optext_ops', 'test_optext', 'btree',
+  ARRAY['public.<(test_optext, test_optext)', 'public.<=(test_optext, test_optext)', 'public.<>(test_optext, test_optext)',
+        'public.>=(test_optext, test_optext)', 'public.>(test_optext, test_optext)']::regoperator[],
+  ARRAY['public.test_optext_cmp(test_optext, test_optext)']::regprocedure[]);
+SET SESSION AUTHORIZATION dbuser2;
+DROP OPERATOR public.<> (test_optext, test_optext);
+DROP FUNCTION public.test_optext_ne(test_optext, test_optext);
+-- Invalid: support function defined in internal, even though it is owned by the caller
+RESET SESSION AUTHORIZATION;
+CREATE FUNCTION public.test_optext_internal_cmp(test_optext, test_optext) RETURNS int AS 'byteacmp' LANGUAGE internal IMMUTABLE STRICT;
+ALTER FUNCTION public.test_optext_internal_cmp(test_optext, test_optext) OWNER TO dbadmin;
+SET SESSION AUTHORIZATION dbadmin;
+SELECT pgtle.create_operator_class('public', 'test_optext_ops', 'test_optext', 'btree',
+  ARRAY['public.<(test_optext, test_optext)', 'public.<=(test_optext, test_optext)', 'public.=(test_optext, test_optext)',
+        'public.>=(test_optext, test_optext)', 'public.>(test_optext, test_optext)']::regoperator[],
+  ARRAY['public.test_optext_internal_cmp(test_optext, test_optext)']::regprocedure[]);
+DROP FUNCTION public.test_optext_internal_cmp(test_optext, test_optext);
+
+SELECT pgtle.create_operator_class('public', 'test_optext_ops', 'test_optext', 'btree',
+  ARRAY['public.<(test_optext, test_optext)', 'public.<=(test_optext, test_optext)', 'public.=(test_optext, test_optext)',
+        'public.>=(test_optext, test_optext)', 'public.>(test_optext, test_optext)']::regoperator[],
+  ARRAY['public.test_optext_cmp(test_optext, test_optext)']::regprocedure[]);
+SELECT pgtle.create_operator_class('public', 'test_optext_hash_ops', 'test_optext', 'hash',
+  ARRAY['public.=(test_optext, test_optext)']::regoperator[],
+  ARRAY['public.test_optext_hash(test_optext)']::regprocedure[]);
+-- create_operator_class fails if the operator class already exists
+SELECT pgtle.create_operator_class('public', 'test_optext_hash_ops', 'test_optext', 'hash',
+  ARRAY['public.=(test_optext, test_optext)']::regoperator[],
+  ARRAY['public.test_optext_hash(test_optext)']::regprocedure[]);
+-- create_operator_class fails if the type already has a default operator class for the method
+SELECT pgtle.create_operator_class('public', 'test_optext_hash_ops2', 'test_optext', 'hash',
+  ARRAY['public.=(test_optext, test_optext)']::regoperator[],
+  ARRAY['public.test_optext_hash(test_optext)']::regprocedure[]);
+-- The operator classes and families are owned by the caller
+SELECT c.opcname, a.amname, c.opcowner::regrole, c.opcdefault FROM pg_catalog.pg_opclass c
+  JOIN pg_catalog.pg_am a ON a.oid = c.opcmethod WHERE c.opcname LIKE 'test_optext%' ORDER BY c.opcname;
+SELECT f.opfname, a.amname, f.opfowner::regrole FROM pg_catalog.pg_opfamily f
+  JOIN pg_catalog.pg_am a ON a.oid = f.opfmethod WHERE f.opfname LIKE 'test_optext%' ORDER BY f.opfname;
+
+-- Regular user can index, sort and deduplicate the type
+SET SESSION AUTHORIZATION dbstaff;
+CREATE TABLE public.test_dt(c1 test_optext PRIMARY KEY);
+CREATE INDEX test_dt_hash_idx ON public.test_dt USING hash (c1);
+INSERT INTO test_dt VALUES ('banana'), ('apple'), ('cherry');
+INSERT INTO test_dt VALUES ('apple');
+SELECT * FROM test_dt ORDER BY c1;
+SELECT DISTINCT c1 FROM (SELECT c1 FROM test_dt UNION ALL SELECT c1 FROM test_dt) t ORDER BY c1;
+SELECT c1 FROM test_dt WHERE c1 = 'apple';
+DROP TABLE test_dt;
+
+SET SESSION AUTHORIZATION dbadmin;
+DROP OPERATOR FAMILY public.test_optext_ops USING btree;
+DROP OPERATOR FAMILY public.test_optext_hash_ops USING hash;
+DROP OPERATOR public.< (test_optext, test_optext), public.<= (test_optext, test_optext), public.= (test_optext, test_optext),
+  public.>= (test_optext, test_optext), public.> (test_optext, test_optext);
+DROP FUNCTION test_optext_cmp(test_optext, test_optext), test_optext_lt(test_optext, test_optext),
+  test_optext_le(test_optext, test_optext), test_optext_eq(test_optext, test_optext),
+  test_optext_ge(test_optext, test_optext), test_optext_gt(test_optext, test_optext), test_optext_hash(test_optext);
+DROP FUNCTION test_optext_cmp(bytea, bytea), test_optext_lt(bytea, bytea), test_optext_le(bytea, bytea),
+  test_optext_eq(bytea, bytea), test_optext_ge(bytea, bytea), test_optext_gt(bytea, bytea), test_optext_hash(bytea);
+DROP FUNCTION test_optext_in(text) CASCADE;
+DROP FUNCTION test_optext_out(bytea) CASCADE;
+
+-- Test casts
+SELECT pgtle.create_shell_type('public', 'test_casttext');
+CREATE FUNCTION public.test_casttext_in(input text) RETURNS bytea AS
+$$
+  SELECT pg_catalog.convert_to(input, 'UTF8');
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_casttext_out(input bytea) RETURNS text AS
+$$
+  SELECT pg_catalog.convert_from(input, 'UTF8');
+$$ IMMUTABLE STRICT LANGUAGE sql;
+SELECT pgtle.create_base_type('public', 'test_casttext', 'test_casttext_in(text)'::regprocedure, 'test_casttext_out(bytea)'::regprocedure, -1);
+
+CREATE FUNCTION public.test_casttext_to_text(v test_casttext) RETURNS text AS
+$$
+  SELECT pg_catalog.upper(pg_catalog.convert_from(v::bytea, 'UTF8'));
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.text_to_test_casttext(v text) RETURNS test_casttext AS
+$$
+  SELECT public.test_casttext_in(pg_catalog.lower(v)::cstring);
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_casttext_length(v bytea) RETURNS int AS
+$$
+  SELECT pg_catalog.length(v);
+$$ IMMUTABLE STRICT LANGUAGE sql;
+SELECT pgtle.create_operator_func('public', 'test_casttext', 'public.test_casttext_length(bytea)'::regprocedure);
+
+-- unprivileged role cannot execute pgtle.create_cast
+SET SESSION AUTHORIZATION dbstaff;
+SELECT pgtle.create_cast('test_casttext', 'text', 'public.test_casttext_to_text(test_casttext)'::regprocedure);
+
+SET SESSION AUTHORIZATION dbadmin;
+-- Invalid: cast context
+SELECT pgtle.create_cast('test_casttext', 'text', 'public.test_casttext_to_text(test_casttext)'::regprocedure, 'sometimes');
+-- Invalid: neither type is a pg_tle base type
+SELECT pgtle.create_cast('int4', 'text', 'public.test_casttext_to_text(test_casttext)'::regprocedure);
+-- Invalid: not owner of the cast function
+SELECT pgtle.create_cast('test_casttext', 'int4', 'pg_catalog.length(bytea)'::regprocedure);
+-- Invalid: cast function argument type mismatch
+SELECT pgtle.create_cast('text', 'test_casttext', 'public.test_casttext_to_text(test_casttext)'::regprocedure);
+-- not owner of the type
+SET SESSION AUTHORIZATION dbuser2;
+SELECT pgtle.create_cast('test_casttext', 'text', 'public.test_casttext_to_text(test_casttext)'::regprocedure);
+
+SET SESSION AUTHORIZATION dbadmin;
+SELECT pgtle.create_cast('test_casttext', 'text', 'public.test_casttext_to_text(test_casttext)'::regprocedure, 'assignment');
+SELECT pgtle.create_cast('text', 'test_casttext', 'public.text_to_test_casttext(text)'::regprocedure, 'implicit');
+SELECT pgtle.create_cast('test_casttext', 'int4', 'public.test_casttext_length(test_casttext)'::regprocedure);
+-- create_cast fails if the cast already exists
+SELECT pgtle.create_cast('test_casttext', 'int4', 'public.test_casttext_length(test_casttext)'::regprocedure);
+-- create_cast_if_not_exists returns false if the cast already exists
+SELECT pgtle.create_cast_if_not_exists('test_casttext', 'int4', 'public.test_casttext_length(test_casttext)'::regprocedure);
+SELECT castsource::regtype, casttarget::regtype, castfunc::regprocedure, castcontext FROM pg_catalog.pg_cast
+  WHERE castsource = 'public.test_casttext'::regtype OR casttarget = 'public.test_casttext'::regtype
+  ORDER BY castsource::regtype::text, casttarget::regtype::text;
+
+CREATE TABLE test_dt(c1 test_casttext, c2 text);
+-- Implicit cast from text, assignment cast to text
+INSERT INTO test_dt VALUES ('Hello'::text, 'World'::test_casttext);
+SELECT c1, c2, CAST(c1 AS int4) FROM test_dt;
+DROP TABLE test_dt;
+
+DROP CAST (test_casttext AS text);
+DROP CAST (text AS test_casttext);
+DROP CAST (test_casttext AS int4);
+DROP FUNCTION public.test_casttext_to_text(test_casttext);
+DROP FUNCTION public.text_to_test_casttext(text);
+DROP FUNCTION public.test_casttext_length(test_casttext);
+DROP FUNCTION public.test_casttext_length(bytea);
+DROP FUNCTION test_casttext_in(text) CASCADE;
+DROP FUNCTION test_casttext_out(bytea) CASCADE;
+
+-- Test binary receive and send functions
+SELECT pgtle.create_shell_type('public', 'test_bintext');
+CREATE FUNCTION public.test_bintext_in(input text) RETURNS bytea AS
+$$
+  SELECT pg_catalog.convert_to(input, 'UTF8');
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_bintext_out(input bytea) RETURNS text AS
+$$
+  SELECT pg_catalog.convert_from(input, 'UTF8');
+$$ IMMUTABLE STRICT LANGUAGE sql;
+SELECT pgtle.create_base_type('public', 'test_bintext', 'test_bintext_in(text)'::regprocedure, 'test_bintext_out(bytea)'::regprocedure, -1);
+
+-- The wire format is upper case, the internal representation is lower case
+CREATE FUNCTION public.test_bintext_recv(input bytea) RETURNS bytea AS
+$$
+  SELECT pg_catalog.convert_to(pg_catalog.lower(pg_catalog.convert_from(input, 'UTF8')), 'UTF8');
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_bintext_send(input bytea) RETURNS bytea AS
+$$
+  SELECT pg_catalog.convert_to(pg_catalog.upper(pg_catalog.convert_from(input, 'UTF8')), 'UTF8');
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_bintext_send_stable(input bytea) RETURNS bytea AS
+$$
+  SELECT input;
+$$ STABLE STRICT LANGUAGE sql;
+
+-- unprivileged role cannot execute pgtle.set_base_type_binary_io
+SET SESSION AUTHORIZATION dbstaff;
+SELECT pgtle.set_base_type_binary_io('public', 'test_bintext', 'public.test_bintext_recv(bytea)'::regprocedure, 'public.test_bintext_send(bytea)'::regprocedure);
+
+SET SESSION AUTHORIZATION dbadmin;
+-- Invalid: wrong argument type
+SELECT pgtle.set_base_type_binary_io('public', 'test_bintext', 'public.test_bintext_in(text)'::regprocedure, 'public.test_bintext_send(bytea)'::regprocedure);
+-- Invalid: wrong return type
+SELECT pgtle.set_base_type_binary_io('public', 'test_bintext', 'public.test_bintext_recv(bytea)'::regprocedure, 'public.test_bintext_out(bytea)'::regprocedure);
+-- Invalid: not immutable
+SELECT pgtle.set_base_type_binary_io('public', 'test_bintext', 'public.test_bintext_recv(bytea)'::regprocedure, 'public.test_bintext_send_stable(bytea)'::regprocedure);
+-- Invalid: type does not exist
+SELECT pgtle.set_base_type_binary_io('public', 'test_bintext2', 'public.test_bintext_recv(bytea)'::regprocedure, 'public.test_bintext_send(bytea)'::regprocedure);
+-- not owner of the type
+SET SESSION AUTHORIZATION dbuser2;
+SELECT pgtle.set_base_type_binary_io('public', 'test_bintext', 'public.test_bintext_recv(bytea)'::regprocedure, 'public.test_bintext_send(bytea)'::regprocedure);
+
+SET SESSION AUTHORIZATION dbadmin;
+SELECT pgtle.set_base_type_binary_io('public', 'test_bintext', 'public.test_bintext_recv(bytea)'::regprocedure, 'public.test_bintext_send(bytea)'::regprocedure);
+-- set_base_type_binary_io fails if the type already has receive and send functions
+SELECT pgtle.set_base_type_binary_io('public', 'test_bintext', 'public.test_bintext_recv(bytea)'::regprocedure, 'public.test_bintext_send(bytea)'::regprocedure);
+SELECT typreceive::regprocedure, typsend::regprocedure FROM pg_catalog.pg_type WHERE oid = 'public.test_bintext'::regtype;
+-- Invalid: REPLACE pgtle used type receive and send functions
+CREATE OR REPLACE FUNCTION public.test_bintext_send(input bytea) RETURNS bytea AS
+$$
+  SELECT input;
+$$ IMMUTABLE STRICT LANGUAGE sql;
+
+CREATE TABLE test_dt(c1 test_bintext, c2 test_bintext[]);
+INSERT INTO test_dt VALUES ('Hello', ARRAY['World'::test_bintext]);
+SELECT public.test_bintext_send(c1) FROM test_dt;
+-- Round trip through the binary COPY format
+RESET SESSION AUTHORIZATION;
+COPY test_dt TO 'pg_tle_test_bintext.data' WITH (FORMAT binary);
+TRUNCATE test_dt;
+COPY test_dt FROM 'pg_tle_test_bintext.data' WITH (FORMAT binary);
+SELECT * FROM test_dt;
+DROP TABLE test_dt;
+
+SET SESSION AUTHORIZATION dbadmin;
+DROP TYPE test_bintext CASCADE;
+DROP FUNCTION test_bintext_in(text), test_bintext_out(bytea), test_bintext_recv(bytea),
+  test_bintext_send(bytea), test_bintext_send_stable(bytea);
+
+-- Test type modifier input and output functions
+SELECT pgtle.create_shell_type('public', 'test_vartext');
+CREATE FUNCTION public.test_vartext_in(input text) RETURNS bytea AS
+$$
+  SELECT pg_catalog.convert_to(input, 'UTF8');
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_vartext_out(input bytea) RETURNS text AS
+$$
+  SELECT pg_catalog.convert_from(input, 'UTF8');
+$$ IMMUTABLE STRICT LANGUAGE sql;
+SELECT pgtle.create_base_type('public', 'test_vartext', 'test_vartext_in(text)'::regprocedure, 'test_vartext_out(bytea)'::regprocedure, -1);
+
+-- The type modifier is the maximum length, returns -1 for invalid modifiers
+CREATE FUNCTION public.test_vartext_mod_in(mods text[]) RETURNS int AS
+$$
+  SELECT CASE WHEN pg_catalog.array_length(mods, 1) = 1 THEN mods[1]::int ELSE -1 END;
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_vartext_mod_out(typmod int) RETURNS text AS
+$$
+  SELECT '(' || typmod || ')';
+$$ IMMUTABLE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_vartext_mod_out_volatile(typmod int) RETURNS text AS
+$$
+  SELECT '(' || typmod || ')';
+$$ VOLATILE STRICT LANGUAGE sql;
+CREATE FUNCTION public.test_vartext_typmod_out(typmod int) RETURNS text AS
+$$
+  SELECT '(' || typmod || ')';
+$$ IMMUTABLE STRICT LANGUAGE sql;
+
+-- unprivileged role cannot execute pgtle.set_base_type_typmod_io
+SET SESSION AUTHORIZATION dbstaff;
+SELECT pgtle.set_base_type_typmod_io('public', 'test_vartext', 'public.test_vartext_mod_in(text[])'::regprocedure, 'public.test_vartext_mod_out(int)'::regprocedure);
+
+SET SESSION AUTHORIZATION dbadmin;
+-- Invalid: wrong argument type
+SELECT pgtle.set_base_type_typmod_io('public', 'test_vartext', 'public.test_vartext_in(text)'::regprocedure, 'public.test_vartext_mod_out(int)'::regprocedure);
+SELECT pgtle.set_base_type_typmod_io('public', 'test_vartext', 'public.test_vartext_mod_in(text[])'::regprocedure, 'public.test_vartext_mod_in(text[])'::regprocedure);
+-- Invalid: not immutable
+SELECT pgtle.set_base_type_typmod_io('public', 'test_vartext', 'public.test_vartext_mod_in(text[])'::regprocedure, 'public.test_vartext_mod_out_volatile(int)'::regprocedure);
+-- Invalid: type does not exist
+SELECT pgtle.set_base_type_typmod_io('public', 'test_vartext2', 'public.test_vartext_mod_in(text[])'::regprocedure, 'public.test_vartext_mod_out(int)'::regprocedure);
+-- Invalid: the C version typmod_out function would clash with the user-defined function
+SELECT pgtle.set_base_type_typmod_io('public', 'test_vartext', 'public.test_vartext_mod_in(text[])'::regprocedure, 'public.test_vartext_typmod_out(int)'::regprocedure);
+DROP FUNCTION public.test_vartext_typmod_out(int);
+-- not owner of the type
+SET SESSION AUTHORIZATION dbuser2;
+SELECT pgtle.set_base_type_typmod_io('public', 'test_vartext', 'public.test_vartext_mod_in(text[])'::regprocedure, 'public.test_vartext_mod_out(int)'::regprocedure);
+
+SET SESSION AUTHORIZATION dbadmin;
+SELECT pgtle.set_base_type_typmod_io('public', 'test_vartext', 'public.test_vartext_mod_in(text[])'::regprocedure, 'public.test_vartext_mod_out(int)'::regprocedure);
+-- set_base_type_typmod_io fails if the type already has typmod_in and typmod_out functions
+SELECT pgtle.set_base_type_typmod_io('public', 'test_vartext', 'public.test_vartext_mod_in(text[])'::regprocedure, 'public.test_vartext_mod_out(int)'::regprocedure);
+SELECT typmodin::regprocedure, typmodout::regprocedure FROM pg_catalog.pg_type WHERE oid = 'public.test_vartext'::regtype;
+-- Invalid: REPLACE pgtle used typmod_in and typmod_out functions
+CREATE OR REPLACE FUNCTION public.test_vartext_mod_out(typmod int) RETURNS text AS
+$$
+  SELECT '[' || typmod || ']';
+$$ IMMUTABLE STRICT LANGUAGE sql;
+
+-- Length coercion cast that truncates values to the declared maximum length
+CREATE FUNCTION public.test_vartext_limit(v test_vartext, typmod int, is_explicit boolean) RETURNS test_vartext AS
+$$
+  SELECT public.test_vartext_in(pg_catalog.substr(pg_catalog.convert_from(v::bytea, 'UTF8'), 1, typmod)::cstring);
+$$ IMMUTABLE STRICT LANGUAGE sql;
+SELECT pgtle.create_cast('test_vartext', 'test_vartext', 'public.test_vartext_limit(test_vartext,int,boolean)'::regprocedure, 'implicit');
+
+CREATE TABLE test_dt(c1 test_vartext(3));
+SELECT pg_catalog.format_type(atttypid, atttypmod) FROM pg_catalog.pg_attribute WHERE attrelid = 'test_dt'::regclass AND attname = 'c1';
+INSERT INTO test_dt VALUES ('Hello');
+SELECT * FROM test_dt;
+SELECT 'World'::test_vartext(2);
+-- Invalid: type modifier rejected by the typmod_in function
+CREATE TABLE test_dt2(c1 test_vartext(1, 2));
+DROP TABLE test_dt;
+DROP CAST (test_vartext AS test_vartext);
+DROP FUNCTION test_vartext_limit(test_vartext,int,boolean);
+
+DROP TYPE test_vartext CASCADE;
+DROP FUNCTION test_vartext_in(text), test_vartext_out(bytea), test_vartext_mod_in(text[]),
+  test_vartext_mod_out(int), test_vartext_mod_out_volatile(int);
+
+-- Test aggregates
+CREATE FUNCTION public.test_avg_sfunc(state bigint[], v int) RETURNS bigint[] AS
+$$
+  SELECT ARRAY[state[1] + v, state[2] + 1];
+$$ IMMUTABLE STRICT PARALLEL SAFE LANGUAGE sql;
+CREATE FUNCTION public.test_avg_final(state bigint[]) RETURNS numeric AS
+$$
+  SELECT CASE WHEN state[2] = 0 THEN NULL ELSE state[1]::numeric / state[2] END;
+$$ IMMUTABLE STRICT PARALLEL SAFE LANGUAGE sql;
+CREATE FUNCTION public.test_avg_combine(state1 bigint[], state2 bigint[]) RETURNS bigint[] AS
+$$
+  SELECT ARRAY[state1[1] + state2[1], state1[2] + state2[2]];
+$$ IMMUTABLE STRICT PARALLEL SAFE LANGUAGE sql;
+
+-- unprivileged role cannot execute pgtle.create_aggregate
+SET SESSION AUTHORIZATION dbstaff;
+SELECT pgtle.create_aggregate('public', 'test_avg', '{int}', 'public.test_avg_sfunc(bigint[],int)'::regprocedure, 'bigint[]');
+
+SET SESSION AUTHORIZATION dbadmin;
+-- Invalid: NULL arguments
+SELECT pgtle.create_aggregate('public', 'test_avg', '{int}', NULL, 'bigint[]');
+-- Invalid: transition function does not match the state and argument types
+SELECT pgtle.create_aggregate('public', 'test_avg', '{bigint}', 'public.test_avg_sfunc(bigint[],int)'::regprocedure, 'bigint[]');
+SELECT pgtle.create_aggregate('public', 'test_avg', '{int}', 'public.test_avg_sfunc(bigint[],int)'::regprocedure, 'int[]');
+-- Invalid: final function does not accept the state type
+SELECT pgtle.create_aggregate('public', 'test_avg', '{int}', 'public.test_avg_sfunc(bigint[],int)'::regprocedure, 'bigint[]',
+  finalfunc => 'public.test_avg_sfunc(bigint[],int)'::regprocedure);
+-- Invalid: combine function does not accept the state type
+SELECT pgtle.create_aggregate('public', 'test_avg', '{int}', 'public.test_avg_sfunc(bigint[],int)'::regprocedure, 'bigint[]',
+  combinefunc => 'public.test_avg_sfunc(bigint[],int)'::regprocedure);
+-- Invalid: not owner of the transition function
+SELECT pgtle.create_aggregate('public', 'test_sum', '{int}', 'pg_catalog.int4pl(int,int)'::regprocedure, 'int');
+-- Invalid: parallel option
+SELECT pgtle.create_aggregate('public', 'test_avg', '{int}', 'public.test_avg_sfunc(bigint[],int)'::regprocedure, 'bigint[]',
+  parallel => 'maybe');
+
+SELECT pgtle.create_aggregate('public', 'test_avg', '{int}', 'public.test_avg_sfunc(bigint[],int)'::regprocedure, 'bigint[]',
+  'public.test_avg_final(bigint[])'::regprocedure, 'public.test_avg_combine(bigint[],bigint[])'::regprocedure, '{0,0}', 'safe');
+-- create_aggregate fails if the aggregate already exists
+SELECT pgtle.create_aggregate('public', 'test_avg', '{int}', 'public.test_avg_sfunc(bigint[],int)'::regprocedure, 'bigint[]',
+  'public.test_avg_final(bigint[])'::regprocedure, 'public.test_avg_combine(bigint[],bigint[])'::regprocedure, '{0,0}', 'safe');
+SELECT aggtransfn::regprocedure, aggfinalfn::regprocedure, aggcombinefn::regprocedure, agginitval, proparallel FROM pg_catalog.pg_aggregate JOIN pg_catalog.pg_proc ON aggfnoid = pg_proc.oid WHERE aggfnoid = 'public.test_avg(int)'::regprocedure;
+SELECT public.test_avg(x) FROM generate_series(1, 10) x;
+SELECT public.test_avg(x) FROM generate_series(1, 0) x;
+
+DROP AGGREGATE public.test_avg(int);
+DROP FUNCTION public.test_avg_sfunc(bigint[], int), public.test_avg_final(bigint[]), public.test_avg_combine(bigint[], bigint[]);
+
 -- clean up
 RESET SESSION AUTHORIZATION;
 REVOKE CREATE, USAGE ON SCHEMA PUBLIC FROM dbadmin;
@@ -139,8 +139,13 @@ SELECT CURRENT_USER;
 ALTER EXTENSION test123 UPDATE TO '1.1';
 SELECT test123_func_2();
 SELECT * FROM pgtle.extension_update_paths('test123');
-SELECT * FROM pgtle.available_extensions() ORDER BY name;
-SELECT * FROM pgtle.available_extension_versions() ORDER BY name;
+SELECT name, default_version, comment, superuser, trusted, relocatable, schema, requires,
+  installed_at IS NOT NULL AS has_installed_at
+FROM pgtle.available_extensions() ORDER BY name;
+-- only versions with an install script have an installation timestamp
+SELECT name, version, superuser, trusted, relocatable, schema, requires, comment,
+  installed_at IS NOT NULL AS has_installed_at
+FROM pgtle.available_extension_versions() ORDER BY name, version;
 DROP EXTENSION test123;
 
 -- negative tests, run as unprivileged role
@@ -230,7 +235,7 @@ $_pgtle_$
 );
 
 -- check available extension versions -- should be 1.0 and 1.1
-SELECT *
+SELECT x.name, x.version, x.superuser, x.trusted, x.relocatable, x.schema, x.requires, x.comment
 FROM pgtle.available_extension_versions() x WHERE x.name = 'new_ext';
 
 -- check avaialble version update paths -- should be 1.0<=>1.1
@@ -241,7 +246,7 @@ ORDER BY x.source;
 SELECT pgtle.uninstall_extension('new_ext', '1.1');
 
 -- check avaialble versions, should only be 1.0
-SELECT *
+SELECT x.name, x.version, x.superuser, x.trusted, x.relocatable, x.schema, x.requires, x.comment
 FROM pgtle.available_extension_versions() x WHERE x.name = 'new_ext';
 
 -- check avaialble version update paths -- should be none
@@ -352,13 +357,15 @@ $_pgtle_$
 );
 
 -- test the default version, should be 1.0
-SELECT * FROM pgtle.available_extensions() x WHERE x.name = 'new_ext';
+SELECT x.name, x.default_version, x.comment
+FROM pgtle.available_extensions() x WHERE x.name = 'new_ext';
 
 -- set the new default
 SELECT pgtle.set_default_version('new_ext', '1.1');
 
 -- test the default version, should be 1.1
-SELECT * FROM pgtle.available_extensions() x WHERE x.name = 'new_ext';
+SELECT x.name, x.default_version, x.comment
+FROM pgtle.available_extensions() x WHERE x.name = 'new_ext';
 
 -- try setting a default version that does not exist
 -- fail
@@ -444,7 +451,7 @@ $_pgtle_$
 $_pgtle_$
 );
 
-SELECT pgtle.available_extension_versions();
+SELECT name, version FROM pgtle.available_extension_versions() ORDER BY version;
 
 SELECT pgtle.install_extension_version_sql
 (
@@ -459,7 +466,7 @@ $_pgtle_$
 $_pgtle_$
 );
 
-SELECT pgtle.available_extension_versions();
+SELECT name, version FROM pgtle.available_extension_versions() ORDER BY version;
 
 -- install an already installed version
 -- fails
@@ -476,26 +483,26 @@ $_pgtle_$
 $_pgtle_$
 );
 
-SELECT pgtle.available_extension_versions();
+SELECT name, version FROM pgtle.available_extension_versions() ORDER BY version;
 
 -- uninstall default version
 -- fails
 SELECT pgtle.uninstall_extension('test42', '1.0');
-SELECT pgtle.available_extension_versions();
+SELECT name, version FROM pgtle.available_extension_versions() ORDER BY version;
 
 -- uninstall a version that is not the default version
 -- succeeds
 SELECT pgtle.uninstall_extension('test42', '2.0');
-SELECT pgtle.available_extension_versions();
+SELECT name, version FROM pgtle.available_extension_versions() ORDER BY version;
 
 -- uninstall non-existent version
 -- fails
 SELECT pgtle.uninstall_extension('test42', '3.0');
-SELECT pgtle.available_extension_versions();
+SELECT name, version FROM pgtle.available_extension_versions() ORDER BY version;
 
 -- uninstall the only installed and  default version
 SELECT pgtle.uninstall_extension('test42', '1.0');
-SELECT pgtle.available_extension_versions();
+SELECT name, version FROM pgtle.available_extension_versions() ORDER BY version;
 
 SELECT pgtle.install_extension
 (
@@ -524,12 +531,12 @@ $_pgtle_$
 $_pgtle_$
 );
 
-SELECT pgtle.available_extension_versions();
+SELECT name, version FROM pgtle.available_extension_versions() ORDER BY version;
 
 -- uninstall extension with multiple versions
 SELECT pgtle.uninstall_extension('test42');
 
-SELECT pgtle.available_extension_versions();
+SELECT name, version FROM pgtle.available_extension_versions() ORDER BY version;
 
 -- install extension with '@' symbol in name
 SELECT pgtle.install_extension
@@ -546,18 +553,201 @@ $_pgtle_$
 $_pgtle_$
 );
 
-SELECT pgtle.available_extensions();
+SELECT name, default_version, comment FROM pgtle.available_extensions();
 CREATE EXTENSION "foo@bar";
 SELECT extname, extversion from pg_extension where extname='foo@bar';
 SELECT at_func();
 DROP EXTENSION "foo@bar";
 SELECT pgtle.uninstall_extension('foo@bar');
 
+-- uninstall an extension that other extensions require
+SELECT pgtle.install_extension
+(
+ 'dep_a',
+ '1.0',
+ 'Test TLE Functions',
+$_pgtle_$
+  SELECT 1;
+$_pgtle_$
+);
+
+SELECT pgtle.install_extension
+(
+ 'dep_b',
+ '1.0',
+ 'Test TLE Functions',
+$_pgtle_$
+  SELECT 1;
+$_pgtle_$,
+ '{dep_a}'
+);
+
+SELECT pgtle.install_extension
+(
+ 'dep_c',
+ '1.0',
+ 'Test TLE Functions',
+$_pgtle_$
+  SELECT 1;
+$_pgtle_$,
+ '{dep_b}'
+);
+
+SELECT pgtle.install_extension
+(
+ 'dep_d',
+ '1.0',
+ 'Test TLE Functions',
+$_pgtle_$
+  SELECT 1;
+$_pgtle_$,
+ '{dep_a,dep_c}'
+);
+
+-- fails, all extensions that directly or indirectly require it are listed
+SELECT pgtle.uninstall_extension('dep_a');
+SELECT pgtle.uninstall_extension('dep_c', '1.0');
+SELECT name FROM pgtle.available_extensions() ORDER BY name;
+
+-- succeeds, extensions are uninstalled in reverse dependency order, and
+-- dep_bb, whose name starts with dep_b, is not uninstalled
+SELECT pgtle.install_extension
+(
+ 'dep_bb',
+ '1.0',
+ 'Test TLE Functions',
+$_pgtle_$
+  SELECT 1;
+$_pgtle_$
+);
+SELECT pgtle.uninstall_extension('dep_b', cascade => true);
+SELECT name FROM pgtle.available_extensions() ORDER BY name;
+SELECT pgtle.uninstall_extension('dep_a');
+-- fails, only dep_bb is installed
+SELECT pgtle.uninstall_extension('dep_b');
+-- auxiliary control functions are uninstalled as well
+CREATE FUNCTION pgtle."dep_bb--1.0.control"() RETURNS text AS
+$$ SELECT 'comment = ''Auxiliary control''' $$ LANGUAGE sql;
+SELECT pgtle.uninstall_extension('dep_bb');
+SELECT count(*) FROM pg_catalog.pg_proc WHERE proname LIKE 'dep\_b%';
+
+-- modify the metadata of an installed extension
+SELECT pgtle.install_extension
+(
+ 'meta_ext',
+ '1.0',
+ 'Test TLE Functions',
+$_pgtle_$
+  SELECT 1;
+$_pgtle_$
+);
+SELECT pgtle.install_extension_version_sql
+(
+ 'meta_ext',
+ '2.0',
+$_pgtle_$
+  SELECT 2;
+$_pgtle_$
+);
+SELECT pgtle.set_comment('meta_ext', 'Corrected description');
+SELECT pgtle.set_requires('meta_ext', ARRAY['meta_dep']);
+SELECT name, default_version, comment, requires FROM pgtle.available_extensions() WHERE name = 'meta_ext';
+SELECT name, version, comment, requires FROM pgtle.available_extension_versions() WHERE name = 'meta_ext' ORDER BY version;
+SELECT pgtle.set_requires('meta_ext', NULL);
+SELECT name, default_version, comment, requires FROM pgtle.available_extensions() WHERE name = 'meta_ext';
+-- fails
+SELECT pgtle.set_comment('bogus', 'Corrected description');
+SELECT pgtle.set_requires('bogus', ARRAY['meta_dep']);
+SELECT pgtle.set_comment('meta_ext', NULL);
+SELECT pgtle.uninstall_extension('meta_ext');
+
+-- large scripts are stored compressed and run as usual
+SELECT pgtle.install_extension
+(
+ 'big_ext',
+ '1.0',
+ 'Test TLE Functions',
+ '-- ' || repeat('padding ', 1000) || E'\nCREATE FUNCTION big_func() RETURNS INT AS $$ SELECT 1 $$ LANGUAGE SQL;\n'
+);
+SELECT pgtle.install_update_path
+(
+ 'big_ext',
+ '1.0',
+ '1.1',
+$_pgtle_$
+  CREATE OR REPLACE FUNCTION big_func() RETURNS INT AS $$ SELECT 2 $$ LANGUAGE SQL;
+$_pgtle_$
+);
+SELECT pgtle."big_ext--1.0.sql"() LIKE E'-- pg_tle compressed script of 8075 bytes\n%';
+-- the history stores the script compressed as well
+SELECT script LIKE E'-- pg_tle compressed script of 8075 bytes\n%' FROM pgtle.extension_history
+WHERE name = 'big_ext' AND version = '1.0';
+SELECT pgtle.extension_history_script('big_ext', '1.0') =
+  '-- ' || repeat('padding ', 1000) || E'\nCREATE FUNCTION big_func() RETURNS INT AS $$ SELECT 1 $$ LANGUAGE SQL;\n';
+SELECT name, scripts, compressed_scripts, stored_bytes < script_bytes AS smaller
+FROM pgtle.extension_storage_stats() WHERE name = 'big_ext';
+CREATE EXTENSION big_ext;
+SELECT big_func();
+ALTER EXTENSION big_ext UPDATE TO '1.1';
+SELECT big_func();
+DROP EXTENSION big_ext;
+SELECT pgtle.uninstall_extension('big_ext');
+-- scripts cannot start with the header of compressed scripts
+SELECT pgtle.install_extension('fake_ext', '1.0', 'Test TLE Functions', E'-- pg_tle compressed script of 10 bytes\nSELECT 1;');
+
+-- installation is limited by the quotas set by a superuser
+SET pgtle.max_extensions = 1;
+SET pgtle.max_extension_versions = 2;
+SET pgtle.max_script_size = 64;
+SET pgtle.max_extension_size = 100;
+SELECT pgtle.install_extension('quota_a', '1.0', 'Test TLE Functions', 'SELECT 1;');
+SELECT pgtle.install_extension_version_sql('quota_a', '2.0', 'SELECT 2;');
+SELECT pgtle.install_update_path('quota_a', '1.0', '2.0', 'SELECT 2; ' || repeat('-', 54));
+-- fails
+SELECT pgtle.install_extension('quota_b', '1.0', 'Test TLE Functions', 'SELECT 1;');
+SELECT pgtle.install_extension_version_sql('quota_a', '3.0', 'SELECT 3;');
+SELECT pgtle.install_update_path('quota_a', '2.0', '1.0', 'SELECT 1; ' || repeat('-', 55));
+SELECT pgtle.install_update_path('quota_a', '2.0', '1.0', 'SELECT 1; ' || repeat('-', 54));
+SELECT name, scripts, script_bytes FROM pgtle.extension_storage_stats() WHERE name = 'quota_a';
+RESET pgtle.max_extensions;
+RESET pgtle.max_extension_versions;
+RESET pgtle.max_script_size;
+RESET pgtle.max_extension_size;
+SELECT pgtle.uninstall_extension('quota_a');
+
+-- actions on extensions are recorded in the history
+SET SESSION AUTHORIZATION dbadmin;
+SELECT pgtle.install_extension('hist_ext', '1.0', 'Test TLE Functions', 'SELECT 1;');
+SELECT pgtle.install_extension_version_sql('hist_ext', '2.0', 'SELECT 2;');
+SELECT pgtle.install_update_path('hist_ext', '1.0', '2.0', 'SELECT 12;');
+SELECT pgtle.set_default_version('hist_ext', '2.0');
+SELECT pgtle.uninstall_update_path('hist_ext', '1.0', '2.0');
+SELECT pgtle.uninstall_extension('hist_ext', '1.0');
+SELECT pgtle.uninstall_extension('hist_ext');
+SELECT pgtle.install_extension('hist_ext', '1.0', 'Test TLE Functions', 'SELECT 3;');
+SELECT action, version, performed_by, checksum IS NOT NULL AS has_checksum
+FROM pgtle.extension_history WHERE name = 'hist_ext' ORDER BY id;
+SELECT count(*) FROM pgtle.extension_history
+WHERE name = 'hist_ext' AND checksum <> encode(sha256(convert_to(script, getdatabaseencoding())), 'hex');
+-- scripts of versions that have since been reinstalled
+SELECT pgtle.extension_history_script('hist_ext', '1.0');
+SELECT pgtle.extension_history_script('hist_ext', '1.0',
+  (SELECT max(performed_at) FROM pgtle.extension_history WHERE name = 'hist_ext' AND action = 'uninstall'));
+SELECT pgtle.extension_history_script('hist_ext', '1.0--2.0');
+-- fails
+DELETE FROM pgtle.extension_history WHERE name = 'hist_ext';
+UPDATE pgtle.extension_history SET performed_by = 'dbstaff' WHERE name = 'hist_ext';
+INSERT INTO pgtle.extension_history (action, name, performed_by) VALUES ('install', 'hist_ext', 'dbstaff');
+INSERT INTO pgtle.extension_history (action, name) VALUES ('install', 'hist_ext');
+SELECT pgtle.feature_info_history();
+SELECT pgtle.uninstall_extension('hist_ext');
+RESET SESSION AUTHORIZATION;
+
 -- Skip TransactionStmts
 BEGIN;
-SELECT pgtle.available_extension_versions();
+SELECT name, version FROM pgtle.available_extension_versions() ORDER BY version;
 SELECT 1/0;
-SELECT pgtle.available_extension_versions();
+SELECT name, version FROM pgtle.available_extension_versions() ORDER BY version;
 ROLLBACK;
 
 -- clean up
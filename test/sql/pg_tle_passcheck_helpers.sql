@@ -0,0 +1,67 @@
+/*
+*
+* Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+*/
+
+/*
+* 1. Test each password policy helper on plaintext passwords
+* 2. Test that hashed passwords are accepted unless plaintext is required
+* 3. Test that pgtle.passcheck_policy can be registered as a passcheck function
+*/
+
+\pset pager off
+CREATE EXTENSION pg_tle;
+
+-- minimum length
+SELECT pgtle.passcheck_min_length('short', 'PASSWORD_TYPE_PLAINTEXT', 8);
+SELECT pgtle.passcheck_min_length('longenough', 'PASSWORD_TYPE_PLAINTEXT', 8);
+
+-- character classes
+SELECT pgtle.passcheck_character_classes('alllowercase', 'PASSWORD_TYPE_PLAINTEXT', 3);
+SELECT pgtle.passcheck_character_classes('Lower1', 'PASSWORD_TYPE_PLAINTEXT', 3);
+SELECT pgtle.passcheck_character_classes('Lower1!', 'PASSWORD_TYPE_PLAINTEXT', 4);
+
+-- entropy
+SELECT pgtle.password_entropy('');
+SELECT round(pgtle.password_entropy('abc')::numeric, 2);
+SELECT round(pgtle.password_entropy('aB3$')::numeric, 2);
+SELECT pgtle.passcheck_min_entropy('password', 'PASSWORD_TYPE_PLAINTEXT', 50);
+SELECT pgtle.passcheck_min_entropy('c0rrect-H0rse-battery', 'PASSWORD_TYPE_PLAINTEXT', 50);
+
+-- similarity to the role name
+SELECT pgtle.passcheck_not_similar_to_username('alice', 'xxALICExx', 'PASSWORD_TYPE_PLAINTEXT');
+SELECT pgtle.passcheck_not_similar_to_username('alice', 'ecila2024', 'PASSWORD_TYPE_PLAINTEXT');
+SELECT pgtle.passcheck_not_similar_to_username('alice', 'bob2024', 'PASSWORD_TYPE_PLAINTEXT');
+
+-- hashed passwords cannot be checked
+SELECT pgtle.passcheck_min_length('md5deadbeef', 'PASSWORD_TYPE_MD5', 64);
+SELECT pgtle.passcheck_character_classes('md5deadbeef', 'PASSWORD_TYPE_MD5', 4);
+SELECT pgtle.passcheck_min_entropy('md5deadbeef', 'PASSWORD_TYPE_MD5', 500);
+SELECT pgtle.passcheck_not_similar_to_username('md5', 'md5deadbeef', 'PASSWORD_TYPE_MD5');
+SELECT pgtle.passcheck_require_plaintext('PASSWORD_TYPE_MD5');
+SELECT pgtle.passcheck_require_plaintext('PASSWORD_TYPE_PLAINTEXT');
+
+-- combined policy
+SELECT pgtle.passcheck_policy('alice', 'Sh0rt', 'PASSWORD_TYPE_PLAINTEXT', NULL, true);
+SELECT pgtle.passcheck_policy('alice', 'alllowercase1', 'PASSWORD_TYPE_PLAINTEXT', NULL, true);
+SELECT pgtle.passcheck_policy('alice', 'Alice-is-great1', 'PASSWORD_TYPE_PLAINTEXT', NULL, true);
+SELECT pgtle.passcheck_policy('alice', 'Correct-horse-1', 'PASSWORD_TYPE_PLAINTEXT', NULL, true);
+
+-- the policy can be registered directly
+ALTER SYSTEM SET pgtle.enable_password_check = 'on';
+SELECT pg_catalog.pg_reload_conf();
+-- reconnect to ensure reload settings are propagated immediately
+\c -
+SELECT pgtle.register_feature('pgtle.passcheck_policy', 'passcheck');
+CREATE ROLE alice PASSWORD 'alice-password';
+CREATE ROLE alice PASSWORD 'Correct-horse-1';
+SELECT pgtle.unregister_feature('pgtle.passcheck_policy', 'passcheck');
+ALTER SYSTEM RESET pgtle.enable_password_check;
+SELECT pg_catalog.pg_reload_conf();
+
+-- clean up
+DROP ROLE alice;
+DROP EXTENSION pg_tle;
+DROP SCHEMA pgtle;
+DROP ROLE pgtle_admin;
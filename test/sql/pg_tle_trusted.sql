@@ -0,0 +1,258 @@
+/*
+*
+* Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+ * SPDX-License-Identifier: Apache-2.0
+*/
+
+\pset pager off
+CREATE EXTENSION pg_tle;
+
+-- create semi-privileged role to manipulate pg_tle artifacts
+CREATE ROLE dbadmin;
+GRANT pgtle_admin TO dbadmin;
+
+-- create unprivileged role with CREATE privilege on the database
+CREATE ROLE dbowner;
+
+-- create completely unprivileged role
+CREATE ROLE dbguest;
+
+GRANT CREATE, USAGE ON SCHEMA PUBLIC TO dbowner;
+GRANT CREATE, USAGE ON SCHEMA PUBLIC TO dbguest;
+DO
+$$
+  DECLARE
+    objname text;
+  BEGIN
+    SELECT current_database() INTO objname;
+    EXECUTE format('GRANT CREATE ON DATABASE %I TO dbowner;', objname);
+  END;
+$$ LANGUAGE plpgsql;
+
+-- create function that can be executed by superuser only
+CREATE FUNCTION superuser_only()
+RETURNS INT AS $$
+(
+  SELECT 51
+) $$ LANGUAGE sql;
+
+REVOKE EXECUTE ON FUNCTION superuser_only() FROM PUBLIC;
+
+-- only a superuser can install a trusted extension
+-- fails
+SET SESSION AUTHORIZATION dbadmin;
+SELECT pgtle.install_extension
+(
+ 'trusted_ext',
+ '1.0',
+ 'Test TLE Functions',
+$_pgtle_$
+  SELECT superuser_only();
+  CREATE FUNCTION trusted_func()
+  RETURNS INT AS $$
+  (
+    SELECT 1
+  )$$ LANGUAGE sql;
+$_pgtle_$,
+ trusted => true
+);
+
+-- succeeds
+RESET SESSION AUTHORIZATION;
+SELECT pgtle.install_extension
+(
+ 'trusted_ext',
+ '1.0',
+ 'Test TLE Functions',
+$_pgtle_$
+  SELECT superuser_only();
+  CREATE FUNCTION trusted_func()
+  RETURNS INT AS $$
+  (
+    SELECT 1
+  )$$ LANGUAGE sql;
+$_pgtle_$,
+ trusted => true
+);
+
+SELECT name, superuser, trusted FROM pgtle.available_extensions();
+
+-- only a superuser can add scripts to a trusted extension
+-- fails
+SET SESSION AUTHORIZATION dbadmin;
+SELECT pgtle.install_extension_version_sql
+(
+ 'trusted_ext',
+ '2.0',
+$_pgtle_$
+  SELECT superuser_only();
+$_pgtle_$
+);
+SELECT pgtle.install_update_path
+(
+ 'trusted_ext',
+ '1.0',
+ '1.1',
+$_pgtle_$
+  SELECT superuser_only();
+$_pgtle_$
+);
+
+-- only a superuser can change the control function of a trusted extension
+-- fails
+SELECT pgtle.set_default_version('trusted_ext', '1.0');
+SELECT pgtle.set_requires('trusted_ext', NULL);
+SELECT pgtle.set_comment('trusted_ext', 'Changed by dbadmin');
+
+-- succeeds
+RESET SESSION AUTHORIZATION;
+SELECT pgtle.install_update_path
+(
+ 'trusted_ext',
+ '1.0',
+ '1.1',
+$_pgtle_$
+  SELECT superuser_only();
+  CREATE OR REPLACE FUNCTION trusted_func()
+  RETURNS INT AS $$
+  (
+    SELECT 11
+  )$$ LANGUAGE sql;
+$_pgtle_$
+);
+
+-- role without CREATE privilege on the database can not create a trusted extension
+-- fails
+SET SESSION AUTHORIZATION dbguest;
+CREATE EXTENSION trusted_ext;
+
+-- role with CREATE privilege on the database can create and update a
+-- trusted extension that requires superuser privilege
+SET SESSION AUTHORIZATION dbowner;
+CREATE EXTENSION trusted_ext;
+SELECT trusted_func();
+SELECT extowner::regrole FROM pg_extension WHERE extname = 'trusted_ext';
+ALTER EXTENSION trusted_ext UPDATE TO '1.1';
+SELECT trusted_func();
+DROP EXTENSION trusted_ext;
+
+-- changing the default version keeps the extension trusted
+RESET SESSION AUTHORIZATION;
+SELECT pgtle.install_extension_version_sql
+(
+ 'trusted_ext',
+ '2.0',
+$_pgtle_$
+  SELECT superuser_only();
+$_pgtle_$
+);
+SELECT pgtle.set_default_version('trusted_ext', '2.0');
+SELECT name, default_version, superuser, trusted FROM pgtle.available_extensions();
+SELECT pgtle.uninstall_extension('trusted_ext');
+
+-- a trusted extension can not be installed over scripts provided by a
+-- role that is not a superuser
+SET SESSION AUTHORIZATION dbadmin;
+SELECT pgtle.install_update_path
+(
+ 'pending_ext',
+ '1.0',
+ '1.1',
+$_pgtle_$
+  SELECT superuser_only();
+$_pgtle_$
+);
+RESET SESSION AUTHORIZATION;
+-- fails
+SELECT pgtle.install_extension
+(
+ 'pending_ext',
+ '1.0',
+ 'Test TLE Functions',
+$_pgtle_$
+  SELECT superuser_only();
+$_pgtle_$,
+ trusted => true
+);
+SELECT pgtle.uninstall_update_path('pending_ext', '1.0', '1.1');
+-- succeeds
+SELECT pgtle.install_extension
+(
+ 'pending_ext',
+ '1.0',
+ 'Test TLE Functions',
+$_pgtle_$
+  SELECT superuser_only();
+$_pgtle_$,
+ trusted => true
+);
+SELECT pgtle.uninstall_extension('pending_ext');
+
+-- control and script functions can not be created directly by a role that
+-- is not a superuser
+SET SESSION AUTHORIZATION dbadmin;
+-- fails
+CREATE FUNCTION pgtle."evil_ext.control"()
+RETURNS TEXT AS $_pgtle_$
+  SELECT $$default_version = '1.0'
+trusted = true$$
+$_pgtle_$ LANGUAGE sql;
+CREATE FUNCTION pgtle."trusted_ext--1.0--9.0.sql"()
+RETURNS TEXT AS $_pgtle_$
+  SELECT $$SELECT superuser_only()$$
+$_pgtle_$ LANGUAGE sql;
+-- succeeds
+CREATE FUNCTION pgtle.evil_ext_control()
+RETURNS TEXT AS $_pgtle_$
+  SELECT ''
+$_pgtle_$ LANGUAGE sql;
+-- fails
+ALTER FUNCTION pgtle.evil_ext_control() RENAME TO "evil_ext.control";
+DROP FUNCTION pgtle.evil_ext_control();
+
+-- scripts of a trusted extension that are not owned by a superuser are not
+-- run as the bootstrap superuser
+RESET SESSION AUTHORIZATION;
+SELECT pgtle.install_extension
+(
+ 'owned_ext',
+ '1.0',
+ 'Test TLE Functions',
+$_pgtle_$
+  SELECT superuser_only();
+$_pgtle_$,
+ trusted => true
+);
+CREATE FUNCTION pgtle."owned_ext--1.0--1.1.sql"()
+RETURNS TEXT AS $_pgtle_$
+  SELECT $$SELECT superuser_only()$$
+$_pgtle_$ LANGUAGE sql;
+ALTER FUNCTION pgtle."owned_ext--1.0--1.1.sql"() OWNER TO dbadmin;
+SET SESSION AUTHORIZATION dbowner;
+CREATE EXTENSION owned_ext;
+-- fails
+ALTER EXTENSION owned_ext UPDATE TO '1.1';
+DROP EXTENSION owned_ext;
+RESET SESSION AUTHORIZATION;
+SELECT pgtle.uninstall_extension('owned_ext');
+
+-- clean up
+RESET SESSION AUTHORIZATION;
+DROP FUNCTION superuser_only();
+REVOKE CREATE, USAGE ON SCHEMA PUBLIC FROM dbowner;
+REVOKE CREATE, USAGE ON SCHEMA PUBLIC FROM dbguest;
+DO
+$$
+  DECLARE
+    objname text;
+  BEGIN
+    SELECT current_database() INTO objname;
+    EXECUTE format('REVOKE ALL ON DATABASE %I FROM dbowner;', objname);
+  END;
+$$ LANGUAGE plpgsql;
+DROP ROLE dbadmin;
+DROP ROLE dbowner;
+DROP ROLE dbguest;
+DROP EXTENSION pg_tle;
+DROP SCHEMA pgtle;
+DROP ROLE pgtle_admin;
@@ -29,6 +29,12 @@
 ### 15. Rejects connections when no schema qualified function is found
 ### 16. Database does not come up if clientauth workers fail to start
 ### 17. Malformed strings cannot be used for SQL injection
+### 18. Functions only take effect for users on pgtle.clientauth_users_to_check, if set
+### 19. Functions only take effect for databases on pgtle.clientauth_databases_to_check, if set
+### 20. SQLSTATE, detail and hint raised by functions are returned to the user
+### 21. Functions do not take effect when application_name is on pgtle.clientauth_application_names_to_skip
+### 22. Functions do not take effect for superusers when pgtle.clientauth_skip_superusers = on
+### 23. Functions do not take effect for replication connections when pgtle.clientauth_skip_replication_connections = on
 
 use strict;
 use warnings;
@@ -322,5 +328,112 @@ $node->psql('not_excluded', 'SELECT current_user', extra_params => ['-U', '") /*
 like($psql_out, qr/^"\) \/\*$/,
     "role with injection payload in name can connect");
 
+### 18. Functions only take effect for users on pgtle.clientauth_users_to_check, if set
+$node->append_conf('postgresql.conf', qq(pgtle.clientauth_users_to_check = 'testuser2'));
+$node->psql('postgres', 'SELECT pg_reload_conf()', on_error_die => 1);
+$node->command_ok(
+    ['psql', '-d', 'not_excluded', '-U', 'testuser', '-c', 'select;'],
+    "clientauth function does not reject testuser when testuser is not in pgtle.clientauth_users_to_check");
+
+$node->append_conf('postgresql.conf', qq(pgtle.clientauth_users_to_check = 'testuser2,testuser'));
+$node->psql('postgres', 'SELECT pg_reload_conf()', on_error_die => 1);
+$node->psql('not_excluded', 'select', extra_params => ['-U', 'testuser'], stderr => \$psql_err);
+like($psql_err, qr/FATAL:  testuser is not allowed to connect/,
+    "clientauth function rejects testuser when testuser is in pgtle.clientauth_users_to_check");
+
+### 19. Functions only take effect for databases on pgtle.clientauth_databases_to_check, if set
+$node->append_conf('postgresql.conf', qq(pgtle.clientauth_users_to_check = ''));
+$node->append_conf('postgresql.conf', qq(pgtle.clientauth_databases_to_check = 'not_excluded'));
+$node->psql('postgres', 'SELECT pg_reload_conf()', on_error_die => 1);
+$node->command_ok(
+    ['psql', '-d', '""', '-U', 'testuser', '-c', 'select;'],
+    "clientauth function does not reject testuser when database is not in pgtle.clientauth_databases_to_check");
+$node->psql('not_excluded', 'select', extra_params => ['-U', 'testuser'], stderr => \$psql_err);
+like($psql_err, qr/FATAL:  testuser is not allowed to connect/,
+    "clientauth function rejects testuser when database is in pgtle.clientauth_databases_to_check");
+
+$node->append_conf('postgresql.conf', qq(pgtle.clientauth_databases_to_check = 'postgres'));
+$node->psql('postgres', 'SELECT pg_reload_conf()', on_error_die => 1);
+$node->command_ok(
+    ['psql', '-U', 'testuser', '-c', 'select;'],
+    "pgtle.clientauth_databases_to_skip takes precedence over pgtle.clientauth_databases_to_check");
+
+### 20. SQLSTATE, detail and hint raised by functions are returned to the user
+$node->psql('postgres', q[
+    CREATE FUNCTION reject_banned(port pgtle.clientauth_port_subset, status integer) RETURNS void AS $$
+        BEGIN
+            IF port.user_name = 'testuser2' THEN
+                RAISE EXCEPTION 'connections from banned IP range are not allowed'
+                    USING ERRCODE = 'invalid_authorization_specification',
+                          DETAIL = 'testuser2 is banned',
+                          HINT = 'Contact your administrator.';
+            END IF;
+        END
+    $$ LANGUAGE plpgsql], on_error_die => 1);
+$node->psql('postgres', qq[SELECT pgtle.register_feature('reject_banned', 'clientauth')], on_error_die => 1);
+$node->append_conf('postgresql.conf', qq(pgtle.clientauth_databases_to_check = ''));
+$node->append_conf('postgresql.conf', qq(log_error_verbosity = 'verbose'));
+$node->psql('postgres', 'SELECT pg_reload_conf()', on_error_die => 1);
+
+my $log_offset = -s $node->logfile;
+$node->psql('not_excluded', 'select', extra_params => ['-U', 'testuser2'], stderr => \$psql_err);
+like($psql_err, qr/FATAL:  connections from banned IP range are not allowed\nDETAIL:  testuser2 is banned\nHINT:  Contact your administrator./,
+    "clientauth returns the message, detail and hint raised by the function");
+like(slurp_file($node->logfile, $log_offset), qr/FATAL:  28000: connections from banned IP range are not allowed/,
+    "clientauth returns the SQLSTATE raised by the function");
+
+$log_offset = -s $node->logfile;
+$node->psql('not_excluded', 'select', extra_params => ['-U', 'testuser'], stderr => \$psql_err);
+like(slurp_file($node->logfile, $log_offset), qr/FATAL:  08000: testuser is not allowed to connect/,
+    "clientauth returns connection_exception for errors raised without a SQLSTATE");
+
+### 21. Functions do not take effect when application_name is on pgtle.clientauth_application_names_to_skip
+$node->append_conf('postgresql.conf', qq(pgtle.clientauth_application_names_to_skip = 'monitoring_agent'));
+$node->psql('postgres', 'SELECT pg_reload_conf()', on_error_die => 1);
+$node->command_ok(
+    ['psql', '-d', 'dbname=not_excluded application_name=monitoring_agent', '-U', 'testuser', '-c', 'select;'],
+    "clientauth function does not reject testuser when application_name is in pgtle.clientauth_application_names_to_skip");
+$node->psql('not_excluded', 'select', extra_params => ['-U', 'testuser'], stderr => \$psql_err);
+like($psql_err, qr/FATAL:  testuser is not allowed to connect/,
+    "clientauth function rejects testuser when application_name is not in pgtle.clientauth_application_names_to_skip");
+
+### 22. Functions do not take effect for superusers when pgtle.clientauth_skip_superusers = on
+$node->psql('postgres', q[
+    CREATE FUNCTION reject_all(port pgtle.clientauth_port_subset, status integer) RETURNS void AS $$
+        BEGIN
+            RAISE EXCEPTION '% is not allowed to connect', port.user_name;
+        END
+    $$ LANGUAGE plpgsql], on_error_die => 1);
+$node->psql('postgres', qq[SELECT pgtle.register_feature('reject_all', 'clientauth')], on_error_die => 1);
+$node->psql('not_excluded', 'select', stderr => \$psql_err);
+like($psql_err, qr/FATAL:  .* is not allowed to connect/,
+    "clientauth function rejects superuser when pgtle.clientauth_skip_superusers = off");
+
+$node->append_conf('postgresql.conf', qq(pgtle.clientauth_skip_superusers = on));
+$node->psql('postgres', 'SELECT pg_reload_conf()', on_error_die => 1);
+$node->command_ok(
+    ['psql', '-d', 'not_excluded', '-c', 'select;'],
+    "clientauth function does not reject superuser when pgtle.clientauth_skip_superusers = on");
+$node->psql('not_excluded', 'select', extra_params => ['-U', 'testuser2'], stderr => \$psql_err);
+like($psql_err, qr/FATAL:  .* is not allowed to connect/,
+    "clientauth function rejects other users when pgtle.clientauth_skip_superusers = on");
+
+### 23. Functions do not take effect for replication connections when pgtle.clientauth_skip_replication_connections = on
+$node->append_conf('postgresql.conf', qq(pgtle.clientauth_skip_superusers = off));
+$node->psql('postgres', 'SELECT pg_reload_conf()', on_error_die => 1);
+$node->command_fails_like(
+    ['psql', '-d', 'dbname=not_excluded replication=database', '-c', 'IDENTIFY_SYSTEM'],
+    qr/FATAL:  .* is not allowed to connect/,
+    "clientauth function rejects replication connections when pgtle.clientauth_skip_replication_connections = off");
+
+$node->append_conf('postgresql.conf', qq(pgtle.clientauth_skip_replication_connections = on));
+$node->psql('postgres', 'SELECT pg_reload_conf()', on_error_die => 1);
+$node->command_ok(
+    ['psql', '-d', 'dbname=not_excluded replication=database', '-c', 'IDENTIFY_SYSTEM'],
+    "clientauth function does not reject replication connections when pgtle.clientauth_skip_replication_connections = on");
+$node->psql('not_excluded', 'select', stderr => \$psql_err);
+like($psql_err, qr/FATAL:  .* is not allowed to connect/,
+    "clientauth function rejects other connections when pgtle.clientauth_skip_replication_connections = on");
+
 $node->stop;
 done_testing();
\ No newline at end of file
@@ -0,0 +1,165 @@
+# Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+#
+#  Licensed under the Apache License, Version 2.0 (the "License").
+#  You may not use this file except in compliance with the License.
+#  You may obtain a copy of the License at
+#
+#      http://www.apache.org/licenses/LICENSE-2.0
+#
+#  Unless required by applicable law or agreed to in writing, software
+#  distributed under the License is distributed on an "AS IS" BASIS,
+#  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+#  See the License for the specific language governing permissions and
+#  limitations under the License.
+
+### 1. Basic login function rejects sessions based on session info
+### 2. Basic login function allows other sessions
+### 3. Login function receives the user, database and application_name
+### 4. Login function can set session configuration parameters
+### 5. Settings are applied again if the first transaction is rolled back
+### 6. Functions do not take effect when pgtle.enable_login = 'off'
+### 7. Functions do not take effect when user is on pgtle.login_users_to_skip
+### 8. Functions do not take effect when database is on pgtle.login_databases_to_skip
+### 9. Sessions are rejected when pgtle.enable_login = 'require' and pg_tle is not installed
+### 10. Sessions are rejected when pgtle.enable_login = 'require' and no functions are registered to login
+
+use strict;
+use warnings;
+
+use PostgreSQL::Test::Cluster;
+use PostgreSQL::Test::Utils;
+use Test::More;
+
+my $psql_err = '';
+my $psql_out = '';
+my $node = PostgreSQL::Test::Cluster->new('login_test');
+
+$node->init;
+$node->append_conf('postgresql.conf', qq(shared_preload_libraries = 'pg_tle'));
+$node->append_conf('postgresql.conf', qq(pgtle.enable_login = 'on'));
+$node->start;
+
+$node->psql('postgres', 'CREATE EXTENSION pg_tle', on_error_die => 1);
+$node->psql('postgres', 'CREATE ROLE testuser LOGIN', on_error_die => 1);
+$node->psql('postgres', 'CREATE ROLE testuser2 LOGIN', on_error_die => 1);
+
+### 1. Basic login function rejects sessions based on session info
+$node->psql('postgres', q[
+    CREATE FUNCTION reject_testuser(role text, database text, application_name text) RETURNS void AS $$
+        BEGIN
+            IF role = 'testuser' THEN
+                RAISE EXCEPTION 'testuser is not allowed to connect';
+            END IF;
+        END
+    $$ LANGUAGE plpgsql], on_error_die => 1);
+$node->psql('postgres', qq[SELECT pgtle.register_feature('reject_testuser', 'login')], on_error_die => 1);
+
+$node->psql('postgres', 'select', extra_params => ['-U', 'testuser'], stderr => \$psql_err);
+like($psql_err, qr/FATAL:  testuser is not allowed to connect/,
+    "login function rejects testuser");
+
+### 2. Basic login function allows other sessions
+$node->command_ok(
+    ['psql', '-U', 'testuser2', '-c', 'select;'],
+    "login function accepts other users");
+
+### 3. Login function receives the user, database and application_name
+$node->psql('postgres', q[
+    CREATE TABLE login_audit(role text, database text, application_name text);
+    CREATE FUNCTION audit_login(role text, database text, application_name text) RETURNS void AS $$
+        INSERT INTO public.login_audit VALUES (role, database, application_name);
+    $$ LANGUAGE sql SECURITY DEFINER], on_error_die => 1);
+$node->psql('postgres', qq[SELECT pgtle.register_feature('audit_login', 'login')], on_error_die => 1);
+
+$node->command_ok(
+    ['psql', '-U', 'testuser2', '-c', 'select;'],
+    "login function records session of testuser2");
+$node->psql('postgres', q[SELECT role, database, application_name FROM login_audit WHERE role = 'testuser2'],
+    stdout => \$psql_out);
+is($psql_out, 'testuser2|postgres|psql',
+    "login function receives the user, database and application_name");
+$node->psql('postgres', qq[SELECT pgtle.unregister_feature('audit_login', 'login')], on_error_die => 1);
+
+### 4. Login function can set session configuration parameters
+$node->psql('postgres', q[
+    CREATE FUNCTION set_work_mem(role text, database text, application_name text) RETURNS void AS $$
+        BEGIN
+            IF role = 'testuser2' THEN
+                PERFORM pg_catalog.set_config('work_mem', '1234kB', false);
+            END IF;
+        END
+    $$ LANGUAGE plpgsql], on_error_die => 1);
+$node->psql('postgres', qq[SELECT pgtle.register_feature('set_work_mem', 'login')], on_error_die => 1);
+
+$node->psql('postgres', 'SHOW work_mem', extra_params => ['-U', 'testuser2'], stdout => \$psql_out);
+is($psql_out, '1234kB', "login function sets work_mem for testuser2");
+$node->psql('postgres', 'SHOW work_mem', stdout => \$psql_out);
+isnt($psql_out, '1234kB', "login function does not set work_mem for other users");
+
+### 5. Settings are applied again if the first transaction is rolled back
+$node->command_like(
+    ['psql', '-U', 'testuser2', '-d', 'postgres', '-XAt', '-c', 'SELECT 1/0', '-c', 'SHOW work_mem'],
+    qr/^1234kB$/,
+    "login function is called again after the first transaction is rolled back");
+
+### 6. Functions do not take effect when pgtle.enable_login = 'off'
+$node->append_conf('postgresql.conf', qq(pgtle.enable_login = 'off'));
+$node->reload;
+
+$node->command_ok(
+    ['psql', '-U', 'testuser', '-c', 'select;'],
+    "login function does not reject testuser when pgtle.enable_login = 'off'");
+$node->psql('postgres', 'SHOW work_mem', extra_params => ['-U', 'testuser2'], stdout => \$psql_out);
+isnt($psql_out, '1234kB', "login function does not set work_mem when pgtle.enable_login = 'off'");
+
+### 7. Functions do not take effect when user is on pgtle.login_users_to_skip
+$node->append_conf('postgresql.conf', qq(pgtle.enable_login = 'on'));
+$node->append_conf('postgresql.conf', qq(pgtle.login_users_to_skip = 'testuser'));
+$node->reload;
+
+$node->command_ok(
+    ['psql', '-U', 'testuser', '-c', 'select;'],
+    "login function does not reject testuser when testuser is in pgtle.login_users_to_skip");
+
+### 8. Functions do not take effect when database is on pgtle.login_databases_to_skip
+$node->psql('postgres', 'CREATE DATABASE skipped', on_error_die => 1);
+$node->psql('skipped', 'CREATE EXTENSION pg_tle', on_error_die => 1);
+$node->psql('skipped', q[
+    CREATE FUNCTION reject_all(role text, database text, application_name text) RETURNS void AS $$
+        BEGIN
+            RAISE EXCEPTION '% is not allowed to connect to %', role, database;
+        END
+    $$ LANGUAGE plpgsql], on_error_die => 1);
+$node->psql('skipped', qq[SELECT pgtle.register_feature('reject_all', 'login')]);
+
+$node->psql('skipped', 'select', extra_params => ['-U', 'testuser2'], stderr => \$psql_err);
+like($psql_err, qr/FATAL:  testuser2 is not allowed to connect to skipped/,
+    "login function rejects testuser2 in skipped");
+
+$node->append_conf('postgresql.conf', qq(pgtle.login_databases_to_skip = 'skipped'));
+$node->reload;
+
+$node->command_ok(
+    ['psql', '-U', 'testuser2', '-d', 'skipped', '-c', 'select;'],
+    "login function does not reject testuser2 when database is in pgtle.login_databases_to_skip");
+
+### 9. Sessions are rejected when pgtle.enable_login = 'require' and pg_tle is not installed
+$node->psql('postgres', 'CREATE DATABASE no_pg_tle', on_error_die => 1);
+$node->append_conf('postgresql.conf', qq(pgtle.enable_login = 'require'));
+$node->reload;
+
+$node->psql('no_pg_tle', 'select', extra_params => ['-U', 'testuser2'], stderr => \$psql_err);
+like($psql_err, qr/FATAL:  "pgtle.enable_login" feature is set to require but extension "pg_tle" is not installed in the database "no_pg_tle"/,
+    "sessions are rejected in databases without pg_tle when pgtle.enable_login = 'require'");
+
+### 10. Sessions are rejected when pgtle.enable_login = 'require' and no functions are registered to login
+$node->psql('skipped', qq[SELECT pgtle.unregister_feature('reject_all', 'login')], on_error_die => 1);
+$node->append_conf('postgresql.conf', qq(pgtle.login_databases_to_skip = ''));
+$node->reload;
+
+$node->psql('skipped', 'select', extra_params => ['-U', 'testuser2'], stderr => \$psql_err);
+like($psql_err, qr/FATAL:  "pgtle.enable_login" feature is set to require, however no entries exist in "pgtle.feature_info" with the feature "login" in the database "skipped"/,
+    "sessions are rejected without login functions when pgtle.enable_login = 'require'");
+
+$node->stop;
+done_testing();
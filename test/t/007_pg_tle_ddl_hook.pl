@@ -0,0 +1,169 @@
+# Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+#
+#  Licensed under the Apache License, Version 2.0 (the "License").
+#  You may not use this file except in compliance with the License.
+#  You may obtain a copy of the License at
+#
+#      http://www.apache.org/licenses/LICENSE-2.0
+#
+#  Unless required by applicable law or agreed to in writing, software
+#  distributed under the License is distributed on an "AS IS" BASIS,
+#  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+#  See the License for the specific language governing permissions and
+#  limitations under the License.
+
+### 1. ddl_command_start function blocks DDL
+### 2. ddl_command_start function allows other DDL
+### 3. ddl_command_end function receives the command information
+### 4. Functions are not called for commands that are not DDL
+### 5. Functions are not called for the DDL they run themselves
+### 6. Functions do not take effect when pgtle.enable_ddl_hook = 'off'
+### 7. DDL is rejected when pgtle.enable_ddl_hook = 'require' and pg_tle is not installed
+### 8. Functions in pgtle.ddl_hook_db_name are called for DDL in all databases
+### 9. Functions are called as the user running DDL in pgtle.ddl_hook_db_name itself
+
+use strict;
+use warnings;
+
+use PostgreSQL::Test::Cluster;
+use PostgreSQL::Test::Utils;
+use Test::More;
+
+my $psql_err = '';
+my $psql_out = '';
+my $superuser = '';
+my $node = PostgreSQL::Test::Cluster->new('ddl_hook_test');
+
+$node->init;
+$node->append_conf('postgresql.conf', qq(shared_preload_libraries = 'pg_tle'));
+$node->append_conf('postgresql.conf', qq(pgtle.enable_ddl_hook = 'on'));
+$node->start;
+
+$node->psql('postgres', 'CREATE EXTENSION pg_tle', on_error_die => 1);
+$node->psql('postgres', 'SELECT current_user', stdout => \$superuser);
+$node->psql('postgres', 'CREATE SCHEMA protected', on_error_die => 1);
+
+### 1. ddl_command_start function blocks DDL
+$node->psql('postgres', q[
+    CREATE FUNCTION protect_schema(event text, command_tag text, command text, database_name text, user_name text) RETURNS void AS $$
+        BEGIN
+            IF command_tag = 'DROP SCHEMA' AND command ~* 'protected' THEN
+                RAISE EXCEPTION 'schema "protected" cannot be dropped';
+            END IF;
+        END
+    $$ LANGUAGE plpgsql], on_error_die => 1);
+$node->psql('postgres', qq[SELECT pgtle.register_feature('protect_schema', 'ddl_command_start')], on_error_die => 1);
+
+$node->psql('postgres', 'DROP SCHEMA protected', stderr => \$psql_err);
+like($psql_err, qr/ERROR:  schema "protected" cannot be dropped/,
+    "ddl_command_start function blocks DROP SCHEMA protected");
+$node->psql('postgres', q[SELECT count(*) FROM pg_namespace WHERE nspname = 'protected'], stdout => \$psql_out);
+is($psql_out, '1', "schema protected still exists");
+
+### 2. ddl_command_start function allows other DDL
+$node->command_ok(
+    ['psql', '-c', 'CREATE SCHEMA unprotected; DROP SCHEMA unprotected;'],
+    "ddl_command_start function allows other DDL");
+
+### 3. ddl_command_end function receives the command information
+$node->psql('postgres', q[
+    CREATE TABLE ddl_audit(event text, command_tag text, command text, database_name text, user_name text);
+    CREATE FUNCTION audit_ddl(event text, command_tag text, command text, database_name text, user_name text) RETURNS void AS $$
+        INSERT INTO public.ddl_audit VALUES (event, command_tag, command, database_name, user_name);
+    $$ LANGUAGE sql], on_error_die => 1);
+$node->psql('postgres', qq[SELECT pgtle.register_feature('audit_ddl', 'ddl_command_end')], on_error_die => 1);
+
+$node->psql('postgres', 'SELECT 1; CREATE TABLE audited(a int);', on_error_die => 1);
+$node->psql('postgres', q[SELECT * FROM ddl_audit WHERE command_tag = 'CREATE TABLE'], stdout => \$psql_out);
+is($psql_out, 'ddl_command_end|CREATE TABLE|CREATE TABLE audited(a int)|postgres|' . $superuser,
+    "ddl_command_end function receives the command information");
+
+### 4. Functions are not called for commands that are not DDL
+$node->psql('postgres', 'TRUNCATE ddl_audit', on_error_die => 1);
+$node->psql('postgres', 'VACUUM audited; SET work_mem TO 1234; SHOW work_mem;', on_error_die => 1);
+$node->psql('postgres', 'SELECT count(*) FROM ddl_audit', stdout => \$psql_out);
+is($psql_out, '0', "functions are not called for commands that are not DDL");
+$node->psql('postgres', q[
+    CREATE ROLE renamed_role;
+    ALTER ROLE renamed_role RENAME TO renamed_role2;
+    COMMENT ON ROLE renamed_role2 IS 'not DDL';
+    ALTER DATABASE postgres OWNER TO renamed_role2;
+    ALTER DATABASE postgres OWNER TO CURRENT_USER;
+    GRANT CONNECT ON DATABASE postgres TO renamed_role2;
+    REVOKE CONNECT ON DATABASE postgres FROM renamed_role2;
+    DROP ROLE renamed_role2;], on_error_die => 1);
+$node->psql('postgres', 'SELECT count(*) FROM ddl_audit', stdout => \$psql_out);
+is($psql_out, '0', "functions are not called for commands on roles and databases");
+$node->psql('postgres', q[COMMENT ON TABLE audited IS 'DDL'], on_error_die => 1);
+$node->psql('postgres', 'SELECT command_tag FROM ddl_audit', stdout => \$psql_out);
+is($psql_out, 'COMMENT', "functions are called for commands on other objects");
+
+### 5. Functions are not called for the DDL they run themselves
+$node->psql('postgres', q[
+    CREATE FUNCTION create_table(event text, command_tag text, command text, database_name text, user_name text) RETURNS void AS $$
+        BEGIN
+            IF command_tag = 'CREATE VIEW' THEN
+                CREATE TABLE IF NOT EXISTS created_by_hook(a int);
+            END IF;
+        END
+    $$ LANGUAGE plpgsql], on_error_die => 1);
+$node->psql('postgres', qq[SELECT pgtle.register_feature('create_table', 'ddl_command_end')], on_error_die => 1);
+$node->psql('postgres', 'TRUNCATE ddl_audit', on_error_die => 1);
+$node->psql('postgres', 'CREATE VIEW audited_view AS SELECT 1', on_error_die => 1);
+$node->psql('postgres', 'SELECT command_tag FROM ddl_audit', stdout => \$psql_out);
+is($psql_out, 'CREATE VIEW', "functions are not called for the DDL they run themselves");
+$node->psql('postgres', qq[SELECT pgtle.unregister_feature('create_table', 'ddl_command_end')], on_error_die => 1);
+
+### 6. Functions do not take effect when pgtle.enable_ddl_hook = 'off'
+$node->append_conf('postgresql.conf', qq(pgtle.enable_ddl_hook = 'off'));
+$node->reload;
+
+$node->command_ok(
+    ['psql', '-c', 'DROP SCHEMA protected; CREATE SCHEMA protected;'],
+    "ddl_command_start function does not block DDL when pgtle.enable_ddl_hook = 'off'");
+
+### 7. DDL is rejected when pgtle.enable_ddl_hook = 'require' and pg_tle is not installed
+$node->psql('postgres', 'CREATE DATABASE otherdb', on_error_die => 1);
+$node->append_conf('postgresql.conf', qq(pgtle.enable_ddl_hook = 'require'));
+$node->reload;
+
+$node->psql('otherdb', 'CREATE TABLE t(a int)', stderr => \$psql_err);
+like($psql_err, qr/ERROR:  "pgtle.enable_ddl_hook" feature is set to require but extension "pg_tle" is not installed/,
+    "DDL is rejected in databases without pg_tle when pgtle.enable_ddl_hook = 'require'");
+
+### 8. Functions in pgtle.ddl_hook_db_name are called for DDL in all databases
+$node->append_conf('postgresql.conf', qq(pgtle.ddl_hook_db_name = 'postgres'));
+$node->reload;
+
+$node->psql('otherdb', 'CREATE SCHEMA protected', on_error_die => 1);
+$node->psql('otherdb', 'DROP SCHEMA protected', stderr => \$psql_err);
+like($psql_err, qr/ERROR:  schema "protected" cannot be dropped/,
+    "ddl_command_start function in pgtle.ddl_hook_db_name blocks DDL in otherdb");
+$node->psql('postgres', q[SELECT database_name, user_name FROM ddl_audit WHERE command = 'CREATE SCHEMA protected'],
+    stdout => \$psql_out);
+is($psql_out, 'otherdb|' . $superuser,
+    "ddl_command_end function in pgtle.ddl_hook_db_name records DDL in otherdb");
+
+$node->psql('postgres', 'DROP SCHEMA protected', stderr => \$psql_err);
+like($psql_err, qr/ERROR:  schema "protected" cannot be dropped/,
+    "ddl_command_start function blocks DDL in pgtle.ddl_hook_db_name itself");
+
+### 9. Functions are called as the user running DDL in pgtle.ddl_hook_db_name itself
+$node->psql('postgres', q[
+    CREATE ROLE ddluser LOGIN;
+    GRANT CREATE ON DATABASE postgres TO ddluser;
+    GRANT INSERT ON ddl_audit TO ddluser;
+    CREATE TABLE ddl_hook_users(username text);
+    GRANT INSERT ON ddl_hook_users TO ddluser;
+    CREATE FUNCTION record_current_user(event text, command_tag text, command text, database_name text, user_name text) RETURNS void AS $$
+        INSERT INTO public.ddl_hook_users VALUES (current_user);
+    $$ LANGUAGE sql], on_error_die => 1);
+$node->psql('postgres', qq[SELECT pgtle.register_feature('record_current_user', 'ddl_command_end')], on_error_die => 1);
+
+$node->psql('postgres', 'CREATE SCHEMA ddluser_schema', extra_params => ['-U', 'ddluser'], on_error_die => 1);
+$node->psql('postgres', 'SELECT username FROM ddl_hook_users', stdout => \$psql_out);
+is($psql_out, 'ddluser',
+    "functions are called as the user running DDL in pgtle.ddl_hook_db_name itself");
+
+$node->stop;
+done_testing();
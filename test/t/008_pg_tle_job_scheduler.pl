@@ -0,0 +1,148 @@
+# Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+#
+#  Licensed under the Apache License, Version 2.0 (the "License").
+#  You may not use this file except in compliance with the License.
+#  You may obtain a copy of the License at
+#
+#      http://www.apache.org/licenses/LICENSE-2.0
+#
+#  Unless required by applicable law or agreed to in writing, software
+#  distributed under the License is distributed on an "AS IS" BASIS,
+#  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+#  See the License for the specific language governing permissions and
+#  limitations under the License.
+
+### 1. Scheduled job runs periodically
+### 2. Scheduled job runs as the user who scheduled it
+### 3. Errors raised by a scheduled job are recorded
+### 4. Jobs cannot be scheduled twice or with a schedule shorter than 1 second
+### 5. Unscheduled job no longer runs
+### 6. Only pgtle_admin can schedule jobs
+### 7. Jobs can only be unscheduled by the user they run as or a superuser
+### 8. Job fails once its user is no longer a member of pgtle_admin
+### 9. Job keeps running as its user after the user is renamed
+### 10. Jobs of a dropped user are removed
+
+use strict;
+use warnings;
+
+use PostgreSQL::Test::Cluster;
+use PostgreSQL::Test::Utils;
+use Test::More;
+
+my $psql_err = '';
+my $psql_out = '';
+my $node = PostgreSQL::Test::Cluster->new('job_scheduler_test');
+
+$node->init;
+$node->append_conf('postgresql.conf', qq(shared_preload_libraries = 'pg_tle'));
+$node->append_conf('postgresql.conf', qq(pgtle.enable_job_scheduler = on));
+$node->start;
+
+$node->psql('postgres', 'CREATE EXTENSION pg_tle', on_error_die => 1);
+$node->psql('postgres', q[
+    CREATE ROLE tle_admin;
+    GRANT pgtle_admin TO tle_admin;
+    CREATE ROLE tle_user;
+    CREATE ROLE other_admin;
+    GRANT pgtle_admin TO other_admin;
+    CREATE TABLE job_runs(run_at timestamptz, run_as text);
+    GRANT INSERT ON job_runs TO tle_admin;
+    CREATE FUNCTION record_run() RETURNS void AS $$
+        INSERT INTO public.job_runs VALUES (pg_catalog.now(), current_user);
+    $$ LANGUAGE sql;
+    CREATE FUNCTION fail() RETURNS void AS $$
+        BEGIN
+            RAISE EXCEPTION 'job failed on purpose';
+        END
+    $$ LANGUAGE plpgsql;], on_error_die => 1);
+
+### 1. Scheduled job runs periodically
+$node->psql('postgres', q[SET ROLE tle_admin; SELECT pgtle.schedule_job('record_run', '1 second', 'record_run')],
+    on_error_die => 1);
+ok($node->poll_query_until('postgres', 'SELECT count(*) >= 2 FROM job_runs'),
+    "scheduled job runs periodically");
+$node->psql('postgres', q[SELECT last_status, last_error IS NULL FROM pgtle.scheduled_job WHERE name = 'record_run'],
+    stdout => \$psql_out);
+is($psql_out, 'succeeded|t', "successful run is recorded");
+
+### 2. Scheduled job runs as the user who scheduled it
+$node->psql('postgres', 'SELECT DISTINCT run_as FROM job_runs', stdout => \$psql_out);
+is($psql_out, 'tle_admin', "scheduled job runs as tle_admin");
+
+### 3. Errors raised by a scheduled job are recorded
+$node->psql('postgres', q[SELECT pgtle.schedule_job('fail', '1 second', 'fail')], on_error_die => 1);
+ok($node->poll_query_until('postgres', q[SELECT last_status = 'failed' FROM pgtle.scheduled_job WHERE name = 'fail']),
+    "failed run is recorded");
+$node->psql('postgres', q[SELECT last_error FROM pgtle.scheduled_job WHERE name = 'fail'], stdout => \$psql_out);
+is($psql_out, 'job failed on purpose', "error raised by the scheduled job is recorded");
+
+### 4. Jobs cannot be scheduled twice or with a schedule shorter than 1 second
+$node->psql('postgres', q[SELECT pgtle.schedule_job('fail', '1 minute', 'fail')], stderr => \$psql_err);
+like($psql_err, qr/ERROR:  job "fail" already exists/, "job cannot be scheduled twice");
+$node->psql('postgres', q[SELECT pgtle.schedule_job('too_often', '100 milliseconds', 'fail')], stderr => \$psql_err);
+like($psql_err, qr/ERROR:  schedule must be at least 1 second/, "job cannot be scheduled more than once a second");
+
+### 5. Unscheduled job no longer runs
+$node->psql('postgres', q[SELECT pgtle.unschedule_job('record_run')], on_error_die => 1);
+# Let a run that started before the job was unscheduled finish
+sleep(1);
+$node->psql('postgres', 'TRUNCATE job_runs', on_error_die => 1);
+sleep(3);
+$node->psql('postgres', 'SELECT count(*) FROM job_runs', stdout => \$psql_out);
+is($psql_out, '0', "unscheduled job no longer runs");
+$node->psql('postgres', q[SELECT pgtle.unschedule_job('record_run')], stderr => \$psql_err);
+like($psql_err, qr/ERROR:  job "record_run" does not exist/, "job cannot be unscheduled twice");
+
+### 6. Only pgtle_admin can schedule jobs
+$node->psql('postgres', q[SET ROLE tle_user; SELECT pgtle.schedule_job('record_run', '1 second', 'record_run')],
+    stderr => \$psql_err);
+like($psql_err, qr/ERROR:  permission denied for function schedule_job/, "tle_user cannot schedule jobs");
+$node->psql('postgres', q[SET ROLE tle_admin; DELETE FROM pgtle.scheduled_job], stderr => \$psql_err);
+like($psql_err, qr/ERROR:  permission denied for table scheduled_job/, "tle_admin cannot modify jobs directly");
+
+### 7. Jobs can only be unscheduled by the user they run as or a superuser
+$node->psql('postgres', q[SET ROLE tle_admin; SELECT pgtle.schedule_job('owned', '1 hour', 'record_run')],
+    on_error_die => 1);
+$node->psql('postgres', q[SET ROLE other_admin; SELECT pgtle.unschedule_job('owned')], stderr => \$psql_err);
+like($psql_err, qr/ERROR:  must be owner of job "owned"/, "other_admin cannot unschedule a job of tle_admin");
+$node->psql('postgres', q[SET ROLE tle_admin; SELECT pgtle.unschedule_job('owned')], on_error_die => 1);
+$node->psql('postgres', q[SELECT pgtle.unschedule_job('fail')], on_error_die => 1);
+$node->psql('postgres', 'SELECT count(*) FROM pgtle.scheduled_job', stdout => \$psql_out);
+is($psql_out, '0', "jobs are unscheduled by their user and by a superuser");
+
+### 8. Job fails once its user is no longer a member of pgtle_admin
+$node->psql('postgres', q[SET ROLE tle_admin; SELECT pgtle.schedule_job('revoked', '1 second', 'record_run')],
+    on_error_die => 1);
+$node->psql('postgres', 'REVOKE pgtle_admin FROM tle_admin', on_error_die => 1);
+ok($node->poll_query_until('postgres', q[SELECT last_status = 'failed' FROM pgtle.scheduled_job WHERE name = 'revoked']),
+    "job of a user that is no longer a member of pgtle_admin fails");
+$node->psql('postgres', q[SELECT last_error FROM pgtle.scheduled_job WHERE name = 'revoked'], stdout => \$psql_out);
+is($psql_out, 'role "tle_admin" is not a member of "pgtle_admin"', "membership error is recorded");
+$node->psql('postgres', q[SELECT pgtle.unschedule_job('revoked')], on_error_die => 1);
+
+### 9. Job keeps running as its user after the user is renamed
+$node->psql('postgres', q[
+    CREATE ROLE renamed_admin;
+    GRANT pgtle_admin TO renamed_admin;
+    GRANT INSERT ON job_runs TO renamed_admin;
+    SET ROLE renamed_admin;
+    SELECT pgtle.schedule_job('renamed', '1 second', 'record_run');
+    RESET ROLE;
+    ALTER ROLE renamed_admin RENAME TO renamed_admin2;
+    TRUNCATE job_runs;], on_error_die => 1);
+ok($node->poll_query_until('postgres', q[SELECT count(*) >= 1 FROM job_runs WHERE run_as = 'renamed_admin2']),
+    "job runs as its user after the user is renamed");
+$node->psql('postgres', q[SELECT run_as, last_status FROM pgtle.scheduled_job WHERE name = 'renamed'],
+    stdout => \$psql_out);
+is($psql_out, 'renamed_admin2|succeeded', "renamed user is shown in run_as");
+
+### 10. Jobs of a dropped user are removed
+$node->psql('postgres', q[
+    REVOKE INSERT ON job_runs FROM renamed_admin2;
+    DROP ROLE renamed_admin2;], on_error_die => 1);
+ok($node->poll_query_until('postgres', q[SELECT count(*) = 0 FROM pgtle.scheduled_job WHERE name = 'renamed']),
+    "job of a dropped user is removed");
+
+$node->stop;
+done_testing();
@@ -0,0 +1,128 @@
+# Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+#
+#  Licensed under the Apache License, Version 2.0 (the "License").
+#  You may not use this file except in compliance with the License.
+#  You may obtain a copy of the License at
+#
+#      http://www.apache.org/licenses/LICENSE-2.0
+#
+#  Unless required by applicable law or agreed to in writing, software
+#  distributed under the License is distributed on an "AS IS" BASIS,
+#  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+#  See the License for the specific language governing permissions and
+#  limitations under the License.
+
+### 1. CREATE ROLE passes the attributes set by the statement
+### 2. CREATE USER passes login
+### 3. ALTER ROLE passes the existing attributes and the attributes set by the statement
+### 4. Indirect memberships are passed
+### 5. Functions that only take the original five arguments are still called
+### 6. Role attributes are passed to functions in pgtle.passcheck_db_name
+### 7. Policy requiring privileged roles to have expiring passwords
+
+use strict;
+use warnings;
+
+use PostgreSQL::Test::Cluster;
+use PostgreSQL::Test::Utils;
+use Test::More;
+
+my $psql_err = '';
+my $node = PostgreSQL::Test::Cluster->new('passcheck_role_attributes_test');
+
+$node->init;
+$node->append_conf('postgresql.conf', qq(shared_preload_libraries = 'pg_tle'));
+$node->append_conf('postgresql.conf', qq(pgtle.enable_password_check = 'on'));
+$node->start;
+
+$node->psql('postgres', 'CREATE EXTENSION pg_tle', on_error_die => 1);
+$node->psql('postgres', q[
+    CREATE ROLE admins;
+    CREATE ROLE ops IN ROLE admins;
+    CREATE ROLE existing LOGIN IN ROLE ops;
+    CREATE FUNCTION show_attributes(username text, password text, password_type pgtle.password_types, valid_until timestamptz, valid_null boolean, superuser boolean, login boolean, member_of text[]) RETURNS void AS $$
+        BEGIN
+            RAISE EXCEPTION 'superuser=% login=% member_of=%', superuser, login,
+                (SELECT pg_catalog.array_agg(m ORDER BY m) FROM pg_catalog.unnest(member_of) m);
+        END
+    $$ LANGUAGE plpgsql], on_error_die => 1);
+$node->psql('postgres', qq[SELECT pgtle.register_feature('show_attributes', 'passcheck')], on_error_die => 1);
+
+### 1. CREATE ROLE passes the attributes set by the statement
+$node->psql('postgres', q[CREATE ROLE newrole SUPERUSER LOGIN IN ROLE admins PASSWORD 'password'],
+    stderr => \$psql_err);
+like($psql_err, qr/ERROR:  superuser=t login=t member_of=\{admins\}/,
+    "CREATE ROLE passes the attributes set by the statement");
+
+$node->psql('postgres', q[CREATE ROLE newrole PASSWORD 'password'], stderr => \$psql_err);
+like($psql_err, qr/ERROR:  superuser=f login=f member_of=<NULL>/,
+    "CREATE ROLE passes the default attributes");
+
+### 2. CREATE USER passes login
+$node->psql('postgres', q[CREATE USER newrole PASSWORD 'password'], stderr => \$psql_err);
+like($psql_err, qr/ERROR:  superuser=f login=t member_of=<NULL>/,
+    "CREATE USER passes login");
+
+### 3. ALTER ROLE passes the existing attributes and the attributes set by the statement
+$node->psql('postgres', q[ALTER ROLE existing PASSWORD 'password'], stderr => \$psql_err);
+like($psql_err, qr/ERROR:  superuser=f login=t member_of=\{admins,ops\}/,
+    "ALTER ROLE passes the existing attributes");
+
+$node->psql('postgres', q[ALTER ROLE existing SUPERUSER NOLOGIN PASSWORD 'password'], stderr => \$psql_err);
+like($psql_err, qr/ERROR:  superuser=t login=f member_of=\{admins,ops\}/,
+    "ALTER ROLE passes the attributes set by the statement");
+
+### 4. Indirect memberships are passed
+$node->psql('postgres', q[CREATE ROLE newrole IN ROLE ops PASSWORD 'password'], stderr => \$psql_err);
+like($psql_err, qr/ERROR:  superuser=f login=f member_of=\{admins,ops\}/,
+    "indirect memberships are passed");
+
+### 5. Functions that only take the original five arguments are still called
+$node->psql('postgres', qq[SELECT pgtle.unregister_feature('show_attributes', 'passcheck')], on_error_die => 1);
+$node->psql('postgres', q[
+    CREATE FUNCTION block_all_passwords(username text, password text, password_type pgtle.password_types, valid_until timestamptz, valid_null boolean) RETURNS void AS $$
+        BEGIN
+            RAISE EXCEPTION 'block all set password attempts';
+        END
+    $$ LANGUAGE plpgsql], on_error_die => 1);
+$node->psql('postgres', qq[SELECT pgtle.register_feature('block_all_passwords', 'passcheck')], on_error_die => 1);
+
+$node->psql('postgres', q[ALTER ROLE existing PASSWORD 'password'], stderr => \$psql_err);
+like($psql_err, qr/ERROR:  block all set password attempts/,
+    "functions that take five arguments are still called");
+$node->psql('postgres', qq[SELECT pgtle.unregister_feature('block_all_passwords', 'passcheck')], on_error_die => 1);
+
+### 6. Role attributes are passed to functions in pgtle.passcheck_db_name
+$node->psql('postgres', 'CREATE DATABASE otherdb', on_error_die => 1);
+$node->psql('postgres', qq[SELECT pgtle.register_feature('show_attributes', 'passcheck')], on_error_die => 1);
+$node->append_conf('postgresql.conf', qq(pgtle.passcheck_db_name = 'postgres'));
+$node->reload;
+
+$node->psql('otherdb', q[CREATE ROLE newrole SUPERUSER IN ROLE ops PASSWORD 'password'], stderr => \$psql_err);
+like($psql_err, qr/ERROR:  superuser=t login=f member_of=\{admins,ops\}/,
+    "role attributes are passed to functions in pgtle.passcheck_db_name");
+$node->psql('postgres', qq[SELECT pgtle.unregister_feature('show_attributes', 'passcheck')], on_error_die => 1);
+
+### 7. Policy requiring privileged roles to have expiring passwords
+$node->psql('postgres', q[
+    CREATE FUNCTION privileged_password_expiry(username text, password text, password_type pgtle.password_types, valid_until timestamptz, valid_null boolean, superuser boolean, login boolean, member_of text[]) RETURNS void AS $$
+        BEGIN
+            IF (superuser OR 'admins' = ANY(member_of)) AND valid_null THEN
+                RAISE EXCEPTION 'privileged roles must have expiring passwords';
+            END IF;
+        END
+    $$ LANGUAGE plpgsql], on_error_die => 1);
+$node->psql('postgres', qq[SELECT pgtle.register_feature('privileged_password_expiry', 'passcheck')], on_error_die => 1);
+
+$node->psql('otherdb', q[ALTER ROLE existing PASSWORD 'password'], stderr => \$psql_err);
+like($psql_err, qr/ERROR:  privileged roles must have expiring passwords/,
+    "privileged role cannot set a password without expiry");
+$node->command_ok(
+    ['psql', '-c', q[ALTER ROLE existing PASSWORD 'password' VALID UNTIL '2100-01-01']],
+    "privileged role can set an expiring password");
+$node->command_ok(
+    ['psql', '-c', q[CREATE ROLE unprivileged PASSWORD 'password']],
+    "unprivileged role can set a password without expiry");
+
+$node->stop;
+done_testing();
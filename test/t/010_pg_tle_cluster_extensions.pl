@@ -0,0 +1,94 @@
+# Copyright Amazon.com, Inc. or its affiliates. All Rights Reserved.
+#
+#  Licensed under the Apache License, Version 2.0 (the "License").
+#  You may not use this file except in compliance with the License.
+#  You may obtain a copy of the License at
+#
+#      http://www.apache.org/licenses/LICENSE-2.0
+#
+#  Unless required by applicable law or agreed to in writing, software
+#  distributed under the License is distributed on an "AS IS" BASIS,
+#  WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+#  See the License for the specific language governing permissions and
+#  limitations under the License.
+
+### 1. Installed and created TLEs in all databases are reported
+### 2. Updated versions are reported
+### 3. Dropped extensions and databases are no longer reported
+### 4. Only pgtle_admin can call cluster_extensions
+
+use strict;
+use warnings;
+
+use PostgreSQL::Test::Cluster;
+use PostgreSQL::Test::Utils;
+use Test::More;
+
+my $psql_err = '';
+my $psql_out = '';
+my $node = PostgreSQL::Test::Cluster->new('cluster_extensions_test');
+
+$node->init;
+$node->append_conf('postgresql.conf', qq(shared_preload_libraries = 'pg_tle'));
+$node->append_conf('postgresql.conf', qq(pgtle.enable_cluster_extensions = on));
+$node->append_conf('postgresql.conf', qq(pgtle.cluster_extensions_refresh_interval = 1));
+$node->start;
+
+my $install_sql = q[
+    SELECT pgtle.install_extension('test_ext', '1.0', 'Test TLE',
+    $_pgtle_$
+        CREATE FUNCTION test_func() RETURNS INT AS $$ SELECT 1 $$ LANGUAGE SQL;
+    $_pgtle_$);
+    SELECT pgtle.install_update_path('test_ext', '1.0', '1.1',
+    $_pgtle_$
+        CREATE OR REPLACE FUNCTION test_func() RETURNS INT AS $$ SELECT 2 $$ LANGUAGE SQL;
+    $_pgtle_$);];
+
+$node->psql('postgres', 'CREATE DATABASE db1', on_error_die => 1);
+$node->psql('postgres', 'CREATE DATABASE db2', on_error_die => 1);
+$node->psql('postgres', 'CREATE DATABASE db_without_pg_tle', on_error_die => 1);
+$node->psql('postgres', 'CREATE EXTENSION pg_tle', on_error_die => 1);
+foreach my $db ('db1', 'db2')
+{
+    $node->psql($db, 'CREATE EXTENSION pg_tle', on_error_die => 1);
+    $node->psql($db, $install_sql, on_error_die => 1);
+}
+$node->psql('db1', 'CREATE EXTENSION test_ext', on_error_die => 1);
+
+my $cluster_extensions_sql = q[
+    SELECT pg_catalog.string_agg(database || ':' || name || ':' || coalesce(version, '-'), ',' ORDER BY database)
+    FROM pgtle.cluster_extensions()];
+
+### 1. Installed and created TLEs in all databases are reported
+ok($node->poll_query_until('postgres', $cluster_extensions_sql, 'db1:test_ext:1.0,db2:test_ext:-'),
+    "installed and created TLEs in all databases are reported");
+
+### 2. Updated versions are reported
+$node->psql('db1', q[ALTER EXTENSION test_ext UPDATE TO '1.1'], on_error_die => 1);
+$node->psql('db2', 'CREATE EXTENSION test_ext', on_error_die => 1);
+ok($node->poll_query_until('postgres', $cluster_extensions_sql, 'db1:test_ext:1.1,db2:test_ext:1.0'),
+    "updated versions are reported");
+
+### 3. Dropped extensions and databases are no longer reported
+$node->psql('db1', 'DROP EXTENSION test_ext', on_error_die => 1);
+$node->psql('db1', q[SELECT pgtle.uninstall_extension('test_ext')], on_error_die => 1);
+$node->psql('postgres', 'DROP DATABASE db2', on_error_die => 1);
+ok($node->poll_query_until('postgres',
+    q[SELECT count(*) FROM pgtle.cluster_extensions()], '0'),
+    "dropped extensions and databases are no longer reported");
+
+### 4. Only pgtle_admin can call cluster_extensions
+$node->psql('postgres', q[
+    CREATE ROLE tle_admin;
+    GRANT pgtle_admin TO tle_admin;
+    CREATE ROLE tle_user;], on_error_die => 1);
+$node->psql('postgres', q[SET ROLE tle_admin; SELECT count(*) FROM pgtle.cluster_extensions()],
+    stdout => \$psql_out, on_error_die => 1);
+is($psql_out, '0', "tle_admin can call cluster_extensions");
+$node->psql('postgres', q[SET ROLE tle_user; SELECT count(*) FROM pgtle.cluster_extensions()],
+    stderr => \$psql_err);
+like($psql_err, qr/ERROR:  permission denied for function cluster_extensions/,
+    "tle_user cannot call cluster_extensions");
+
+$node->stop;
+done_testing();